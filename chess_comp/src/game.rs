@@ -1,7 +1,12 @@
-use std::collections::VecDeque;
+use std::{collections::VecDeque, fmt};
 
 use bitflags::bitflags;
 
+use crate::{
+    move_generation::{Move, MoveKind},
+    zobrist::Keys,
+};
+
 pub fn index_to_position(index: usize) -> String {
     let col = index % 8;
     let col_char = (b'a' + col as u8) as char;
@@ -51,13 +56,80 @@ pub fn position_to_bit(position: &str) -> Result<u64, String> {
     }
 }
 
+/// Why a FEN string couldn't be read.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FenError {
+    MissingField(&'static str),
+    UnexpectedField(String),
+    WrongRankCount(usize),
+    BadRankLength {
+        rank: usize,
+        length: usize,
+    },
+    UnknownPiece(char),
+    PawnOnBackRank(usize),
+    InvalidActiveColor(String),
+    InvalidCastlingRights(String),
+    InvalidEnPassant(String, String),
+    /// No pawn can have just skipped the square: it's occupied, or no enemy
+    /// pawn stands just past it.
+    ImpossibleEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "Missing {field}"),
+            FenError::UnexpectedField(field) => {
+                write!(f, "Unexpected field after fullmove number: '{field}'")
+            }
+            FenError::WrongRankCount(count) => write!(f, "Expected 8 ranks, found {count}"),
+            FenError::BadRankLength { rank, length } => {
+                write!(f, "Rank {rank} covers {length} squares instead of 8")
+            }
+            FenError::UnknownPiece(ch) => write!(f, "Unknown piece: '{ch}'"),
+            FenError::PawnOnBackRank(rank) => write!(f, "Pawn on rank {rank}"),
+            FenError::InvalidActiveColor(color) => write!(f, "Invalid active color: '{color}'"),
+            FenError::InvalidCastlingRights(rights) => {
+                write!(f, "Invalid castling rights: '{rights}'")
+            }
+            FenError::InvalidEnPassant(square, reason) => {
+                write!(f, "Invalid en passant square '{square}': {reason}")
+            }
+            FenError::ImpossibleEnPassant(square) => {
+                write!(
+                    f,
+                    "No pawn can have just skipped en passant square '{square}'"
+                )
+            }
+            FenError::InvalidHalfmoveClock(clock) => write!(f, "Invalid halfmove clock: '{clock}'"),
+            FenError::InvalidFullmoveNumber(number) => {
+                write!(f, "Invalid fullmove number: '{number}'")
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Color {
     White,
     Black,
 }
 
-#[derive(Debug, PartialEq)]
+impl Color {
+    pub fn opposite(self) -> Self {
+        match self {
+            Color::White => Color::Black,
+            Color::Black => Color::White,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum PieceType {
     Pawn,
     Rook,
@@ -67,7 +139,7 @@ pub enum PieceType {
     King,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct Piece {
     position: u64,
     color: Color,
@@ -75,32 +147,42 @@ pub struct Piece {
 }
 
 impl Piece {
-    fn to_string(&self) -> String {
-        let mut result = "".to_string();
-        result.push_str(match self.piece_type {
-            PieceType::Pawn => "p ",
-            PieceType::Rook => "r ",
-            PieceType::Knight => "n ",
-            PieceType::Bishop => "b ",
-            PieceType::Queen => "q ",
-            PieceType::King => "k ",
-        });
+    pub fn piece_type(&self) -> PieceType {
+        self.piece_type
+    }
+
+    /// The piece's letter in FEN, upper case for white.
+    fn symbol(&self) -> char {
+        let symbol = match self.piece_type {
+            PieceType::Pawn => 'p',
+            PieceType::Rook => 'r',
+            PieceType::Knight => 'n',
+            PieceType::Bishop => 'b',
+            PieceType::Queen => 'q',
+            PieceType::King => 'k',
+        };
 
-        if self.color == Color::White {
-            result.make_ascii_uppercase();
+        match self.color {
+            Color::White => symbol.to_ascii_uppercase(),
+            Color::Black => symbol,
         }
+    }
+}
 
-        result
+impl fmt::Display for Piece {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ", self.symbol())
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, PartialEq, Copy, Clone)]
 pub enum Square {
     Empty,
     Occupied(usize),
 }
 
 bitflags! {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
     pub struct CastlingRights: u8 {
         const NONE = 0;
         const WHITE_KING_SIDE = 1 << 0;
@@ -111,6 +193,21 @@ bitflags! {
     }
 }
 
+/// What `Game::make_move` changed that can't be worked out from the move
+/// itself, so that `Game::unmake_move` can put it back.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct Undo {
+    mv: Move,
+    captured: Option<usize>,
+    castling_rights: CastlingRights,
+    en_passant: Option<u64>,
+    halfmove_clock: usize,
+    hash: u64,
+}
+
+/// Captured pieces stay in `pieces`, so that the indices in `squares` stay
+/// valid, with an empty `position` until a move is undone.
+#[derive(Debug, PartialEq, Clone)]
 pub struct Game {
     pieces: Vec<Piece>,
     squares: Vec<Square>,
@@ -119,9 +216,13 @@ pub struct Game {
     en_passant: Option<u64>,
     halfmove_clock: usize,
     fullmove_number: usize,
+    history: Vec<Undo>,
+    /// The Zobrist hash of the position, kept up to date as moves are made.
+    hash: u64,
 }
 
 impl Game {
+    #[cfg(test)]
     fn push_piece_and_square(
         &mut self,
         position: usize,
@@ -130,47 +231,198 @@ impl Game {
         index: &mut usize,
     ) {
         self.pieces.push(Piece {
-            position: (1 as u64) << position,
-            color: color,
-            piece_type: piece_type,
+            position: 1u64 << position,
+            color,
+            piece_type,
         });
         self.squares.push(Square::Occupied(*index));
 
         *index += 1;
     }
 
+    #[cfg(test)]
     fn push_empty_square(&mut self) {
         self.squares.push(Square::Empty);
     }
 
     pub fn initalize() -> Self {
         Self::read_FEN("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1")
+            .expect("The initial position is valid")
     }
 
-    pub fn to_string(&self) -> String {
-        let mut board = "".to_string();
-        let mut temp = "".to_string();
+    pub fn active_color(&self) -> Color {
+        self.active_color
+    }
 
-        for (i, square) in self.squares.iter().enumerate() {
-            match square {
-                Square::Empty => temp.push_str(&index_to_position(i)),
-                Square::Occupied(idx) => temp.push_str(&self.pieces[*idx].to_string()),
-            }
+    pub fn castling_rights(&self) -> CastlingRights {
+        self.castling_rights
+    }
 
-            if (i + 1) % 8 == 0 {
-                temp.push_str("\n");
-                board.insert_str(0, &temp);
-                temp.clear();
-            } else {
-                temp.push(' ');
-            }
+    /// The square a pawn can capture en passant, as a bitboard.
+    pub fn en_passant(&self) -> Option<u64> {
+        self.en_passant
+    }
+
+    /// The Zobrist hash of the position, which is the same for the same
+    /// pieces, side to move, castling rights and en passant square however
+    /// the position was reached.
+    pub fn hash(&self) -> u64 {
+        self.hash
+    }
+
+    /// Works out the hash from scratch, rather than from the last move.
+    fn compute_hash(&self) -> u64 {
+        let keys = Keys::get();
+        let mut hash = keys.castling(self.castling_rights) ^ keys.en_passant(self.en_passant);
+
+        for piece in self.pieces.iter().filter(|piece| piece.position != 0) {
+            let square = piece.position.trailing_zeros() as usize;
+            hash ^= keys.piece(piece.color, piece.piece_type, square);
+        }
+        if self.active_color == Color::Black {
+            hash ^= keys.black_to_move();
         }
 
-        board
+        hash
+    }
+
+    pub fn piece_at(&self, square: usize) -> Option<&Piece> {
+        match self.squares[square] {
+            Square::Empty => None,
+            Square::Occupied(idx) => Some(&self.pieces[idx]),
+        }
     }
 
+    /// Bitboards of every piece on the board, by color and type.
+    pub fn bitboards(&self) -> Bitboards {
+        let mut bitboards = Bitboards::default();
+
+        for piece in &self.pieces {
+            bitboards.pieces[piece.color as usize][piece.piece_type as usize] |= piece.position;
+        }
+
+        bitboards
+    }
+
+    /// Plays `mv`, which has to be one of `legal_moves` (or at least
+    /// `pseudo_legal_moves`) for the position, and remembers how to take it
+    /// back.
+    pub fn make_move(&mut self, mv: Move) {
+        let captured_square = capture_square(mv);
+        let captured = match self.squares[captured_square] {
+            Square::Occupied(idx) if mv.is_capture() => Some(idx),
+            _ => None,
+        };
+        let Square::Occupied(moving) = self.squares[mv.from] else {
+            panic!("No piece to move on {}", index_to_position(mv.from));
+        };
+        let is_pawn_move = self.pieces[moving].piece_type == PieceType::Pawn;
+
+        self.history.push(Undo {
+            mv,
+            captured,
+            castling_rights: self.castling_rights,
+            en_passant: self.en_passant,
+            halfmove_clock: self.halfmove_clock,
+            hash: self.hash,
+        });
+
+        let keys = Keys::get();
+        self.hash ^= keys.castling(self.castling_rights) ^ keys.en_passant(self.en_passant);
+
+        if let Some(idx) = captured {
+            let piece = &mut self.pieces[idx];
+            self.hash ^= keys.piece(piece.color, piece.piece_type, captured_square);
+            piece.position = 0;
+            self.squares[captured_square] = Square::Empty;
+        }
+
+        self.move_piece(mv.from, mv.to);
+        if let Some(promotion) = mv.promotion {
+            let piece = &mut self.pieces[moving];
+            self.hash ^= keys.piece(piece.color, piece.piece_type, mv.to)
+                ^ keys.piece(piece.color, promotion, mv.to);
+            piece.piece_type = promotion;
+        }
+        if let Some((rook_from, rook_to)) = castling_rook(mv) {
+            self.move_piece(rook_from, rook_to);
+        }
+
+        for square in [mv.from, mv.to] {
+            self.castling_rights.remove(castling_rights_lost(square));
+        }
+
+        self.en_passant = match mv.kind {
+            MoveKind::DoublePawnPush => Some(1 << ((mv.from + mv.to) / 2)),
+            _ => None,
+        };
+        self.hash ^= keys.castling(self.castling_rights)
+            ^ keys.en_passant(self.en_passant)
+            ^ keys.black_to_move();
+
+        if captured.is_some() || is_pawn_move {
+            self.halfmove_clock = 0;
+        } else {
+            self.halfmove_clock += 1;
+        }
+
+        if self.active_color == Color::Black {
+            self.fullmove_number += 1;
+        }
+        self.active_color = self.active_color.opposite();
+    }
+
+    /// Takes back the last move made, returning it, or `None` if there is
+    /// nothing to take back.
+    pub fn unmake_move(&mut self) -> Option<Move> {
+        let undo = self.history.pop()?;
+        let mv = undo.mv;
+
+        self.active_color = self.active_color.opposite();
+        if self.active_color == Color::Black {
+            self.fullmove_number -= 1;
+        }
+
+        self.move_piece(mv.to, mv.from);
+        if mv.promotion.is_some()
+            && let Square::Occupied(idx) = self.squares[mv.from]
+        {
+            self.pieces[idx].piece_type = PieceType::Pawn;
+        }
+        if let Some((rook_from, rook_to)) = castling_rook(mv) {
+            self.move_piece(rook_to, rook_from);
+        }
+
+        if let Some(idx) = undo.captured {
+            let square = capture_square(mv);
+            self.pieces[idx].position = 1 << square;
+            self.squares[square] = Square::Occupied(idx);
+        }
+
+        self.castling_rights = undo.castling_rights;
+        self.en_passant = undo.en_passant;
+        self.halfmove_clock = undo.halfmove_clock;
+        self.hash = undo.hash;
+
+        Some(mv)
+    }
+
+    fn move_piece(&mut self, from: usize, to: usize) {
+        let square = std::mem::replace(&mut self.squares[from], Square::Empty);
+        if let Square::Occupied(idx) = square {
+            let piece = &mut self.pieces[idx];
+            let keys = Keys::get();
+            self.hash ^= keys.piece(piece.color, piece.piece_type, from)
+                ^ keys.piece(piece.color, piece.piece_type, to);
+            piece.position = 1 << to;
+        }
+        self.squares[to] = square;
+    }
+
+    /// Parses a position in Forsyth-Edwards Notation. The halfmove clock and
+    /// fullmove number can be left off, and default to 0 and 1.
     #[allow(non_snake_case)]
-    pub fn read_FEN(fen: &str) -> Self {
+    pub fn read_FEN(fen: &str) -> Result<Self, FenError> {
         let mut game = Game {
             pieces: vec![],
             squares: vec![],
@@ -179,22 +431,28 @@ impl Game {
             en_passant: None,
             halfmove_clock: 0,
             fullmove_number: 1,
+            history: vec![],
+            hash: 0,
         };
 
-        let mut split = fen.split(" ");
-        let positions = split.next().unwrap();
+        let fields: Vec<&str> = fen.split_whitespace().collect();
+        if let Some(extra) = fields.get(6) {
+            return Err(FenError::UnexpectedField((*extra).to_owned()));
+        }
+        let mut split = fields.into_iter();
+        let positions = split.next().unwrap_or_default();
+
+        let rows: Vec<&str> = positions.split('/').collect();
+        if rows.len() != 8 {
+            return Err(FenError::WrongRankCount(rows.len()));
+        }
 
-        let mut piece_index = 0;
-        let mut piece_position = 64;
         let mut dequeu_squares = VecDeque::new();
 
-        for row in positions.splitn(8, |ch| ch == '/') {
-            piece_position -= 8;
-            let (pieces, squares) = parse_row(row, piece_index, piece_position);
-            for p in pieces {
-                game.pieces.push(p);
-                piece_index += 1;
-            }
+        for (i, row) in rows.iter().enumerate() {
+            let rank = 8 - i;
+            let (pieces, squares) = parse_row(row, rank, game.pieces.len(), (rank - 1) * 8)?;
+            game.pieces.extend(pieces);
             for s in squares {
                 dequeu_squares.push_front(s);
             }
@@ -202,60 +460,239 @@ impl Game {
 
         game.squares = Vec::from(dequeu_squares);
 
-        let color_to_move = split.next().unwrap();
+        let color_to_move = split.next().ok_or(FenError::MissingField("active color"))?;
         game.active_color = match color_to_move {
             "w" => Color::White,
             "b" => Color::Black,
-            _ => panic!("Unknown color: {color_to_move}"),
+            _ => return Err(FenError::InvalidActiveColor(color_to_move.to_owned())),
         };
 
-        let castling_rights = split.next().unwrap();
+        let castling_rights = split
+            .next()
+            .ok_or(FenError::MissingField("castling rights"))?;
         let mut castling = CastlingRights::NONE;
-        for ch in castling_rights.chars() {
-            match ch {
-                'K' => castling |= CastlingRights::WHITE_KING_SIDE,
-                'Q' => castling |= CastlingRights::WHITE_QUEEN_SIDE,
-                'k' => castling |= CastlingRights::BLACK_KING_SIDE,
-                'q' => castling |= CastlingRights::BLACK_QUEEN_SIDE,
-                '-' => (),
-                _ => panic!("Invalid character in castling rights '{ch}'"),
+        if castling_rights != "-" {
+            for ch in castling_rights.chars() {
+                let right = match ch {
+                    'K' => CastlingRights::WHITE_KING_SIDE,
+                    'Q' => CastlingRights::WHITE_QUEEN_SIDE,
+                    'k' => CastlingRights::BLACK_KING_SIDE,
+                    'q' => CastlingRights::BLACK_QUEEN_SIDE,
+                    _ => return Err(FenError::InvalidCastlingRights(castling_rights.to_owned())),
+                };
+                if castling.contains(right) {
+                    return Err(FenError::InvalidCastlingRights(castling_rights.to_owned()));
+                }
+                castling |= right;
             }
         }
         game.castling_rights = castling;
 
-        let en_passant = split.next().unwrap();
-        match en_passant {
-            "-" => game.en_passant = None,
-            s => {
-                let pos = position_to_bit(s).unwrap_or_else(|e| panic!("{e}"));
-                game.en_passant = Some(pos);
+        let en_passant = split
+            .next()
+            .ok_or(FenError::MissingField("en passant square"))?;
+        if en_passant != "-" {
+            let invalid = |reason| FenError::InvalidEnPassant(en_passant.to_owned(), reason);
+            let pos = position_to_bit(en_passant).map_err(invalid)?;
+
+            // the square a pawn of the side that just moved skipped over
+            let rank = pos.trailing_zeros() / 8 + 1;
+            let expected_rank = match game.active_color {
+                Color::White => 6,
+                Color::Black => 3,
+            };
+            if rank != expected_rank {
+                return Err(invalid(format!(
+                    "must be on rank {expected_rank} when {} is to move",
+                    color_to_move
+                )));
+            }
+
+            // the pawn that skipped it stands just past it, with nothing left
+            // behind on the square itself
+            let square = pos.trailing_zeros() as usize;
+            let pawn_square = match game.active_color {
+                Color::White => square - 8,
+                Color::Black => square + 8,
+            };
+            let skipped_by_pawn = game.piece_at(pawn_square).is_some_and(|piece| {
+                piece.piece_type == PieceType::Pawn && piece.color != game.active_color
+            });
+            if game.piece_at(square).is_some() || !skipped_by_pawn {
+                return Err(FenError::ImpossibleEnPassant(en_passant.to_owned()));
             }
+
+            game.en_passant = Some(pos);
         }
 
-        let halfmove_clock = split.next().unwrap();
-        game.halfmove_clock = halfmove_clock
-            .parse()
-            .unwrap_or_else(|_| panic!("Invalid halfmove: {}", halfmove_clock));
+        if let Some(halfmove_clock) = split.next() {
+            game.halfmove_clock = halfmove_clock
+                .parse()
+                .map_err(|_| FenError::InvalidHalfmoveClock(halfmove_clock.to_owned()))?;
+        }
 
-        let full_move_number = split.next().unwrap();
-        game.fullmove_number = full_move_number
-            .parse()
-            .unwrap_or_else(|_| panic!("Invalid ful move number: {}", full_move_number));
+        if let Some(full_move_number) = split.next() {
+            game.fullmove_number = full_move_number
+                .parse()
+                .ok()
+                .filter(|&number| number > 0)
+                .ok_or_else(|| FenError::InvalidFullmoveNumber(full_move_number.to_owned()))?;
+        }
 
-        game
+        game.hash = game.compute_hash();
+
+        Ok(game)
+    }
+
+    /// The position in Forsyth-Edwards Notation.
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+
+        for rank in (0..8).rev() {
+            let mut empty = 0;
+
+            for square in &self.squares[rank * 8..rank * 8 + 8] {
+                match square {
+                    Square::Empty => empty += 1,
+                    Square::Occupied(idx) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(self.pieces[*idx].symbol());
+                    }
+                }
+            }
+
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+
+        fen.push_str(match self.active_color {
+            Color::White => " w ",
+            Color::Black => " b ",
+        });
+
+        if self.castling_rights.is_empty() {
+            fen.push('-');
+        }
+        for (right, ch) in [
+            (CastlingRights::WHITE_KING_SIDE, 'K'),
+            (CastlingRights::WHITE_QUEEN_SIDE, 'Q'),
+            (CastlingRights::BLACK_KING_SIDE, 'k'),
+            (CastlingRights::BLACK_QUEEN_SIDE, 'q'),
+        ] {
+            if self.castling_rights.contains(right) {
+                fen.push(ch);
+            }
+        }
+
+        let en_passant = match self.en_passant {
+            Some(pos) => index_to_position(pos.trailing_zeros() as usize),
+            None => "-".to_owned(),
+        };
+
+        format!(
+            "{fen} {en_passant} {} {}",
+            self.halfmove_clock, self.fullmove_number
+        )
+    }
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut board = "".to_string();
+        let mut temp = "".to_string();
+
+        for (i, square) in self.squares.iter().enumerate() {
+            match square {
+                Square::Empty => temp.push_str(&index_to_position(i)),
+                Square::Occupied(idx) => temp.push_str(&self.pieces[*idx].to_string()),
+            }
+
+            if (i + 1) % 8 == 0 {
+                temp.push('\n');
+                board.insert_str(0, &temp);
+                temp.clear();
+            } else {
+                temp.push(' ');
+            }
+        }
+
+        write!(f, "{board}")
     }
 }
 
+/// The square of the piece `mv` captures, which for en passant is beside
+/// the square the pawn moves to.
+fn capture_square(mv: Move) -> usize {
+    match mv.kind {
+        MoveKind::EnPassant => mv.from / 8 * 8 + mv.to % 8,
+        _ => mv.to,
+    }
+}
+
+/// Where the rook moves from and to when `mv` castles.
+fn castling_rook(mv: Move) -> Option<(usize, usize)> {
+    match mv.kind {
+        MoveKind::KingSideCastle => Some((mv.to + 1, mv.to - 1)),
+        MoveKind::QueenSideCastle => Some((mv.to - 2, mv.to + 1)),
+        _ => None,
+    }
+}
+
+/// The castling rights lost once a piece moves from or to `square`.
+fn castling_rights_lost(square: usize) -> CastlingRights {
+    match square {
+        0 => CastlingRights::WHITE_QUEEN_SIDE,
+        4 => CastlingRights::WHITE_KING_SIDE | CastlingRights::WHITE_QUEEN_SIDE,
+        7 => CastlingRights::WHITE_KING_SIDE,
+        56 => CastlingRights::BLACK_QUEEN_SIDE,
+        60 => CastlingRights::BLACK_KING_SIDE | CastlingRights::BLACK_QUEEN_SIDE,
+        63 => CastlingRights::BLACK_KING_SIDE,
+        _ => CastlingRights::NONE,
+    }
+}
+
+/// A snapshot of where the pieces are, one bitboard per color and type.
+#[derive(Debug, Default, PartialEq, Clone, Copy)]
+pub struct Bitboards {
+    pieces: [[u64; 6]; 2],
+}
+
+impl Bitboards {
+    pub fn of(&self, color: Color, piece_type: PieceType) -> u64 {
+        self.pieces[color as usize][piece_type as usize]
+    }
+
+    pub fn color(&self, color: Color) -> u64 {
+        self.pieces[color as usize]
+            .iter()
+            .fold(0, |all, pieces| all | pieces)
+    }
+
+    pub fn occupancy(&self) -> u64 {
+        self.color(Color::White) | self.color(Color::Black)
+    }
+}
+
+/// Parses one rank of the piece placement, numbering pieces from
+/// `piece_index` and squares from `piece_position`, its `a` file.
 fn parse_row(
     row: &str,
+    rank: usize,
     mut piece_index: usize,
     mut piece_position: usize,
-) -> (Vec<Piece>, Vec<Square>) {
+) -> Result<(Vec<Piece>, Vec<Square>), FenError> {
     let mut pieces = Vec::new();
     let mut squares = VecDeque::new();
 
     for ch in row.chars() {
-        if let Some(num) = ch.to_digit(10) {
+        if let Some(num) = ch.to_digit(10).filter(|num| (1..=8).contains(num)) {
             for _ in 0..num {
                 squares.push_front(Square::Empty);
                 piece_position += 1;
@@ -276,22 +713,34 @@ fn parse_row(
             'q' => PieceType::Queen,
             'k' => PieceType::King,
             'p' => PieceType::Pawn,
-            _ => panic!("Invalid piece char: {ch}"),
+            _ => return Err(FenError::UnknownPiece(ch)),
         };
+        if piece_type == PieceType::Pawn && (rank == 1 || rank == 8) {
+            return Err(FenError::PawnOnBackRank(rank));
+        }
 
-        let piece = Piece {
-            color,
-            piece_type,
-            position: 1u64 << piece_position,
-        };
-        pieces.push(piece);
+        // a rank that's too long is reported below
+        if squares.len() < 8 {
+            pieces.push(Piece {
+                color,
+                piece_type,
+                position: 1u64 << piece_position,
+            });
+        }
         squares.push_front(Square::Occupied(piece_index));
 
         piece_index += 1;
         piece_position += 1;
     }
 
-    (pieces, Vec::from(squares))
+    if squares.len() != 8 {
+        return Err(FenError::BadRankLength {
+            rank,
+            length: squares.len(),
+        });
+    }
+
+    Ok((pieces, Vec::from(squares)))
 }
 
 #[cfg(test)]
@@ -307,6 +756,8 @@ mod tests {
             en_passant: None,
             halfmove_clock: 0,
             fullmove_number: 1,
+            history: vec![],
+            hash: 0,
         };
         let mut piece_index = 0;
 
@@ -335,7 +786,7 @@ mod tests {
         }
 
         let offset = 56;
-        game.push_piece_and_square(0 + offset, color, PieceType::Rook, &mut piece_index);
+        game.push_piece_and_square(offset, color, PieceType::Rook, &mut piece_index);
         game.push_piece_and_square(1 + offset, color, PieceType::Knight, &mut piece_index);
         game.push_piece_and_square(2 + offset, color, PieceType::Bishop, &mut piece_index);
         game.push_piece_and_square(3 + offset, color, PieceType::Queen, &mut piece_index);
@@ -369,23 +820,26 @@ mod tests {
 
     #[test]
     fn read_fen_black_active() {
-        let game = Game::read_FEN("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - - 1 2");
+        let game =
+            Game::read_FEN("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - - 1 2").unwrap();
         assert_eq!(game.active_color, Color::Black);
     }
 
     #[test]
     fn read_fen_no_castling() {
-        let game = Game::read_FEN("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - - 1 2");
+        let game =
+            Game::read_FEN("rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - - 1 2").unwrap();
         assert_eq!(game.castling_rights.bits(), CastlingRights::NONE.bits());
     }
 
     #[test]
     fn read_fen_en_passant_allowed() {
-        let en_passant_square = "g7";
+        let en_passant_square = "e3";
         let game = Game::read_FEN(&format!(
             "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq {} 1 2",
             en_passant_square
-        ));
+        ))
+        .unwrap();
         assert_eq!(
             game.en_passant,
             Some(position_to_bit(en_passant_square).unwrap())
@@ -394,7 +848,8 @@ mod tests {
 
     #[test]
     fn read_fen_moveclocks() {
-        let game = Game::read_FEN("rnbqkbnr/pp1ppppp/7P/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - g7 1 2");
+        let game = Game::read_FEN("rnbqkbnr/pp1ppppp/7P/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b - e3 1 2")
+            .unwrap();
         assert_eq!(game.halfmove_clock, 1);
         assert_eq!(game.fullmove_number, 2);
     }
@@ -405,16 +860,19 @@ mod tests {
         let right_chars = ["K", "Q", "k", "q"];
         for i in 0..(u8::pow(2, 4)) {
             let bitflag_rights = CastlingRights::from_bits(i).unwrap();
-            for j in 0..4 {
+            for (j, right_char) in right_chars.iter().enumerate() {
                 if (i >> j) & 1 != 0 {
-                    rights.push_str(right_chars[j]);
+                    rights.push_str(right_char);
                 }
             }
+            if rights.is_empty() {
+                rights.push('-');
+            }
             let fen = format!(
                 "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w {} - 0 1",
                 rights
             );
-            let game = Game::read_FEN(&fen);
+            let game = Game::read_FEN(&fen).unwrap();
             assert_eq!(
                 game.castling_rights.bits(),
                 bitflag_rights.bits(),
@@ -425,4 +883,325 @@ mod tests {
             rights.clear();
         }
     }
+
+    fn play(game: &mut Game, mv: &str) {
+        let mv = game
+            .legal_moves()
+            .into_iter()
+            .find(|legal| legal.to_string() == mv)
+            .unwrap_or_else(|| panic!("{mv} isn't legal"));
+        game.make_move(mv);
+    }
+
+    /// Checks `game` is the position `fen` describes, including the piece
+    /// list and squares agreeing with each other.
+    fn assert_position(game: &Game, fen: &str) {
+        let expected = Game::read_FEN(fen).unwrap();
+        assert_eq!(game.to_string(), expected.to_string());
+        assert_eq!(game.bitboards(), expected.bitboards());
+        assert_eq!(game.active_color, expected.active_color);
+        assert_eq!(game.castling_rights, expected.castling_rights);
+        assert_eq!(game.en_passant, expected.en_passant);
+        assert_eq!(game.halfmove_clock, expected.halfmove_clock);
+        assert_eq!(game.fullmove_number, expected.fullmove_number);
+    }
+
+    #[test]
+    fn make_double_pawn_push() {
+        let mut game = Game::initalize();
+        play(&mut game, "e2e4");
+        assert_position(
+            &game,
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+        );
+
+        play(&mut game, "g8f6");
+        assert_position(
+            &game,
+            "rnbqkb1r/pppppppp/5n2/8/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 1 2",
+        );
+    }
+
+    #[test]
+    fn make_castling_moves_the_rook() {
+        let mut game = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 3 10").unwrap();
+        play(&mut game, "e1g1");
+        assert_position(&game, "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 4 10");
+
+        play(&mut game, "e8c8");
+        assert_position(&game, "2kr3r/8/8/8/8/8/8/R4RK1 w - - 5 11");
+    }
+
+    #[test]
+    fn capturing_a_rook_loses_castling_rights() {
+        let mut game = Game::read_FEN("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        play(&mut game, "a1a8");
+        assert_position(&game, "R3k2r/8/8/8/8/8/8/4K2R b Kk - 0 1");
+    }
+
+    #[test]
+    fn make_en_passant_and_promotion() {
+        let mut game = Game::read_FEN("4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1").unwrap();
+        play(&mut game, "e5d6");
+        assert_position(&game, "4k3/1P6/3P4/8/8/8/8/4K3 b - - 0 1");
+
+        play(&mut game, "e8d7");
+        play(&mut game, "b7b8n");
+        assert_position(&game, "1N6/3k4/3P4/8/8/8/8/4K3 b - - 0 2");
+    }
+
+    /// Makes and unmakes every legal move to `depth`, checking the game
+    /// is exactly as it was after each one is undone.
+    fn assert_unmake_restores(game: &mut Game, depth: usize) {
+        if depth == 0 {
+            return;
+        }
+
+        for mv in game.legal_moves() {
+            let before = game.clone();
+            game.make_move(mv);
+            assert_unmake_restores(game, depth - 1);
+            assert_eq!(game.unmake_move(), Some(mv));
+            assert_eq!(*game, before, "after undoing {mv}");
+        }
+    }
+
+    #[test]
+    fn unmake_restores_every_move() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+            "4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ] {
+            assert_unmake_restores(&mut Game::read_FEN(fen).unwrap(), 2);
+        }
+    }
+
+    #[test]
+    fn unmake_without_history() {
+        let mut game = Game::initalize();
+        assert_eq!(game.unmake_move(), None);
+    }
+
+    #[test]
+    fn read_fen_errors() {
+        let cases = [
+            ("", FenError::WrongRankCount(1)),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::WrongRankCount(7),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/9/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::UnknownPiece('9'),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/44P/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::BadRankLength { rank: 4, length: 9 },
+            ),
+            (
+                "rnbqkbnr/ppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                FenError::BadRankLength { rank: 7, length: 7 },
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNX w KQkq - 0 1",
+                FenError::UnknownPiece('X'),
+            ),
+            (
+                "P3k3/8/8/8/8/8/8/4K3 w - - 0 1",
+                FenError::PawnOnBackRank(8),
+            ),
+            (
+                "4k3/8/8/8/8/8/8/p3K3 w - - 0 1",
+                FenError::PawnOnBackRank(1),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR",
+                FenError::MissingField("active color"),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR x KQkq - 0 1",
+                FenError::InvalidActiveColor("x".to_owned()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkx - 0 1",
+                FenError::InvalidCastlingRights("KQkx".to_owned()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KK - 0 1",
+                FenError::InvalidCastlingRights("KK".to_owned()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq",
+                FenError::MissingField("en passant square"),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - x 1",
+                FenError::InvalidHalfmoveClock("x".to_owned()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 0",
+                FenError::InvalidFullmoveNumber("0".to_owned()),
+            ),
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1 x",
+                FenError::UnexpectedField("x".to_owned()),
+            ),
+        ];
+
+        for (fen, error) in cases {
+            assert_eq!(Game::read_FEN(fen).unwrap_err(), error, "{fen}");
+        }
+    }
+
+    #[test]
+    fn read_fen_invalid_en_passant() {
+        for en_passant in ["e4", "i3", "e9", "e33"] {
+            let fen =
+                format!("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq {en_passant} 0 1");
+            assert!(
+                matches!(Game::read_FEN(&fen), Err(FenError::InvalidEnPassant(square, _)) if square == en_passant),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn read_fen_impossible_en_passant() {
+        for fen in [
+            // no black pawn on d5
+            "4k3/8/8/4P3/8/8/8/4K3 w - d6 0 1",
+            // a white pawn there instead
+            "4k3/8/8/3PP3/8/8/8/4K3 w - d6 0 1",
+            // the skipped square is occupied
+            "4k3/8/3n4/3pP3/8/8/8/4K3 w - d6 0 1",
+            "4k3/8/8/8/4P3/4N3/8/4K3 b - e3 0 1",
+        ] {
+            let en_passant = fen.split_whitespace().nth(3).unwrap();
+            assert_eq!(
+                Game::read_FEN(fen).unwrap_err(),
+                FenError::ImpossibleEnPassant(en_passant.to_owned()),
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn read_fen_with_repeated_whitespace() {
+        let game = Game::read_FEN("  4k3/8/8/3pP3/8/8/8/4K3  w\t-  d6 0   57 ").unwrap();
+        assert_eq!(game.to_fen(), "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 57");
+    }
+
+    #[test]
+    fn read_fen_without_clocks() {
+        let game = Game::read_FEN("8/8/8/8/8/8/8/K6k w - -").unwrap();
+        assert_eq!(game.halfmove_clock, 0);
+        assert_eq!(game.fullmove_number, 1);
+    }
+
+    #[test]
+    fn to_fen_of_known_positions() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1",
+            "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+            "4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 57",
+        ] {
+            assert_eq!(Game::read_FEN(fen).unwrap().to_fen(), fen);
+        }
+    }
+
+    /// A small xorshift generator, so the random games are the same on
+    /// every run.
+    struct Random(u64);
+
+    impl Random {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+    }
+
+    #[test]
+    fn fen_round_trips_through_random_games() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..50 {
+            let mut game = Game::initalize();
+
+            for _ in 0..100 {
+                let fen = game.to_fen();
+                let read = Game::read_FEN(&fen).unwrap();
+                assert_eq!(read.to_fen(), fen);
+                assert_eq!(read.to_string(), game.to_string());
+                assert_eq!(read.bitboards(), game.bitboards());
+                assert_eq!(read.legal_moves(), game.legal_moves(), "{fen}");
+
+                let moves = game.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                game.make_move(moves[random.next() as usize % moves.len()]);
+            }
+        }
+    }
+
+    #[test]
+    fn hash_is_kept_up_to_date() {
+        let mut random = Random(0x9e37_79b9_7f4a_7c15);
+
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+            "4k3/1P6/8/3pP3/8/8/8/4K3 w - d6 0 1",
+        ] {
+            let mut game = Game::read_FEN(fen).unwrap();
+
+            for _ in 0..200 {
+                let moves = game.legal_moves();
+                if moves.is_empty() {
+                    break;
+                }
+                game.make_move(moves[random.next() as usize % moves.len()]);
+
+                assert_eq!(game.hash, game.compute_hash(), "{}", game.to_fen());
+                assert_eq!(game.hash, Game::read_FEN(&game.to_fen()).unwrap().hash);
+            }
+
+            while game.unmake_move().is_some() {
+                assert_eq!(game.hash, game.compute_hash(), "{}", game.to_fen());
+            }
+        }
+    }
+
+    #[test]
+    fn hash_depends_on_position_not_move_order() {
+        let mut first = Game::initalize();
+        for mv in ["g1f3", "g8f6", "b1c3"] {
+            play(&mut first, mv);
+        }
+        let mut second = Game::initalize();
+        for mv in ["b1c3", "g8f6", "g1f3"] {
+            play(&mut second, mv);
+        }
+
+        assert_eq!(first.hash(), second.hash());
+        assert_ne!(first.hash(), Game::initalize().hash());
+    }
+
+    #[test]
+    fn hash_includes_side_castling_and_en_passant() {
+        let hash = |fen: String| Game::read_FEN(&fen).unwrap().hash();
+        let position = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR";
+
+        let base = hash(format!("{position} b KQkq - 0 1"));
+        assert_ne!(base, hash(format!("{position} w KQkq - 0 1")));
+        assert_ne!(base, hash(format!("{position} b Kkq - 0 1")));
+        assert_ne!(base, hash(format!("{position} b KQkq e3 0 1")));
+    }
 }
@@ -1,8 +1,103 @@
+mod evaluation;
 mod game;
+mod move_generation;
+mod perft;
 mod ray_attacks;
+mod search;
+mod transposition;
+mod uci;
+mod zobrist;
+
+use std::{process::ExitCode, time::Instant};
 
 use game::*;
+use perft::{divide, perft};
+
+/// Without arguments runs as a UCI engine for chess GUIs.
+///
+/// `chess_comp perft <fen> <depth>` counts the positions `depth` moves
+/// from `fen` and `chess_comp divide <fen> <depth>` breaks the count down by
+/// the first move. `startpos` can stand for the initial position.
+///
+/// Otherwise plays the moves given as arguments, such as `e2e4 e7e5`, from
+/// the initial position, `undo` taking back the last one, and prints the
+/// board, its FEN, the legal moves and the status.
+fn main() -> ExitCode {
+    let arguments: Vec<String> = std::env::args().skip(1).collect();
+
+    match arguments.as_slice() {
+        [mode, fen, depth] if mode == "perft" || mode == "divide" => {
+            run_perft(mode == "divide", fen, depth)
+        }
+        [] => {
+            uci::run();
+            ExitCode::SUCCESS
+        }
+        moves => play(moves),
+    }
+}
+
+fn run_perft(divided: bool, fen: &str, depth: &str) -> ExitCode {
+    let Ok(depth) = depth.parse() else {
+        eprintln!("Invalid depth: {depth}");
+        return ExitCode::FAILURE;
+    };
+    let mut game = match fen {
+        "startpos" => Game::initalize(),
+        fen => match Game::read_FEN(fen) {
+            Ok(game) => game,
+            Err(error) => {
+                eprintln!("Invalid FEN: {error}");
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    let start = Instant::now();
+    let nodes = if divided {
+        let moves = divide(&mut game, depth);
+        for (mv, nodes) in &moves {
+            println!("{mv}: {nodes}");
+        }
+        println!();
+        moves.iter().map(|(_, nodes)| nodes).sum()
+    } else {
+        perft(&mut game, depth)
+    };
+    let elapsed = start.elapsed();
+
+    println!("Nodes searched: {nodes}");
+    println!(
+        "Time: {:.3}s ({:.0} nodes/s)",
+        elapsed.as_secs_f64(),
+        nodes as f64 / elapsed.as_secs_f64()
+    );
+
+    ExitCode::SUCCESS
+}
+
+fn play(moves: &[String]) -> ExitCode {
+    let mut game = Game::initalize();
+
+    for argument in moves {
+        if argument == "undo" {
+            game.unmake_move();
+            continue;
+        }
+
+        let Some(mv) = game.find_move(argument) else {
+            eprintln!("Illegal move: {argument}");
+            return ExitCode::FAILURE;
+        };
+        game.make_move(mv);
+    }
+
+    println!("{game}");
+    println!("{}", game.to_fen());
+
+    let moves: Vec<String> = game.legal_moves().iter().map(|mv| mv.to_string()).collect();
+    println!("{}", moves.join(" "));
+    println!("{:?}", game.status());
 
-fn main() {    
-    println!("{}", Game::initalize().to_string());
+    ExitCode::SUCCESS
 }
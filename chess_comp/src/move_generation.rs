@@ -0,0 +1,593 @@
+use std::{fmt, sync::OnceLock};
+
+use crate::{
+    game::{Bitboards, CastlingRights, Color, Game, PieceType, index_to_position},
+    ray_attacks::Rays,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveKind {
+    Quiet,
+    DoublePawnPush,
+    Capture,
+    EnPassant,
+    KingSideCastle,
+    QueenSideCastle,
+}
+
+/// Whether the side to move can carry on, and whether it is in check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GameStatus {
+    Ongoing,
+    Check,
+    Checkmate,
+    Stalemate,
+}
+
+/// A move from one square to another, squares numbered from `a1` as 0 to
+/// `h8` as 63. Castling is written as the king's move.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Move {
+    pub from: usize,
+    pub to: usize,
+    pub kind: MoveKind,
+    pub promotion: Option<PieceType>,
+}
+
+impl Move {
+    fn new(from: usize, to: usize, kind: MoveKind) -> Self {
+        Self {
+            from,
+            to,
+            kind,
+            promotion: None,
+        }
+    }
+
+    pub fn is_capture(&self) -> bool {
+        matches!(self.kind, MoveKind::Capture | MoveKind::EnPassant)
+    }
+}
+
+/// Long algebraic notation as used by UCI, such as `e2e4` or `e7e8q`.
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            index_to_position(self.from),
+            index_to_position(self.to)
+        )?;
+
+        match self.promotion {
+            Some(PieceType::Queen) => write!(f, "q"),
+            Some(PieceType::Rook) => write!(f, "r"),
+            Some(PieceType::Bishop) => write!(f, "b"),
+            Some(PieceType::Knight) => write!(f, "n"),
+            _ => Ok(()),
+        }
+    }
+}
+
+const PROMOTIONS: [PieceType; 4] = [
+    PieceType::Queen,
+    PieceType::Rook,
+    PieceType::Bishop,
+    PieceType::Knight,
+];
+
+const KNIGHT_OFFSETS: [(i8, i8); 8] = [
+    (1, 2),
+    (2, 1),
+    (2, -1),
+    (1, -2),
+    (-1, -2),
+    (-2, -1),
+    (-2, 1),
+    (-1, 2),
+];
+
+const KING_OFFSETS: [(i8, i8); 8] = [
+    (1, 0),
+    (1, 1),
+    (0, 1),
+    (-1, 1),
+    (-1, 0),
+    (-1, -1),
+    (0, -1),
+    (1, -1),
+];
+
+/// For each square, the squares reached by stepping by each of `offsets`
+/// (in rows and columns) without leaving the board.
+fn step_table(offsets: &[(i8, i8)]) -> [u64; 64] {
+    let mut table = [0; 64];
+
+    for (square, attacks) in table.iter_mut().enumerate() {
+        let row = (square / 8) as i8;
+        let col = (square % 8) as i8;
+
+        for (row_offset, col_offset) in offsets {
+            let (row, col) = (row + row_offset, col + col_offset);
+            if (0..8).contains(&row) && (0..8).contains(&col) {
+                *attacks |= 1 << (row * 8 + col);
+            }
+        }
+    }
+
+    table
+}
+
+pub fn knight_attacks(square: usize) -> u64 {
+    static TABLE: OnceLock<[u64; 64]> = OnceLock::new();
+    TABLE.get_or_init(|| step_table(&KNIGHT_OFFSETS))[square]
+}
+
+pub fn king_attacks(square: usize) -> u64 {
+    static TABLE: OnceLock<[u64; 64]> = OnceLock::new();
+    TABLE.get_or_init(|| step_table(&KING_OFFSETS))[square]
+}
+
+/// The squares a pawn of `color` on `square` attacks.
+pub fn pawn_attacks(color: Color, square: usize) -> u64 {
+    static TABLES: OnceLock<[[u64; 64]; 2]> = OnceLock::new();
+    let tables = TABLES.get_or_init(|| {
+        [
+            step_table(&[(1, -1), (1, 1)]),
+            step_table(&[(-1, -1), (-1, 1)]),
+        ]
+    });
+
+    tables[color as usize][square]
+}
+
+/// Calls `f` with the index of each set bit, lowest first.
+pub fn for_each_square(mut bitboard: u64, mut f: impl FnMut(usize)) {
+    while bitboard != 0 {
+        f(bitboard.trailing_zeros() as usize);
+        bitboard &= bitboard - 1;
+    }
+}
+
+/// The pieces of `color` attacking `square` when the board holds
+/// `occupancy`, which can differ from `bitboards` to see through pieces.
+pub fn attackers_of(square: usize, color: Color, bitboards: &Bitboards, occupancy: u64) -> u64 {
+    let rays = Rays::get();
+    let queens = bitboards.of(color, PieceType::Queen);
+    let rooks = bitboards.of(color, PieceType::Rook) | queens;
+    let bishops = bitboards.of(color, PieceType::Bishop) | queens;
+
+    (pawn_attacks(color.opposite(), square) & bitboards.of(color, PieceType::Pawn))
+        | (knight_attacks(square) & bitboards.of(color, PieceType::Knight))
+        | (king_attacks(square) & bitboards.of(color, PieceType::King))
+        | (rays.rook_attacks(square, occupancy) & rooks)
+        | (rays.bishop_attacks(square, occupancy) & bishops)
+}
+
+/// Every square the pieces of `color` attack when the board holds
+/// `occupancy`.
+pub fn attack_map(color: Color, bitboards: &Bitboards, occupancy: u64) -> u64 {
+    let rays = Rays::get();
+    let queens = bitboards.of(color, PieceType::Queen);
+    let mut attacks = 0;
+
+    for_each_square(bitboards.of(color, PieceType::Pawn), |square| {
+        attacks |= pawn_attacks(color, square)
+    });
+    for_each_square(bitboards.of(color, PieceType::Knight), |square| {
+        attacks |= knight_attacks(square)
+    });
+    for_each_square(bitboards.of(color, PieceType::King), |square| {
+        attacks |= king_attacks(square)
+    });
+    for_each_square(bitboards.of(color, PieceType::Bishop) | queens, |square| {
+        attacks |= rays.bishop_attacks(square, occupancy)
+    });
+    for_each_square(bitboards.of(color, PieceType::Rook) | queens, |square| {
+        attacks |= rays.rook_attacks(square, occupancy)
+    });
+
+    attacks
+}
+
+impl Game {
+    /// Every square the pieces of `color` attack.
+    pub fn attacked_squares(&self, color: Color) -> u64 {
+        let bitboards = self.bitboards();
+        attack_map(color, &bitboards, bitboards.occupancy())
+    }
+
+    pub fn is_in_check(&self) -> bool {
+        let color = self.active_color();
+        let king = self.bitboards().of(color, PieceType::King);
+
+        self.attacked_squares(color.opposite()) & king != 0
+    }
+
+    pub fn status(&self) -> GameStatus {
+        match (self.is_in_check(), self.legal_moves().is_empty()) {
+            (false, false) => GameStatus::Ongoing,
+            (true, false) => GameStatus::Check,
+            (true, true) => GameStatus::Checkmate,
+            (false, true) => GameStatus::Stalemate,
+        }
+    }
+
+    /// The legal move written `text` in long algebraic notation, such as
+    /// `e2e4` or `e7e8q`.
+    pub fn find_move(&self, text: &str) -> Option<Move> {
+        self.legal_moves()
+            .into_iter()
+            .find(|mv| mv.to_string() == text)
+    }
+
+    /// Every move the side to move can make that doesn't leave its own king
+    /// in check.
+    ///
+    /// Rather than trying each move, the moves are filtered using the pieces
+    /// giving check, the squares the opponent attacks with the king off the
+    /// board, and the pieces pinned to the king: with one checker a move has
+    /// to capture or block it, with two only the king can move, and a pinned
+    /// piece has to stay on the line between its king and the pinner. En
+    /// passant, which removes two pieces from a rank, is checked directly.
+    pub fn legal_moves(&self) -> Vec<Move> {
+        let moves = self.pseudo_legal_moves();
+        let bitboards = self.bitboards();
+        let color = self.active_color();
+        let enemy = color.opposite();
+        let kings = bitboards.of(color, PieceType::King);
+
+        if kings == 0 {
+            return moves;
+        }
+
+        let rays = Rays::get();
+        let own = bitboards.color(color);
+        let enemies = bitboards.color(enemy);
+        let occupancy = own | enemies;
+        let king = kings.trailing_zeros() as usize;
+
+        let checkers = attackers_of(king, enemy, &bitboards, occupancy);
+        let danger = attack_map(enemy, &bitboards, occupancy & !kings);
+        let check_mask = match checkers.count_ones() {
+            0 => !0,
+            1 => checkers | rays.between(king, checkers.trailing_zeros() as usize),
+            _ => 0,
+        };
+
+        // enemy sliders that would attack the king if our pieces weren't
+        // there, and which of our pieces is alone in the way
+        let queens = bitboards.of(enemy, PieceType::Queen);
+        let snipers = (rays.rook_attacks(king, enemies)
+            & (bitboards.of(enemy, PieceType::Rook) | queens))
+            | (rays.bishop_attacks(king, enemies)
+                & (bitboards.of(enemy, PieceType::Bishop) | queens));
+        let mut pinned = 0;
+        for_each_square(snipers, |sniper| {
+            let blockers = rays.between(king, sniper) & occupancy;
+            if blockers.count_ones() == 1 && blockers & own != 0 {
+                pinned |= blockers;
+            }
+        });
+
+        moves
+            .into_iter()
+            .filter(|mv| match mv.kind {
+                MoveKind::KingSideCastle | MoveKind::QueenSideCastle => checkers == 0,
+                _ if mv.from == king => danger & (1 << mv.to) == 0,
+                MoveKind::EnPassant => {
+                    let captured = mv.from / 8 * 8 + mv.to % 8;
+                    let occupancy = occupancy ^ (1 << mv.from) ^ (1 << captured) | (1 << mv.to);
+
+                    attackers_of(king, enemy, &bitboards, occupancy) & !(1 << captured) == 0
+                }
+                _ => {
+                    check_mask & (1 << mv.to) != 0
+                        && (pinned & (1 << mv.from) == 0
+                            || rays.line(king, mv.from) & (1 << mv.to) != 0)
+                }
+            })
+            .collect()
+    }
+
+    /// Every move the side to move can make without regard to whether it
+    /// leaves its own king in check. Castling is only generated when the
+    /// king doesn't start on, pass through or land on an attacked square.
+    pub fn pseudo_legal_moves(&self) -> Vec<Move> {
+        let mut moves = Vec::with_capacity(64);
+        let bitboards = self.bitboards();
+        let color = self.active_color();
+        let own = bitboards.color(color);
+        let enemies = bitboards.color(color.opposite());
+        let occupancy = own | enemies;
+        let rays = Rays::get();
+
+        self.pawn_moves(&bitboards, &mut moves);
+
+        for piece_type in [
+            PieceType::Knight,
+            PieceType::Bishop,
+            PieceType::Rook,
+            PieceType::Queen,
+            PieceType::King,
+        ] {
+            for_each_square(bitboards.of(color, piece_type), |from| {
+                let attacks = match piece_type {
+                    PieceType::Knight => knight_attacks(from),
+                    PieceType::Bishop => rays.bishop_attacks(from, occupancy),
+                    PieceType::Rook => rays.rook_attacks(from, occupancy),
+                    PieceType::Queen => rays.queen_attacks(from, occupancy),
+                    _ => king_attacks(from),
+                };
+
+                push_moves(from, attacks & !occupancy, MoveKind::Quiet, &mut moves);
+                push_moves(from, attacks & enemies, MoveKind::Capture, &mut moves);
+            });
+        }
+
+        self.castling_moves(&bitboards, &mut moves);
+
+        moves
+    }
+
+    fn pawn_moves(&self, bitboards: &Bitboards, moves: &mut Vec<Move>) {
+        let color = self.active_color();
+        let enemies = bitboards.color(color.opposite());
+        let empty = !bitboards.occupancy();
+        let (forward, start_row, promotion_row): (isize, usize, usize) = match color {
+            Color::White => (8, 1, 7),
+            Color::Black => (-8, 6, 0),
+        };
+
+        for_each_square(bitboards.of(color, PieceType::Pawn), |from| {
+            // pawns can't be on the back ranks, but don't shift off the
+            // board if one is
+            let push = from.checked_add_signed(forward).filter(|&push| push < 64);
+            let promotes = push.is_some_and(|push| push / 8 == promotion_row);
+
+            if let Some(push) = push
+                && empty & (1 << push) != 0
+            {
+                push_pawn_move(from, push, MoveKind::Quiet, promotes, moves);
+
+                let double_push = push.wrapping_add_signed(forward);
+                if from / 8 == start_row && empty & (1 << double_push) != 0 {
+                    moves.push(Move::new(from, double_push, MoveKind::DoublePawnPush));
+                }
+            }
+
+            let attacks = pawn_attacks(color, from);
+            for_each_square(attacks & enemies, |to| {
+                push_pawn_move(from, to, MoveKind::Capture, promotes, moves);
+            });
+            if let Some(en_passant) = self.en_passant()
+                && attacks & en_passant != 0
+            {
+                let to = en_passant.trailing_zeros() as usize;
+                moves.push(Move::new(from, to, MoveKind::EnPassant));
+            }
+        });
+    }
+
+    fn castling_moves(&self, bitboards: &Bitboards, moves: &mut Vec<Move>) {
+        let color = self.active_color();
+        let (king_side, queen_side, king) = match color {
+            Color::White => (
+                CastlingRights::WHITE_KING_SIDE,
+                CastlingRights::WHITE_QUEEN_SIDE,
+                4,
+            ),
+            Color::Black => (
+                CastlingRights::BLACK_KING_SIDE,
+                CastlingRights::BLACK_QUEEN_SIDE,
+                60,
+            ),
+        };
+        let rights = self.castling_rights();
+        let occupancy = bitboards.occupancy();
+        let rooks = bitboards.of(color, PieceType::Rook);
+
+        if bitboards.of(color, PieceType::King) & (1 << king) == 0 {
+            return;
+        }
+
+        let enemy = color.opposite();
+        let is_safe = |squares: &[usize]| {
+            squares
+                .iter()
+                .all(|&square| attackers_of(square, enemy, bitboards, occupancy) == 0)
+        };
+        let is_empty =
+            |squares: &[usize]| squares.iter().all(|&square| occupancy & (1 << square) == 0);
+
+        if rights.contains(king_side)
+            && rooks & (1 << (king + 3)) != 0
+            && is_empty(&[king + 1, king + 2])
+            && is_safe(&[king, king + 1, king + 2])
+        {
+            moves.push(Move::new(king, king + 2, MoveKind::KingSideCastle));
+        }
+
+        if rights.contains(queen_side)
+            && rooks & (1 << (king - 4)) != 0
+            && is_empty(&[king - 1, king - 2, king - 3])
+            && is_safe(&[king, king - 1, king - 2])
+        {
+            moves.push(Move::new(king, king - 2, MoveKind::QueenSideCastle));
+        }
+    }
+}
+
+fn push_moves(from: usize, targets: u64, kind: MoveKind, moves: &mut Vec<Move>) {
+    for_each_square(targets, |to| moves.push(Move::new(from, to, kind)));
+}
+
+/// Adds a pawn move, or one move for each piece it can promote to.
+fn push_pawn_move(from: usize, to: usize, kind: MoveKind, promotes: bool, moves: &mut Vec<Move>) {
+    if !promotes {
+        moves.push(Move::new(from, to, kind));
+        return;
+    }
+
+    for promotion in PROMOTIONS {
+        moves.push(Move {
+            promotion: Some(promotion),
+            ..Move::new(from, to, kind)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn move_strings(fen: &str) -> Vec<String> {
+        let mut moves: Vec<String> = Game::read_FEN(fen)
+            .unwrap()
+            .pseudo_legal_moves()
+            .iter()
+            .map(Move::to_string)
+            .collect();
+        moves.sort();
+        moves
+    }
+
+    #[test]
+    fn initial_position_has_twenty_moves() {
+        assert_eq!(Game::initalize().pseudo_legal_moves().len(), 20);
+    }
+
+    #[test]
+    fn kiwipete_has_forty_eight_moves() {
+        let game =
+            Game::read_FEN("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1")
+                .unwrap();
+        assert_eq!(game.pseudo_legal_moves().len(), 48);
+    }
+
+    #[test]
+    fn pawn_promotions_and_captures() {
+        let moves = move_strings("1n5k/P7/8/8/8/8/8/7K w - - 0 1");
+        for expected in ["a7a8q", "a7a8r", "a7a8b", "a7a8n", "a7b8q", "a7b8n"] {
+            assert!(moves.contains(&expected.to_string()), "missing {expected}");
+        }
+    }
+
+    #[test]
+    fn en_passant_capture() {
+        let moves = move_strings("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 1");
+        assert!(moves.contains(&"e5d6".to_string()));
+    }
+
+    #[test]
+    fn double_push_blocked() {
+        let moves = move_strings("4k3/8/8/8/8/4n3/4P3/4K3 w - - 0 1");
+        assert!(!moves.iter().any(|mv| mv.starts_with("e2e")));
+    }
+
+    #[test]
+    fn castling_through_attacked_square() {
+        let moves = move_strings("4k3/8/8/8/8/8/5r2/R3K2R w KQ - 0 1");
+        assert!(moves.contains(&"e1c1".to_string()));
+        assert!(!moves.contains(&"e1g1".to_string()));
+    }
+
+    #[test]
+    fn castling_needs_rights_and_empty_squares() {
+        let moves = move_strings("4k3/8/8/8/8/8/8/RN2K2R w K - 0 1");
+        assert!(moves.contains(&"e1g1".to_string()));
+        assert!(!moves.contains(&"e1c1".to_string()));
+    }
+
+    fn legal_move_count(fen: &str) -> usize {
+        Game::read_FEN(fen).unwrap().legal_moves().len()
+    }
+
+    #[test]
+    fn legal_move_counts_of_known_positions() {
+        let positions = [
+            (
+                "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+                20,
+            ),
+            (
+                "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+                48,
+            ),
+            ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", 14),
+            (
+                "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+                6,
+            ),
+            (
+                "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8",
+                44,
+            ),
+            (
+                "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10",
+                46,
+            ),
+        ];
+
+        for (fen, count) in positions {
+            assert_eq!(legal_move_count(fen), count, "{fen}");
+        }
+    }
+
+    #[test]
+    fn fools_mate_is_checkmate() {
+        let game = Game::read_FEN("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+            .unwrap();
+        assert_eq!(game.status(), GameStatus::Checkmate);
+    }
+
+    #[test]
+    fn cornered_king_is_stalemate() {
+        let game = Game::read_FEN("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert_eq!(game.status(), GameStatus::Stalemate);
+    }
+
+    #[test]
+    fn check_must_be_answered() {
+        let game = Game::read_FEN("4k3/8/8/8/8/8/3q4/R3K2R w KQ - 0 1").unwrap();
+        assert_eq!(game.status(), GameStatus::Check);
+
+        let moves: Vec<String> = game.legal_moves().iter().map(Move::to_string).collect();
+        assert!(moves.iter().all(|mv| mv.starts_with("e1")));
+        assert!(!moves.contains(&"e1g1".to_string()));
+    }
+
+    #[test]
+    fn pinned_piece_stays_on_the_pin_line() {
+        let moves: Vec<String> = Game::read_FEN("4r1k1/8/8/8/8/8/4R3/4K3 w - - 0 1")
+            .unwrap()
+            .legal_moves()
+            .iter()
+            .map(Move::to_string)
+            .collect();
+
+        assert!(moves.contains(&"e2e8".to_string()));
+        assert!(moves.contains(&"e2e5".to_string()));
+        assert!(!moves.contains(&"e2d2".to_string()));
+    }
+
+    #[test]
+    fn en_passant_exposing_the_king_is_illegal() {
+        let game = Game::read_FEN("8/8/8/KPp4r/8/8/8/7k w - c6 0 1").unwrap();
+        let moves: Vec<String> = game.legal_moves().iter().map(Move::to_string).collect();
+
+        assert!(
+            game.pseudo_legal_moves()
+                .iter()
+                .any(|mv| mv.kind == MoveKind::EnPassant)
+        );
+        assert!(!moves.contains(&"b5c6".to_string()));
+    }
+
+    #[test]
+    fn black_pawns_move_down() {
+        let moves = move_strings("4k3/p7/8/8/8/8/8/4K3 b - - 0 1");
+        assert!(moves.contains(&"a7a6".to_string()));
+        assert!(moves.contains(&"a7a5".to_string()));
+    }
+}
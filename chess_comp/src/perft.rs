@@ -0,0 +1,128 @@
+use crate::{game::Game, move_generation::Move};
+
+/// The number of positions reached by playing every sequence of `depth`
+/// legal moves, for comparing move generation against published counts.
+pub fn perft(game: &mut Game, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+
+    let moves = game.legal_moves();
+    if depth == 1 {
+        return moves.len() as u64;
+    }
+
+    let mut nodes = 0;
+    for mv in moves {
+        game.make_move(mv);
+        nodes += perft(game, depth - 1);
+        game.unmake_move();
+    }
+
+    nodes
+}
+
+/// `perft` split by the first move, to narrow down where a count goes
+/// wrong.
+pub fn divide(game: &mut Game, depth: usize) -> Vec<(Move, u64)> {
+    let mut moves: Vec<(Move, u64)> = game
+        .legal_moves()
+        .into_iter()
+        .map(|mv| {
+            game.make_move(mv);
+            let nodes = perft(game, depth.saturating_sub(1));
+            game.unmake_move();
+            (mv, nodes)
+        })
+        .collect();
+
+    moves.sort_by_key(|(mv, _)| mv.to_string());
+    moves
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+    const KIWIPETE: &str = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+    const POSITION_3: &str = "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1";
+    const POSITION_4: &str = "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1";
+    const POSITION_5: &str = "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8";
+    const POSITION_6: &str =
+        "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - - 0 10";
+
+    fn assert_perft(fen: &str, counts: &[u64]) {
+        let mut game = Game::read_FEN(fen).unwrap();
+
+        for (depth, &count) in counts.iter().enumerate() {
+            assert_eq!(
+                perft(&mut game, depth + 1),
+                count,
+                "{fen} at depth {}",
+                depth + 1
+            );
+        }
+    }
+
+    #[test]
+    fn perft_start() {
+        assert_perft(START, &[20, 400, 8902]);
+    }
+
+    #[test]
+    fn perft_kiwipete() {
+        assert_perft(KIWIPETE, &[48, 2039, 97862]);
+    }
+
+    #[test]
+    fn perft_position_3() {
+        assert_perft(POSITION_3, &[14, 191, 2812, 43238]);
+    }
+
+    #[test]
+    fn perft_position_4() {
+        assert_perft(POSITION_4, &[6, 264, 9467]);
+    }
+
+    #[test]
+    fn perft_position_5() {
+        assert_perft(POSITION_5, &[44, 1486, 62379]);
+    }
+
+    #[test]
+    fn perft_position_6() {
+        assert_perft(POSITION_6, &[46, 2079, 89890]);
+    }
+
+    #[test]
+    #[ignore = "slow without optimizations, run with --release --ignored"]
+    fn perft_deep() {
+        let positions = [
+            (START, 5, 4_865_609),
+            (KIWIPETE, 4, 4_085_603),
+            (POSITION_3, 6, 11_030_083),
+            (POSITION_4, 4, 422_333),
+            (POSITION_5, 4, 2_103_487),
+            (POSITION_6, 4, 3_894_594),
+        ];
+
+        for (fen, depth, count) in positions {
+            assert_eq!(
+                perft(&mut Game::read_FEN(fen).unwrap(), depth),
+                count,
+                "{fen}"
+            );
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        let mut game = Game::read_FEN(KIWIPETE).unwrap();
+        let moves = divide(&mut game, 2);
+
+        assert_eq!(moves.len(), 48);
+        assert_eq!(moves.iter().map(|(_, nodes)| nodes).sum::<u64>(), 2039);
+        assert_eq!(game, Game::read_FEN(KIWIPETE).unwrap());
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::OnceLock;
+
 pub struct Rays {
     n_rays: Vec<u64>,
     e_rays: Vec<u64>,
@@ -22,7 +24,14 @@ macro_rules! make_rays {
     }};
 }
 
+static RAYS: OnceLock<Rays> = OnceLock::new();
+
 impl Rays {
+    /// The ray tables, built the first time they are needed.
+    pub fn get() -> &'static Rays {
+        RAYS.get_or_init(Rays::initialize)
+    }
+
     fn initialize() -> Self {
         let n_rays = make_rays!(n_ray);
         let e_rays = make_rays!(e_ray);
@@ -44,6 +53,71 @@ impl Rays {
             se_rays,
         }
     }
+
+    /// The squares a rook on `square` attacks, stopping at the first piece
+    /// in each direction.
+    pub fn rook_attacks(&self, square: usize, occupancy: u64) -> u64 {
+        blocked_ray_attack(self.n_rays[square], &self.n_rays, true, occupancy)
+            | blocked_ray_attack(self.e_rays[square], &self.e_rays, true, occupancy)
+            | blocked_ray_attack(self.s_rays[square], &self.s_rays, false, occupancy)
+            | blocked_ray_attack(self.w_rays[square], &self.w_rays, false, occupancy)
+    }
+
+    /// The squares a bishop on `square` attacks, stopping at the first piece
+    /// in each direction.
+    pub fn bishop_attacks(&self, square: usize, occupancy: u64) -> u64 {
+        blocked_ray_attack(self.ne_rays[square], &self.ne_rays, true, occupancy)
+            | blocked_ray_attack(self.nw_rays[square], &self.nw_rays, true, occupancy)
+            | blocked_ray_attack(self.se_rays[square], &self.se_rays, false, occupancy)
+            | blocked_ray_attack(self.sw_rays[square], &self.sw_rays, false, occupancy)
+    }
+
+    pub fn queen_attacks(&self, square: usize, occupancy: u64) -> u64 {
+        self.rook_attacks(square, occupancy) | self.bishop_attacks(square, occupancy)
+    }
+
+    /// The squares strictly between `from` and `to`, or none if they don't
+    /// share a rank, file or diagonal.
+    pub fn between(&self, from: usize, to: usize) -> u64 {
+        let families = [
+            &self.n_rays,
+            &self.e_rays,
+            &self.s_rays,
+            &self.w_rays,
+            &self.ne_rays,
+            &self.nw_rays,
+            &self.se_rays,
+            &self.sw_rays,
+        ];
+
+        for rays in families {
+            if rays[from] & (1 << to) != 0 {
+                return (rays[from] ^ rays[to]) & !(1 << to);
+            }
+        }
+
+        0
+    }
+
+    /// The whole rank, file or diagonal through both squares, or none if
+    /// they don't share one.
+    pub fn line(&self, a: usize, b: usize) -> u64 {
+        let families = [
+            (&self.n_rays, &self.s_rays),
+            (&self.e_rays, &self.w_rays),
+            (&self.ne_rays, &self.sw_rays),
+            (&self.nw_rays, &self.se_rays),
+        ];
+
+        for (rays, opposite_rays) in families {
+            let line = rays[a] | opposite_rays[a] | (1 << a);
+            if a != b && line & (1 << b) != 0 {
+                return line;
+            }
+        }
+
+        0
+    }
 }
 
 macro_rules! define_ray {
@@ -78,6 +152,7 @@ fn set_bit(bitboard: &mut u64, row_col: (i8, i8)) {
     *bitboard |= 1 << ((col - 1) + (row - 1) * 8)
 }
 
+#[cfg(test)]
 fn bitboard_to_string(bitboard: u64, mark: Option<usize>) -> String {
     let mut board = String::new();
 
@@ -101,14 +176,24 @@ fn bitboard_to_string(bitboard: u64, mark: Option<usize>) -> String {
     board
 }
 
-fn blocked_ray_attack(ray: u64, ray_family: &Vec<u64>, forward_ray: bool, occupancy: u64) -> u64 {
+/// The squares a slider attacks along `ray` when the pieces in `occupancy`
+/// can block it: everything up to and including the first blocker. Forward
+/// rays (north, east, north-east and north-west) run towards higher square
+/// indices, so their first blocker is the lowest set bit, backward rays
+/// the highest.
+fn blocked_ray_attack(ray: u64, ray_family: &[u64], forward_ray: bool, occupancy: u64) -> u64 {
+    let overlap = ray & occupancy;
+    if overlap == 0 {
+        return ray;
+    }
 
-    if forward_ray {
-let overlap = ray & occupancy;
-let bit_index = bit_sa e
+    let blocker = if forward_ray {
+        overlap.trailing_zeros()
     } else {
-        todo!()
-    }
+        63 - overlap.leading_zeros()
+    };
+
+    ray ^ ray_family[blocker as usize]
 }
 
 #[cfg(test)]
@@ -144,6 +229,58 @@ mod tests {
 
         println!("{}", bitboard_to_string(occupancy, Some(idx)));
     }
+
+    #[test]
+    fn rook_attacks_stop_at_first_blocker() {
+        let rays = Rays::initialize();
+        // rook on d4 (27), blockers on d6 (43), b4 (25) and d2 (11)
+        let occupancy = (1 << 43) | (1 << 25) | (1 << 11);
+        let expected = (1 << 35)
+            | (1 << 43)
+            | (1 << 26)
+            | (1 << 25)
+            | (1 << 19)
+            | (1 << 11)
+            | (1 << 28)
+            | (1 << 29)
+            | (1 << 30)
+            | (1 << 31);
+
+        assert_eq!(rays.rook_attacks(27, occupancy), expected);
+    }
+
+    #[test]
+    fn bishop_attacks_stop_at_first_blocker() {
+        let rays = Rays::initialize();
+        // bishop on c1 (2), blocker on e3 (20)
+        let occupancy = 1 << 20;
+        let expected = (1 << 9) | (1 << 16) | (1 << 11) | (1 << 20);
+
+        assert_eq!(rays.bishop_attacks(2, occupancy), expected);
+    }
+
+    #[test]
+    fn between_and_line() {
+        let rays = Rays::initialize();
+
+        // a1 (0) to d4 (27)
+        assert_eq!(rays.between(0, 27), (1 << 9) | (1 << 18));
+        assert_eq!(rays.between(27, 0), (1 << 9) | (1 << 18));
+        assert_eq!(rays.line(27, 0), rays.ne_rays[0] | 1);
+        // a1 to b3 aren't aligned
+        assert_eq!(rays.between(0, 17), 0);
+        assert_eq!(rays.line(0, 17), 0);
+    }
+
+    #[test]
+    fn unblocked_ray_reaches_the_edge() {
+        let rays = Rays::initialize();
+
+        assert_eq!(
+            rays.queen_attacks(0, 0),
+            rays.n_rays[0] | rays.e_rays[0] | rays.ne_rays[0]
+        );
+    }
     // #[test]
     // fn print_n_ray() {
     //     let rays = Rays::initialize();
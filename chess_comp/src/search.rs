@@ -0,0 +1,457 @@
+use std::{
+    cmp::Reverse,
+    sync::atomic::{AtomicBool, Ordering},
+    time::Instant,
+};
+
+use crate::{
+    evaluation::{evaluate, piece_value},
+    game::{Game, PieceType},
+    move_generation::{Move, MoveKind},
+    transposition::{Bound, TranspositionTable},
+};
+
+/// The score of being mated now. Mates further away score less, so that the
+/// quickest mate is preferred.
+pub const MATE: i32 = 100_000;
+
+const INFINITY: i32 = MATE + 1;
+
+const MAX_DEPTH: usize = 64;
+
+/// Whether `score` means one side can force mate.
+pub fn is_mate_score(score: i32) -> bool {
+    score.abs() >= MATE - MAX_DEPTH as i32
+}
+
+/// When to stop searching. Without a depth the search goes on until it's
+/// told to stop or the deadline passes.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Limits {
+    pub depth: Option<usize>,
+    pub deadline: Option<Instant>,
+}
+
+/// What a finished iteration of the search found.
+#[derive(Debug, Clone)]
+pub struct Report {
+    pub depth: usize,
+    pub score: i32,
+    pub best_move: Move,
+    /// The moves both sides are expected to play, starting with
+    /// `best_move`.
+    pub pv: Vec<Move>,
+    pub nodes: u64,
+}
+
+/// Searches `game` one ply deeper at a time until `limits` are reached or
+/// `stop` is set, calling `on_iteration` after each depth it finishes, and
+/// returns the report of the deepest one. Returns `None` if there are no
+/// legal moves. Results are shared with later searches through `table`.
+pub fn search(
+    game: &mut Game,
+    limits: Limits,
+    stop: &AtomicBool,
+    table: &mut TranspositionTable,
+    mut on_iteration: impl FnMut(&Report),
+) -> Option<Report> {
+    let first_move = *game.legal_moves().first()?;
+    table.new_search();
+
+    let mut searcher = Searcher {
+        game,
+        table,
+        limits,
+        stop,
+        stopped: false,
+        nodes: 0,
+        previous_pv: vec![],
+        killers: [[None; 2]; MAX_DEPTH],
+        history: Box::new([[0; 64]; 64]),
+    };
+
+    // in case the search is stopped before the first iteration finishes
+    let mut report = Report {
+        depth: 0,
+        score: 0,
+        best_move: first_move,
+        pv: vec![first_move],
+        nodes: 0,
+    };
+
+    for depth in 1..=limits.depth.unwrap_or(MAX_DEPTH).min(MAX_DEPTH) {
+        let mut pv = vec![];
+        let Some(score) = searcher.negamax(depth, 0, -INFINITY, INFINITY, &mut pv) else {
+            break;
+        };
+
+        report = Report {
+            depth,
+            score,
+            best_move: pv[0],
+            pv: pv.clone(),
+            nodes: searcher.nodes,
+        };
+        on_iteration(&report);
+        searcher.previous_pv = pv;
+
+        if is_mate_score(score) {
+            break;
+        }
+    }
+
+    Some(report)
+}
+
+struct Searcher<'a> {
+    game: &'a mut Game,
+    table: &'a mut TranspositionTable,
+    limits: Limits,
+    stop: &'a AtomicBool,
+    stopped: bool,
+    nodes: u64,
+    /// The principal variation of the last iteration, searched first.
+    previous_pv: Vec<Move>,
+    /// Two quiet moves per ply that recently caused a beta cutoff.
+    killers: [[Option<Move>; 2]; MAX_DEPTH],
+    /// How often a quiet move from one square to another caused a cutoff,
+    /// weighted towards deeper searches.
+    history: Box<[[i32; 64]; 64]>,
+}
+
+impl Searcher<'_> {
+    /// The score of the position for the side to move, looking `depth`
+    /// plies ahead, within the window `alpha` to `beta`. Fills `pv` with
+    /// the best line found. Returns `None` if the search was stopped.
+    fn negamax(
+        &mut self,
+        depth: usize,
+        ply: usize,
+        mut alpha: i32,
+        beta: i32,
+        pv: &mut Vec<Move>,
+    ) -> Option<i32> {
+        pv.clear();
+        if depth == 0 {
+            return self.quiescence(alpha, beta);
+        }
+
+        self.count_node()?;
+
+        let key = self.game.hash();
+        let entry = self.table.probe(key);
+        if let Some(entry) = entry
+            && ply > 0
+            && entry.depth >= depth
+        {
+            let score = score_from_table(entry.score, ply);
+            let usable = match entry.bound {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if usable {
+                return Some(score);
+            }
+        }
+
+        let mut moves = self.game.legal_moves();
+        if moves.is_empty() {
+            return Some(if self.game.is_in_check() {
+                -MATE + ply as i32
+            } else {
+                0
+            });
+        }
+
+        let hash_move = entry.and_then(|entry| entry.best_move);
+        self.order_moves(&mut moves, ply, hash_move);
+
+        let original_alpha = alpha;
+        let mut best_score = -INFINITY;
+        let mut best_move = None;
+        let mut child_pv = vec![];
+        for mv in moves {
+            self.game.make_move(mv);
+            let score = self
+                .negamax(depth - 1, ply + 1, -beta, -alpha, &mut child_pv)
+                .map(|score| -score);
+            self.game.unmake_move();
+            let score = score?;
+
+            if score > best_score {
+                best_score = score;
+                best_move = Some(mv);
+            }
+            if score > alpha {
+                alpha = score;
+                pv.clear();
+                pv.push(mv);
+                pv.extend_from_slice(&child_pv);
+            }
+            if alpha >= beta {
+                if !mv.is_capture() && mv.promotion.is_none() {
+                    self.remember_cutoff(mv, depth, ply);
+                }
+                break;
+            }
+        }
+
+        let bound = if best_score >= beta {
+            Bound::Lower
+        } else if best_score > original_alpha {
+            Bound::Exact
+        } else {
+            Bound::Upper
+        };
+        self.table.store(
+            key,
+            depth,
+            score_to_table(best_score, ply),
+            bound,
+            best_move,
+        );
+
+        Some(best_score)
+    }
+
+    /// Searches captures (and queen promotions) until the position is
+    /// quiet, so that the evaluation isn't taken in the middle of an
+    /// exchange. The side to move can always decline to capture.
+    fn quiescence(&mut self, mut alpha: i32, beta: i32) -> Option<i32> {
+        self.count_node()?;
+
+        let stand_pat = evaluate(self.game);
+        if stand_pat >= beta {
+            return Some(stand_pat);
+        }
+        alpha = alpha.max(stand_pat);
+
+        let mut moves: Vec<Move> = self
+            .game
+            .legal_moves()
+            .into_iter()
+            .filter(|mv| mv.is_capture() || mv.promotion == Some(PieceType::Queen))
+            .collect();
+        moves.sort_by_cached_key(|mv| Reverse(self.capture_score(mv)));
+
+        for mv in moves {
+            self.game.make_move(mv);
+            let score = self.quiescence(-beta, -alpha).map(|score| -score);
+            self.game.unmake_move();
+            let score = score?;
+
+            if score >= beta {
+                return Some(score);
+            }
+            alpha = alpha.max(score);
+        }
+
+        Some(alpha)
+    }
+
+    /// Counts a node, returning `None` if it's time to stop.
+    fn count_node(&mut self) -> Option<()> {
+        self.nodes += 1;
+
+        if !self.stopped && self.nodes.is_multiple_of(1024) {
+            self.stopped = self.stop.load(Ordering::Relaxed)
+                || self
+                    .limits
+                    .deadline
+                    .is_some_and(|deadline| Instant::now() >= deadline);
+        }
+
+        (!self.stopped).then_some(())
+    }
+
+    /// Orders moves so that the ones most likely to be best, and to cut off
+    /// the search early, come first: the best move the transposition table
+    /// knows of, the move from the last iteration's principal variation,
+    /// captures by most valuable victim and least
+    /// valuable attacker, promotions, killer moves and then quiet moves by
+    /// their history.
+    fn order_moves(&self, moves: &mut [Move], ply: usize, hash_move: Option<Move>) {
+        let pv_move = self.previous_pv.get(ply).copied();
+
+        moves.sort_by_cached_key(|mv| {
+            let score = if Some(*mv) == hash_move {
+                i32::MAX
+            } else if Some(*mv) == pv_move {
+                i32::MAX - 1
+            } else if mv.is_capture() {
+                2_000_000 + self.capture_score(mv)
+            } else if let Some(promotion) = mv.promotion {
+                1_500_000 + piece_value(promotion)
+            } else if Some(*mv) == self.killers[ply][0] {
+                1_200_000
+            } else if Some(*mv) == self.killers[ply][1] {
+                1_100_000
+            } else {
+                self.history[mv.from][mv.to]
+            };
+
+            Reverse(score)
+        });
+    }
+
+    /// Most valuable victim, least valuable attacker.
+    fn capture_score(&self, mv: &Move) -> i32 {
+        let victim = match mv.kind {
+            MoveKind::EnPassant => PieceType::Pawn,
+            _ => self
+                .game
+                .piece_at(mv.to)
+                .map_or(PieceType::Pawn, |piece| piece.piece_type()),
+        };
+        let attacker = self
+            .game
+            .piece_at(mv.from)
+            .map_or(PieceType::Pawn, |piece| piece.piece_type());
+
+        10 * piece_value(victim) - piece_value(attacker) / 100
+    }
+
+    fn remember_cutoff(&mut self, mv: Move, depth: usize, ply: usize) {
+        let killers = &mut self.killers[ply];
+        if killers[0] != Some(mv) {
+            killers[1] = killers[0];
+            killers[0] = Some(mv);
+        }
+
+        let history = &mut self.history[mv.from][mv.to];
+        *history += (depth * depth) as i32;
+
+        // keep history scores below the killers'
+        if *history > 1_000_000 {
+            for scores in self.history.iter_mut() {
+                for score in scores.iter_mut() {
+                    *score /= 2;
+                }
+            }
+        }
+    }
+}
+
+/// Mate scores count plies from the root of the search, but an entry in
+/// the transposition table can be reached at any ply, so they're stored
+/// counting from the position itself.
+fn score_to_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if is_mate_score(score) => score + score.signum() * ply as i32,
+        score => score,
+    }
+}
+
+fn score_from_table(score: i32, ply: usize) -> i32 {
+    match score {
+        score if is_mate_score(score) => score - score.signum() * ply as i32,
+        score => score,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search_to(fen: &str, depth: usize) -> Report {
+        let mut game = Game::read_FEN(fen).unwrap();
+        let limits = Limits {
+            depth: Some(depth),
+            deadline: None,
+        };
+
+        let mut table = TranspositionTable::new(1);
+
+        search(
+            &mut game,
+            limits,
+            &AtomicBool::new(false),
+            &mut table,
+            |_| {},
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_mate_in_one() {
+        let report = search_to("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1", 3);
+        assert_eq!(report.best_move.to_string(), "a1a8");
+        assert_eq!(report.score, MATE - 1);
+    }
+
+    #[test]
+    fn finds_mate_in_two() {
+        // 1. Re8+ Rxe8 2. Rxe8#
+        let report = search_to("3r2k1/5ppp/8/8/8/8/4R3/4R1K1 w - - 0 1", 4);
+        assert_eq!(report.score, MATE - 3);
+        assert_eq!(report.pv.len(), 3);
+    }
+
+    #[test]
+    fn takes_a_hanging_queen() {
+        let report = search_to("4k3/8/8/3q4/8/8/3R4/4K3 w - - 0 1", 2);
+        assert_eq!(report.best_move.to_string(), "d2d5");
+    }
+
+    #[test]
+    fn avoids_a_defended_pawn() {
+        // the pawn on d5 is defended, so taking it with the queen loses her
+        let report = search_to("4k3/8/4p3/3p4/8/8/3Q4/4K3 w - - 0 1", 2);
+        assert_ne!(report.best_move.to_string(), "d2d5");
+    }
+
+    #[test]
+    fn principal_variation_is_legal() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let report = search_to(fen, 3);
+        let mut game = Game::read_FEN(fen).unwrap();
+
+        assert_eq!(report.pv[0], report.best_move);
+        for mv in report.pv {
+            assert!(game.legal_moves().contains(&mv), "{mv}");
+            game.make_move(mv);
+        }
+    }
+
+    #[test]
+    fn no_move_when_mated() {
+        let mut game =
+            Game::read_FEN("rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3")
+                .unwrap();
+
+        let mut table = TranspositionTable::new(1);
+        let stop = AtomicBool::new(false);
+
+        assert!(search(&mut game, Limits::default(), &stop, &mut table, |_| {}).is_none());
+    }
+
+    #[test]
+    fn stops_when_told() {
+        let mut game = Game::initalize();
+        let stop = AtomicBool::new(true);
+        let mut table = TranspositionTable::new(1);
+
+        assert!(search(&mut game, Limits::default(), &stop, &mut table, |_| {}).is_some());
+        assert_eq!(game, Game::initalize());
+    }
+
+    #[test]
+    fn table_keeps_results_between_searches() {
+        let fen = "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1";
+        let mut game = Game::read_FEN(fen).unwrap();
+        let mut table = TranspositionTable::new(1);
+        let limits = Limits {
+            depth: Some(4),
+            deadline: None,
+        };
+        let stop = AtomicBool::new(false);
+
+        let first = search(&mut game, limits, &stop, &mut table, |_| {}).unwrap();
+        let second = search(&mut game, limits, &stop, &mut table, |_| {}).unwrap();
+
+        assert_eq!(second.score, first.score);
+        assert!(second.nodes < first.nodes);
+        assert!(table.probe(game.hash()).is_some());
+    }
+}
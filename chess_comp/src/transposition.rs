@@ -0,0 +1,150 @@
+use std::mem;
+
+use crate::move_generation::Move;
+
+/// How a stored score relates to the position's real score, depending on
+/// whether the search that found it failed high, failed low or neither.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    Exact,
+    /// The real score is at least this; the search failed high.
+    Lower,
+    /// The real score is at most this; the search failed low.
+    Upper,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Entry {
+    key: u64,
+    pub depth: usize,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+    generation: u8,
+}
+
+/// The table size in megabytes unless the `Hash` option says otherwise.
+pub const DEFAULT_MEGABYTES: usize = 16;
+
+/// A fixed-size table of search results by position hash. Each hash has
+/// one slot; an entry from the current search is only replaced by one from
+/// a search at least as deep, entries from earlier searches always are.
+pub struct TranspositionTable {
+    entries: Vec<Option<Entry>>,
+    generation: u8,
+}
+
+impl TranspositionTable {
+    pub fn new(megabytes: usize) -> Self {
+        let len = (megabytes * 1024 * 1024 / mem::size_of::<Option<Entry>>()).max(1);
+
+        Self {
+            entries: vec![None; len],
+            generation: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.fill(None);
+        self.generation = 0;
+    }
+
+    /// Marks the entries stored so far as coming from an earlier search.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn probe(&self, key: u64) -> Option<Entry> {
+        self.entries[self.index(key)].filter(|entry| entry.key == key)
+    }
+
+    pub fn store(
+        &mut self,
+        key: u64,
+        depth: usize,
+        score: i32,
+        bound: Bound,
+        best_move: Option<Move>,
+    ) {
+        let generation = self.generation;
+        let index = self.index(key);
+        let slot = &mut self.entries[index];
+
+        let replace = match slot {
+            None => true,
+            Some(entry) => entry.generation != generation || depth >= entry.depth,
+        };
+        if replace {
+            // keep the move from a shallower search of the same position
+            // if this one didn't find one
+            let best_move = best_move.or(slot
+                .filter(|entry| entry.key == key)
+                .and_then(|entry| entry.best_move));
+
+            *slot = Some(Entry {
+                key,
+                depth,
+                score,
+                bound,
+                best_move,
+                generation,
+            });
+        }
+    }
+
+    fn index(&self, key: u64) -> usize {
+        (key % self.entries.len() as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stores_and_probes() {
+        let mut table = TranspositionTable::new(1);
+        table.store(42, 3, 15, Bound::Exact, None);
+
+        let entry = table.probe(42).unwrap();
+        assert_eq!(
+            (entry.depth, entry.score, entry.bound),
+            (3, 15, Bound::Exact)
+        );
+        assert_eq!(table.probe(43), None);
+    }
+
+    #[test]
+    fn prefers_deeper_entries() {
+        let mut table = TranspositionTable::new(1);
+        let len = table.entries.len() as u64;
+
+        table.store(1, 5, 10, Bound::Exact, None);
+        // same slot, shallower search
+        table.store(1 + len, 2, 20, Bound::Lower, None);
+        assert_eq!(table.probe(1).unwrap().score, 10);
+        assert_eq!(table.probe(1 + len), None);
+
+        table.store(1 + len, 6, 30, Bound::Upper, None);
+        assert_eq!(table.probe(1 + len).unwrap().score, 30);
+    }
+
+    #[test]
+    fn replaces_entries_from_earlier_searches() {
+        let mut table = TranspositionTable::new(1);
+        table.store(7, 8, 10, Bound::Exact, None);
+        table.new_search();
+        table.store(7, 1, 20, Bound::Exact, None);
+
+        assert_eq!(table.probe(7).unwrap().score, 20);
+    }
+
+    #[test]
+    fn clear_empties_the_table() {
+        let mut table = TranspositionTable::new(1);
+        table.store(7, 8, 10, Bound::Exact, None);
+        table.clear();
+
+        assert_eq!(table.probe(7), None);
+    }
+}
@@ -0,0 +1,249 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    game::{Color, Game},
+    search::{self, Limits, MATE, Report, is_mate_score},
+    transposition::{DEFAULT_MEGABYTES, TranspositionTable},
+};
+
+/// The range the `Hash` option, the transposition table size in megabytes,
+/// can be set to.
+const MAX_HASH_MEGABYTES: usize = 4096;
+
+/// How many moves are assumed to be left when the GUI doesn't say.
+const DEFAULT_MOVES_TO_GO: u64 = 30;
+
+/// Time kept back from each move for talking to the GUI.
+const MOVE_OVERHEAD: Duration = Duration::from_millis(20);
+
+/// Talks the Universal Chess Interface on stdin and stdout until `quit` or
+/// the end of input. Searches run on their own thread so that `stop` is
+/// answered straight away.
+pub fn run() {
+    let mut engine = Engine::new();
+
+    for line in io::stdin().lock().lines() {
+        let Ok(line) = line else {
+            break;
+        };
+
+        if !engine.handle(&line) {
+            break;
+        }
+    }
+
+    engine.stop();
+}
+
+struct Engine {
+    game: Game,
+    search: Option<JoinHandle<()>>,
+    stop: Arc<AtomicBool>,
+    /// Shared with the search thread, which holds the lock while it runs.
+    table: Arc<Mutex<TranspositionTable>>,
+}
+
+impl Engine {
+    fn new() -> Self {
+        Self {
+            game: Game::initalize(),
+            search: None,
+            stop: Arc::new(AtomicBool::new(false)),
+            table: Arc::new(Mutex::new(TranspositionTable::new(DEFAULT_MEGABYTES))),
+        }
+    }
+
+    /// Handles one command, returning whether to keep going.
+    fn handle(&mut self, line: &str) -> bool {
+        let mut words = line.split_whitespace();
+
+        match words.next() {
+            Some("uci") => {
+                println!("id name chess_comp");
+                println!("id author the chess_comp authors");
+                println!(
+                    "option name Hash type spin default {DEFAULT_MEGABYTES} min 1 max {MAX_HASH_MEGABYTES}"
+                );
+                println!("uciok");
+            }
+            Some("isready") => println!("readyok"),
+            Some("setoption") => self.set_option(words),
+            Some("ucinewgame") => {
+                self.stop();
+                self.game = Game::initalize();
+                self.table.lock().unwrap().clear();
+            }
+            Some("position") => {
+                self.stop();
+                if let Err(error) = self.set_position(words) {
+                    println!("info string {error}");
+                }
+            }
+            Some("go") => self.go(words),
+            Some("stop") => self.stop(),
+            Some("quit") => return false,
+            Some(command) => println!("info string Unknown command: {command}"),
+            None => (),
+        }
+
+        true
+    }
+
+    /// `setoption name <name> [value <value>]`, where both can be several
+    /// words.
+    fn set_option<'a>(&mut self, words: impl Iterator<Item = &'a str>) {
+        let mut name = vec![];
+        let mut value = vec![];
+        let mut in_value = false;
+
+        for word in words {
+            match word {
+                "name" if !in_value && name.is_empty() => (),
+                "value" if !in_value => in_value = true,
+                word if in_value => value.push(word),
+                word => name.push(word),
+            }
+        }
+
+        let name = name.join(" ");
+        let value = value.join(" ");
+
+        if name.eq_ignore_ascii_case("Hash") {
+            let Ok(megabytes) = value.parse::<usize>() else {
+                println!("info string Invalid Hash value: {value}");
+                return;
+            };
+
+            self.stop();
+            let megabytes = megabytes.clamp(1, MAX_HASH_MEGABYTES);
+            *self.table.lock().unwrap() = TranspositionTable::new(megabytes);
+        } else {
+            println!("info string Unknown option: {name}");
+        }
+    }
+
+    /// `position [startpos | fen <fen>] [moves <move>...]`
+    fn set_position<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) -> Result<(), String> {
+        let mut game = match words.next() {
+            Some("startpos") => Game::initalize(),
+            Some("fen") => {
+                let fen: Vec<&str> = words.by_ref().take_while(|&word| word != "moves").collect();
+                Game::read_FEN(&fen.join(" ")).map_err(|error| format!("Invalid FEN: {error}"))?
+            }
+            _ => return Err("Expected startpos or fen".to_owned()),
+        };
+
+        for word in words.skip_while(|&word| word == "moves") {
+            let mv = game
+                .find_move(word)
+                .ok_or_else(|| format!("Illegal move: {word}"))?;
+            game.make_move(mv);
+        }
+
+        self.game = game;
+        Ok(())
+    }
+
+    /// `go [depth <n>] [movetime <ms>] [wtime <ms>] [btime <ms>] [winc <ms>]
+    /// [binc <ms>] [movestogo <n>] [infinite]`
+    fn go<'a>(&mut self, mut words: impl Iterator<Item = &'a str>) {
+        self.stop();
+
+        let start = Instant::now();
+        let mut limits = Limits::default();
+        let mut infinite = false;
+        let mut move_time = None;
+        let mut time_left = [None, None];
+        let mut increment = [0, 0];
+        let mut moves_to_go = DEFAULT_MOVES_TO_GO;
+
+        while let Some(word) = words.next() {
+            let mut number = || words.next().and_then(|value| value.parse::<u64>().ok());
+
+            match word {
+                "depth" => limits.depth = number().map(|depth| depth as usize),
+                "movetime" => move_time = number(),
+                "wtime" => time_left[Color::White as usize] = number(),
+                "btime" => time_left[Color::Black as usize] = number(),
+                "winc" => increment[Color::White as usize] = number().unwrap_or_default(),
+                "binc" => increment[Color::Black as usize] = number().unwrap_or_default(),
+                "movestogo" => moves_to_go = number().unwrap_or(DEFAULT_MOVES_TO_GO).max(1),
+                "infinite" => infinite = true,
+                _ => (),
+            }
+        }
+
+        let color = self.game.active_color() as usize;
+        let budget = match (move_time, time_left[color]) {
+            _ if infinite => None,
+            (Some(move_time), _) => Some(move_time),
+            (None, Some(time_left)) => {
+                // don't plan on using an increment that isn't there yet
+                let budget = time_left / moves_to_go + increment[color] / 2;
+                Some(budget.min(time_left.saturating_sub(MOVE_OVERHEAD.as_millis() as u64)))
+            }
+            (None, None) => None,
+        };
+        limits.deadline = budget
+            .map(|budget| start + Duration::from_millis(budget).saturating_sub(MOVE_OVERHEAD));
+
+        let mut game = self.game.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        self.stop = stop.clone();
+        let table = self.table.clone();
+
+        self.search = Some(thread::spawn(move || {
+            let mut table = table.lock().unwrap();
+            let report = search::search(&mut game, limits, &stop, &mut table, |report| {
+                print_report(report, start);
+            });
+
+            // an infinite search only answers once it's told to stop
+            while infinite && !stop.load(Ordering::Relaxed) {
+                thread::sleep(Duration::from_millis(5));
+            }
+
+            match report {
+                Some(report) => println!("bestmove {}", report.best_move),
+                None => println!("bestmove 0000"),
+            }
+        }));
+    }
+
+    /// Stops the running search, if any, and waits for it to report its
+    /// best move.
+    fn stop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+
+        if let Some(search) = self.search.take() {
+            let _ = search.join();
+        }
+    }
+}
+
+fn print_report(report: &Report, start: Instant) {
+    let score = if is_mate_score(report.score) {
+        // in moves rather than plies, negative when being mated
+        let plies = MATE - report.score.abs();
+        format!("mate {}", report.score.signum() * (plies + 1) / 2)
+    } else {
+        format!("cp {}", report.score)
+    };
+    let elapsed = start.elapsed().as_millis();
+    let pv: Vec<String> = report.pv.iter().map(|mv| mv.to_string()).collect();
+
+    println!(
+        "info depth {} score {score} nodes {} time {elapsed} pv {}",
+        report.depth,
+        report.nodes,
+        pv.join(" ")
+    );
+}
@@ -0,0 +1,75 @@
+use std::sync::OnceLock;
+
+use crate::game::{CastlingRights, Color, PieceType};
+
+/// Random numbers that are XORed together to give a position its hash: one
+/// for each piece on each square, one for each combination of castling
+/// rights, one for each file en passant is possible on, and one for black
+/// being to move.
+pub struct Keys {
+    pieces: [[[u64; 64]; 6]; 2],
+    castling: [u64; 16],
+    en_passant: [u64; 8],
+    black_to_move: u64,
+}
+
+static KEYS: OnceLock<Keys> = OnceLock::new();
+
+impl Keys {
+    /// The keys, the same on every run so that hashes can be compared
+    /// between runs.
+    pub fn get() -> &'static Keys {
+        KEYS.get_or_init(Keys::generate)
+    }
+
+    fn generate() -> Self {
+        let mut state = 0x9e37_79b9_7f4a_7c15;
+        let mut next = || splitmix64(&mut state);
+
+        let mut keys = Keys {
+            pieces: [[[0; 64]; 6]; 2],
+            castling: [0; 16],
+            en_passant: [0; 8],
+            black_to_move: 0,
+        };
+
+        for key in keys.pieces.iter_mut().flatten().flatten() {
+            *key = next();
+        }
+        for key in keys.castling.iter_mut().chain(&mut keys.en_passant) {
+            *key = next();
+        }
+        keys.black_to_move = next();
+
+        keys
+    }
+
+    pub fn piece(&self, color: Color, piece_type: PieceType, square: usize) -> u64 {
+        self.pieces[color as usize][piece_type as usize][square]
+    }
+
+    pub fn castling(&self, rights: CastlingRights) -> u64 {
+        self.castling[rights.bits() as usize]
+    }
+
+    /// The key for an en passant square given as a bitboard, or 0 if there
+    /// is none.
+    pub fn en_passant(&self, en_passant: Option<u64>) -> u64 {
+        en_passant.map_or(0, |square| {
+            self.en_passant[square.trailing_zeros() as usize % 8]
+        })
+    }
+
+    pub fn black_to_move(&self) -> u64 {
+        self.black_to_move
+    }
+}
+
+fn splitmix64(state: &mut u64) -> u64 {
+    *state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,177 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    rc::Rc,
+};
+
+use crate::input_parser::{Token, parse_input};
+
+/// Words after which another command starts, so the next word can be an
+/// alias too.
+const COMMAND_PREFIX_WORDS: [&str; 9] = [
+    "if", "then", "else", "elif", "while", "until", "do", "{", "!",
+];
+
+/// A token waiting to be checked, with the aliases whose expansion produced
+/// it so that an alias can't expand into itself forever.
+struct Pending {
+    token: Token,
+    expanding: Rc<Vec<String>>,
+    /// Set on the last token of an alias ending in a blank, which makes the
+    /// word after it eligible for alias expansion as well.
+    check_next: bool,
+}
+
+/// Replaces aliases in command position with the tokens of their values.
+/// Only unquoted words are looked up, so quoting any part of a command name
+/// avoids its alias.
+pub fn expand_aliases(tokens: Vec<Token>, aliases: &HashMap<String, String>) -> Vec<Token> {
+    let mut pending: VecDeque<Pending> = tokens
+        .into_iter()
+        .map(|token| Pending {
+            token,
+            expanding: Rc::default(),
+            check_next: false,
+        })
+        .collect();
+    let mut result = vec![];
+    let mut command_position = true;
+    let mut redirection_target = false;
+
+    while let Some(Pending {
+        token,
+        expanding,
+        check_next,
+    }) = pending.pop_front()
+    {
+        let Token::Word(word) = &token else {
+            redirection_target = matches!(token, Token::Redirection(..));
+            if !redirection_target {
+                command_position = true;
+            }
+            result.push(token);
+            continue;
+        };
+
+        if std::mem::take(&mut redirection_target) {
+            result.push(token);
+            continue;
+        }
+
+        let name = word.as_literal().unwrap_or_default();
+        let value = aliases
+            .get(name)
+            .filter(|_| command_position && !expanding.iter().any(|alias| alias == name));
+
+        if let Some(value) = value
+            && let Ok(tokens) = parse_input(value.clone())
+        {
+            let mut expanding = expanding.as_ref().clone();
+            expanding.push(name.to_owned());
+            let expanding = Rc::new(expanding);
+            let ends_with_blank = value.ends_with([' ', '\t']);
+            let count = tokens.len();
+
+            for (index, token) in tokens.into_iter().enumerate().rev() {
+                pending.push_front(Pending {
+                    token,
+                    expanding: Rc::clone(&expanding),
+                    check_next: ends_with_blank && index + 1 == count,
+                });
+            }
+            continue;
+        }
+
+        command_position = check_next
+            || (command_position
+                && (COMMAND_PREFIX_WORDS.contains(&name) || word.as_assignment().is_some()));
+        result.push(token);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{builtin_commands::alias::unalias, shell_state::ShellState};
+
+    /// The tokens of `input` after alias expansion, and of `expected`.
+    fn expand(input: &str, expected: &str, aliases: &[(&str, &str)]) -> (Vec<Token>, Vec<Token>) {
+        let aliases = aliases
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect();
+        let tokens = parse_input(input.to_owned()).unwrap();
+
+        (
+            expand_aliases(tokens, &aliases),
+            parse_input(expected.to_owned()).unwrap(),
+        )
+    }
+
+    #[test]
+    fn command_position() {
+        let aliases = [("ll", "ls -l")];
+
+        let (expanded, expected) = expand("ll ll; ll | ll", "ls -l ll; ls -l | ls -l", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("x=1 ll >ll", "x=1 ls -l >ll", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("'ll'; \\ll", "'ll'; \\ll", &aliases);
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn trailing_blank_expands_the_next_word() {
+        let aliases = [("sudo", "sudo "), ("nice", "nice"), ("ll", "ls -l")];
+
+        let (expanded, expected) = expand("sudo ll ll", "sudo ls -l ll", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("sudo sudo ll", "sudo sudo ls -l", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("nice ll", "nice ll", &aliases);
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn recursion_stops_at_self_reference() {
+        let aliases = [
+            ("ls", "ls --color"),
+            ("a", "b x"),
+            ("b", "a y"),
+            ("c", "ls"),
+        ];
+
+        let (expanded, expected) = expand("ls", "ls --color", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("a", "a y x", &aliases);
+        assert_eq!(expanded, expected);
+        let (expanded, expected) = expand("c; ls", "ls --color; ls --color", &aliases);
+        assert_eq!(expanded, expected);
+    }
+
+    #[test]
+    fn unalias_all() {
+        let mut state = ShellState::new(false).unwrap();
+        state.aliases.insert("ll".to_owned(), "ls -l".to_owned());
+        state.aliases.insert("la".to_owned(), "ls -a".to_owned());
+        let mut stderr = vec![];
+
+        assert_eq!(
+            unalias(&["ll".to_owned()], &mut state, &mut stderr).unwrap(),
+            0
+        );
+        assert!(!state.aliases.contains_key("ll"));
+        assert_eq!(
+            unalias(&["ll".to_owned()], &mut state, &mut stderr).unwrap(),
+            1
+        );
+        assert_eq!(String::from_utf8_lossy(&stderr), "unalias: ll: not found\n");
+
+        assert_eq!(
+            unalias(&["-a".to_owned()], &mut state, &mut stderr).unwrap(),
+            0
+        );
+        assert!(state.aliases.is_empty());
+    }
+}
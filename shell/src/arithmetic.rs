@@ -0,0 +1,507 @@
+use crate::{errors::CustomError, input_parser::is_valid_name, shell_state::ShellState};
+
+/// Binary operators from lowest to highest precedence.
+const BINARY_OPERATORS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["==", "!="],
+    &["<", "<=", ">", ">="],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+/// Longest first, so that `<<=` isn't read as `<` `<=`.
+const OPERATORS: &[&str] = &[
+    "<<=", ">>=", "**", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "++", "--", "+=", "-=",
+    "*=", "/=", "%=", "&=", "|=", "^=", "+", "-", "*", "/", "%", "<", ">", "!", "~", "&", "|", "^",
+    "?", ":", "=", ",", "(", ")",
+];
+
+/// How deeply variables whose values are themselves expressions are followed.
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(i64),
+    Name(String),
+    Operator(&'static str),
+}
+
+/// Evaluates a `$((...))` expression using C-like signed 64-bit integer
+/// arithmetic. Variables are read as numbers, or as expressions themselves,
+/// with unset or empty ones counting as 0, and can be assigned to with `=`,
+/// `+=`, `++` and the like.
+pub fn evaluate(expression: &str, state: &mut ShellState) -> Result<i64, CustomError> {
+    evaluate_at_depth(expression, state, 0)
+}
+
+fn evaluate_at_depth(
+    expression: &str,
+    state: &mut ShellState,
+    depth: usize,
+) -> Result<i64, CustomError> {
+    let error =
+        |message: &str| CustomError::Arithmetic(expression.trim().to_owned(), message.to_owned());
+
+    if depth > MAX_DEPTH {
+        return Err(error("expression recursion level exceeded"));
+    }
+
+    let tokens = tokenize(expression).map_err(|message| error(&message))?;
+    if tokens.is_empty() {
+        return Ok(0);
+    }
+
+    let mut evaluator = Evaluator {
+        tokens,
+        position: 0,
+        state,
+        skip: false,
+        depth,
+    };
+    let value = evaluator.comma().map_err(|message| error(&message))?;
+
+    match evaluator.peek() {
+        None => Ok(value),
+        Some(_) => Err(error("syntax error in expression")),
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut rest = expression.trim_start();
+
+    while let Some(character) = rest.chars().next() {
+        if character.is_ascii_digit() {
+            let end = rest
+                .find(|character: char| !character.is_ascii_alphanumeric())
+                .unwrap_or(rest.len());
+            tokens.push(Token::Number(parse_number(&rest[..end])?));
+            rest = &rest[end..];
+        } else if character.is_ascii_alphabetic() || character == '_' {
+            let end = rest
+                .find(|character: char| !character.is_ascii_alphanumeric() && character != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(rest[..end].to_owned()));
+            rest = &rest[end..];
+        } else {
+            let Some(operator) = OPERATORS
+                .iter()
+                .find(|operator| rest.starts_with(**operator))
+            else {
+                return Err(format!(
+                    "syntax error: operand expected (error token is \"{rest}\")"
+                ));
+            };
+            tokens.push(Token::Operator(operator));
+            rest = &rest[operator.len()..];
+        }
+
+        rest = rest.trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// Reads a decimal, `0x` hexadecimal or `0` octal number.
+fn parse_number(text: &str) -> Result<i64, String> {
+    let parsed = if let Some(hexadecimal) = text.strip_prefix("0x").or(text.strip_prefix("0X")) {
+        i64::from_str_radix(hexadecimal, 16)
+    } else if text.len() > 1 && text.starts_with('0') {
+        i64::from_str_radix(&text[1..], 8)
+    } else {
+        text.parse()
+    };
+
+    parsed.map_err(|_| format!("value too great for base (error token is \"{text}\")"))
+}
+
+struct Evaluator<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    state: &'a mut ShellState,
+    /// Set while evaluating the side of `&&`, `||` or `?:` that doesn't
+    /// count, so that assignments there have no effect.
+    skip: bool,
+    depth: usize,
+}
+
+impl Evaluator<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_if_operator(&mut self, operators: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Operator(operator)) if operators.contains(operator) => {
+                let operator = *operator;
+                self.position += 1;
+                Some(operator)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect_operator(&mut self, operator: &str) -> Result<(), String> {
+        match self.next_if_operator(&[operator]) {
+            Some(_) => Ok(()),
+            None => Err(format!("`{operator}' expected")),
+        }
+    }
+
+    /// comma := assignment (`,` assignment)*
+    fn comma(&mut self) -> Result<i64, String> {
+        let mut value = self.assignment()?;
+
+        while self.next_if_operator(&[","]).is_some() {
+            value = self.assignment()?;
+        }
+
+        Ok(value)
+    }
+
+    /// assignment := name (`=` | `+=` | ...) assignment | conditional
+    fn assignment(&mut self) -> Result<i64, String> {
+        let assignment_operators = [
+            "=", "+=", "-=", "*=", "/=", "%=", "<<=", ">>=", "&=", "|=", "^=",
+        ];
+
+        if let (Some(Token::Name(name)), Some(Token::Operator(operator))) = (
+            self.tokens.get(self.position).cloned(),
+            self.tokens.get(self.position + 1).cloned(),
+        ) && assignment_operators.contains(&operator)
+        {
+            self.position += 2;
+            let value = self.assignment()?;
+            let value = match operator {
+                "=" => value,
+                operator => {
+                    apply_binary(operator.trim_end_matches('='), self.variable(&name)?, value)?
+                }
+            };
+
+            return self.assign(&name, value);
+        }
+
+        self.conditional()
+    }
+
+    /// conditional := binary (`?` comma `:` conditional)?
+    fn conditional(&mut self) -> Result<i64, String> {
+        let condition = self.binary(0)?;
+
+        if self.next_if_operator(&["?"]).is_none() {
+            return Ok(condition);
+        }
+
+        let skip = self.skip;
+        self.skip = skip || condition == 0;
+        let when_true = self.comma()?;
+        self.expect_operator(":")?;
+        self.skip = skip || condition != 0;
+        let when_false = self.conditional()?;
+        self.skip = skip;
+
+        Ok(if condition != 0 {
+            when_true
+        } else {
+            when_false
+        })
+    }
+
+    /// binary := operand (operator operand)*, for the operators at `level`
+    /// and above.
+    fn binary(&mut self, level: usize) -> Result<i64, String> {
+        let Some(operators) = BINARY_OPERATORS.get(level) else {
+            return self.power();
+        };
+
+        let mut left = self.binary(level + 1)?;
+
+        while let Some(operator) = self.next_if_operator(operators) {
+            let skip = self.skip;
+            self.skip = skip || (operator == "&&" && left == 0) || (operator == "||" && left != 0);
+            let right = self.binary(level + 1)?;
+            let skipped = self.skip;
+            self.skip = skip;
+
+            left = if skipped {
+                match operator {
+                    "&&" => 0,
+                    "||" => 1,
+                    _ => left,
+                }
+            } else {
+                apply_binary(operator, left, right)?
+            };
+        }
+
+        Ok(left)
+    }
+
+    /// power := unary (`**` power)?
+    fn power(&mut self) -> Result<i64, String> {
+        let base = self.unary()?;
+
+        if self.next_if_operator(&["**"]).is_none() {
+            return Ok(base);
+        }
+
+        let exponent = self.power()?;
+        if self.skip {
+            return Ok(0);
+        }
+
+        let exponent = u32::try_from(exponent).map_err(|_| "exponent less than 0".to_owned())?;
+        Ok(base.wrapping_pow(exponent))
+    }
+
+    /// unary := (`+` | `-` | `!` | `~`) unary | (`++` | `--`) name | postfix
+    fn unary(&mut self) -> Result<i64, String> {
+        if let Some(operator) = self.next_if_operator(&["+", "-", "!", "~"]) {
+            let value = self.unary()?;
+
+            return Ok(match operator {
+                "-" => value.wrapping_neg(),
+                "!" => (value == 0) as i64,
+                "~" => !value,
+                _ => value,
+            });
+        }
+
+        if let Some(operator) = self.next_if_operator(&["++", "--"]) {
+            let Some(Token::Name(name)) = self.peek().cloned() else {
+                return Err("syntax error: operand expected".to_owned());
+            };
+            self.position += 1;
+
+            let change = if operator == "++" { 1 } else { -1 };
+            let value = self.variable(&name)?.wrapping_add(change);
+            return self.assign(&name, value);
+        }
+
+        self.postfix()
+    }
+
+    /// postfix := name (`++` | `--`)? | primary
+    fn postfix(&mut self) -> Result<i64, String> {
+        if let Some(Token::Name(name)) = self.peek().cloned() {
+            self.position += 1;
+            let value = self.variable(&name)?;
+
+            if let Some(operator) = self.next_if_operator(&["++", "--"]) {
+                let change = if operator == "++" { 1 } else { -1 };
+                self.assign(&name, value.wrapping_add(change))?;
+            }
+
+            return Ok(value);
+        }
+
+        self.primary()
+    }
+
+    /// primary := number | `(` comma `)`
+    fn primary(&mut self) -> Result<i64, String> {
+        match self.peek().cloned() {
+            Some(Token::Number(number)) => {
+                self.position += 1;
+                Ok(number)
+            }
+            Some(Token::Operator("(")) => {
+                self.position += 1;
+                let value = self.comma()?;
+                self.expect_operator(")")?;
+                Ok(value)
+            }
+            Some(Token::Operator(operator)) => Err(format!(
+                "syntax error: operand expected (error token is \"{operator}\")"
+            )),
+            _ => Err("syntax error: operand expected".to_owned()),
+        }
+    }
+
+    /// The value of a variable, evaluating it as an expression if it isn't
+    /// a plain number.
+    fn variable(&mut self, name: &str) -> Result<i64, String> {
+        let value = self.state.get_variable(name).unwrap_or_default();
+        let value = value.trim();
+
+        if value.is_empty() {
+            return Ok(0);
+        }
+        if let Ok(number) = value.parse() {
+            return Ok(number);
+        }
+
+        evaluate_at_depth(value, self.state, self.depth + 1).map_err(|error| match error {
+            CustomError::Arithmetic(_, message) => message,
+            error => error.to_string(),
+        })
+    }
+
+    fn assign(&mut self, name: &str, value: i64) -> Result<i64, String> {
+        if !is_valid_name(name) {
+            return Err("attempted assignment to non-variable".to_owned());
+        }
+        if !self.skip {
+            self.state.set_variable(name, value.to_string());
+        }
+
+        Ok(value)
+    }
+}
+
+fn apply_binary(operator: &str, left: i64, right: i64) -> Result<i64, String> {
+    let value = match operator {
+        "||" => (left != 0 || right != 0) as i64,
+        "&&" => (left != 0 && right != 0) as i64,
+        "|" => left | right,
+        "^" => left ^ right,
+        "&" => left & right,
+        "==" => (left == right) as i64,
+        "!=" => (left != right) as i64,
+        "<" => (left < right) as i64,
+        "<=" => (left <= right) as i64,
+        ">" => (left > right) as i64,
+        ">=" => (left >= right) as i64,
+        "<<" => left.wrapping_shl(right as u32),
+        ">>" => left.wrapping_shr(right as u32),
+        "+" => left.wrapping_add(right),
+        "-" => left.wrapping_sub(right),
+        "*" => left.wrapping_mul(right),
+        "/" | "%" if right == 0 => return Err("division by 0".to_owned()),
+        "/" => left.wrapping_div(right),
+        "%" => left.wrapping_rem(right),
+        operator => return Err(format!("unknown operator `{operator}'")),
+    };
+
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state() -> ShellState {
+        ShellState::new(false).unwrap()
+    }
+
+    fn value(expression: &str) -> i64 {
+        evaluate(expression, &mut state()).unwrap()
+    }
+
+    fn error(expression: &str) -> String {
+        match evaluate(expression, &mut state()) {
+            Err(CustomError::Arithmetic(_, message)) => message,
+            result => panic!("{expression} gave {result:?}"),
+        }
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(value("1 + 2 * 3"), 7);
+        assert_eq!(value("(1 + 2) * 3"), 9);
+        assert_eq!(value("2 * 3 ** 2"), 18);
+        assert_eq!(value("1 + 2 << 1"), 6);
+        assert_eq!(value("1 < 2 == 1"), 1);
+        assert_eq!(value("6 & 3 ^ 1 | 8"), 11);
+        assert_eq!(value("1 || 0 && 0"), 1);
+        assert_eq!(value("-2 ** 2"), 4);
+        assert_eq!(value("!0 + ~0"), 0);
+    }
+
+    #[test]
+    fn associativity() {
+        assert_eq!(value("10 - 4 - 3"), 3);
+        assert_eq!(value("64 / 4 / 2"), 8);
+        assert_eq!(value("2 ** 3 ** 2"), 512);
+        assert_eq!(value("1 ? 2 : 0 ? 3 : 4"), 2);
+        assert_eq!(value("0 ? 2 : 0 ? 3 : 4"), 4);
+        assert_eq!(value("1, 2, 3"), 3);
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(value("0x1F"), 31);
+        assert_eq!(value("010"), 8);
+        assert_eq!(value(""), 0);
+        assert!(error("09").starts_with("value too great for base"));
+    }
+
+    #[test]
+    fn assignment() {
+        let mut state = state();
+
+        assert_eq!(evaluate("ARITHMETIC_TEST = 5", &mut state).unwrap(), 5);
+        assert_eq!(evaluate("ARITHMETIC_TEST += 2", &mut state).unwrap(), 7);
+        assert_eq!(evaluate("ARITHMETIC_TEST <<= 1", &mut state).unwrap(), 14);
+        assert_eq!(evaluate("ARITHMETIC_TEST %= 4", &mut state).unwrap(), 2);
+        assert_eq!(evaluate("ARITHMETIC_TEST++", &mut state).unwrap(), 2);
+        assert_eq!(evaluate("++ARITHMETIC_TEST", &mut state).unwrap(), 4);
+        assert_eq!(evaluate("A_TEST = B_TEST = 3", &mut state).unwrap(), 3);
+        assert_eq!(state.get_variable("ARITHMETIC_TEST").as_deref(), Some("4"));
+        assert_eq!(state.get_variable("A_TEST").as_deref(), Some("3"));
+        assert_eq!(state.get_variable("B_TEST").as_deref(), Some("3"));
+        assert_eq!(error("1 = 2"), "syntax error in expression");
+    }
+
+    #[test]
+    fn variables_are_evaluated_as_expressions() {
+        let mut state = state();
+        state.set_variable("EXPRESSION_TEST", "2 + 3".to_owned());
+
+        assert_eq!(evaluate("EXPRESSION_TEST * 2", &mut state).unwrap(), 10);
+        assert_eq!(evaluate("UNSET_TEST + 1", &mut state).unwrap(), 1);
+
+        state.set_variable("LOOP_TEST", "LOOP_TEST".to_owned());
+        assert!(evaluate("LOOP_TEST", &mut state).is_err());
+    }
+
+    #[test]
+    fn short_circuiting() {
+        let mut state = state();
+
+        assert_eq!(evaluate("0 && (SKIPPED_TEST = 1)", &mut state).unwrap(), 0);
+        assert_eq!(evaluate("1 || (SKIPPED_TEST = 1)", &mut state).unwrap(), 1);
+        assert_eq!(
+            evaluate("1 ? 2 : (SKIPPED_TEST = 1)", &mut state).unwrap(),
+            2
+        );
+        assert_eq!(
+            evaluate("0 ? (SKIPPED_TEST = 1) : 3", &mut state).unwrap(),
+            3
+        );
+        assert_eq!(evaluate("0 && 1 / 0", &mut state).unwrap(), 0);
+        assert_eq!(evaluate("1 || 2 ** -1", &mut state).unwrap(), 1);
+        assert_eq!(state.get_variable("SKIPPED_TEST"), None);
+
+        assert_eq!(evaluate("1 && (TAKEN_TEST = 2)", &mut state).unwrap(), 1);
+        assert_eq!(state.get_variable("TAKEN_TEST").as_deref(), Some("2"));
+    }
+
+    #[test]
+    fn overflow_wraps() {
+        assert_eq!(value("9223372036854775807 + 1"), i64::MIN);
+        assert_eq!(value("-9223372036854775807 - 2"), i64::MAX);
+        assert_eq!(value("2 ** 64"), 0);
+        assert_eq!(value("4611686018427387904 * 2"), i64::MIN);
+        assert_eq!(value("(-9223372036854775807 - 1) / -1"), i64::MIN);
+        assert_eq!(value("1 << 64"), 1);
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("1 / 0"), "division by 0");
+        assert_eq!(error("1 % 0"), "division by 0");
+        assert_eq!(error("2 ** -1"), "exponent less than 0");
+        assert_eq!(error("(1 + 2"), "`)' expected");
+        assert_eq!(error("1 ? 2"), "`:' expected");
+        assert_eq!(error("1 +"), "syntax error: operand expected");
+        assert_eq!(error("2 2"), "syntax error in expression");
+        assert!(error("1 @ 2").starts_with("syntax error: operand expected"));
+    }
+}
@@ -0,0 +1,283 @@
+use crate::input_parser::{Word, WordPart};
+
+/// A piece of a word during brace expansion: an unquoted character, which
+/// may be part of a brace expression, or anything else, which can't.
+#[derive(Debug, Clone)]
+enum Piece {
+    Character(char),
+    Part(WordPart),
+}
+
+/// Expands `{a,b,c}` alternatives and `{1..10}` or `{a..e}` sequences in the
+/// unquoted parts of a word, giving one word per combination in order.
+/// Braces that don't form one of these are left alone.
+pub fn expand_braces(word: &Word) -> Vec<Word> {
+    let pieces: Vec<Piece> = word
+        .parts
+        .iter()
+        .flat_map(|part| match part {
+            WordPart::Literal(text) => text.chars().map(Piece::Character).collect(),
+            part => vec![Piece::Part(part.clone())],
+        })
+        .collect();
+
+    expand_from(pieces, 0)
+        .into_iter()
+        .map(|pieces| to_word(pieces, word.quoted))
+        .collect()
+}
+
+fn expand_from(pieces: Vec<Piece>, start: usize) -> Vec<Vec<Piece>> {
+    for open in start..pieces.len() {
+        if !matches!(pieces[open], Piece::Character('{')) {
+            continue;
+        }
+        let Some((close, commas)) = find_close(&pieces, open) else {
+            continue;
+        };
+
+        let alternatives: Vec<Vec<Piece>> = if commas.is_empty() {
+            match sequence(&pieces[open + 1..close]) {
+                Some(items) => items
+                    .into_iter()
+                    .map(|item| item.chars().map(Piece::Character).collect())
+                    .collect(),
+                None => continue,
+            }
+        } else {
+            let mut bounds = vec![open];
+            bounds.extend(&commas);
+            bounds.push(close);
+            bounds
+                .windows(2)
+                .map(|window| pieces[window[0] + 1..window[1]].to_vec())
+                .collect()
+        };
+
+        return alternatives
+            .into_iter()
+            .flat_map(|alternative| {
+                let mut expanded = pieces[..open].to_vec();
+                expanded.extend(alternative);
+                expanded.extend_from_slice(&pieces[close + 1..]);
+                expand_from(expanded, open)
+            })
+            .collect();
+    }
+
+    vec![pieces]
+}
+
+/// Finds the `}` matching the `{` at `open`, and the commas directly inside
+/// the braces.
+fn find_close(pieces: &[Piece], open: usize) -> Option<(usize, Vec<usize>)> {
+    let mut depth = 0;
+    let mut commas = vec![];
+
+    for (index, piece) in pieces.iter().enumerate().skip(open) {
+        match piece {
+            Piece::Character('{') => depth += 1,
+            Piece::Character('}') => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((index, commas));
+                }
+            }
+            Piece::Character(',') if depth == 1 => commas.push(index),
+            _ => (),
+        }
+    }
+
+    None
+}
+
+/// The items of a `start..end[..step]` sequence of integers or single
+/// characters. Integers written with leading zeros are padded to the same
+/// width.
+fn sequence(pieces: &[Piece]) -> Option<Vec<String>> {
+    let text = pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Character(character) => Some(*character),
+            Piece::Part(_) => None,
+        })
+        .collect::<Option<String>>()?;
+
+    let bounds: Vec<&str> = text.split("..").collect();
+    let (start, end, step) = match bounds.as_slice() {
+        [start, end] => (*start, *end, 1),
+        [start, end, step] => (
+            *start,
+            *end,
+            step.parse::<i64>().ok()?.unsigned_abs().max(1),
+        ),
+        _ => return None,
+    };
+
+    if let (Ok(first), Ok(last)) = (start.parse::<i64>(), end.parse::<i64>()) {
+        let is_padded =
+            |bound: &str| bound.trim_start_matches('-').starts_with('0') && bound.len() > 1;
+        let width = if is_padded(start) || is_padded(end) {
+            start.len().max(end.len())
+        } else {
+            0
+        };
+
+        return Some(
+            stepped(first, last, step)
+                .into_iter()
+                .map(|number| format!("{number:0width$}"))
+                .collect(),
+        );
+    }
+
+    let mut start_characters = start.chars();
+    let mut end_characters = end.chars();
+    match (
+        start_characters.next(),
+        start_characters.next(),
+        end_characters.next(),
+        end_characters.next(),
+    ) {
+        (Some(first), None, Some(last), None)
+            if first.is_ascii_alphabetic() && last.is_ascii_alphabetic() =>
+        {
+            Some(
+                stepped(first as i64, last as i64, step)
+                    .into_iter()
+                    .filter_map(|code| char::from_u32(code as u32))
+                    .map(String::from)
+                    .collect(),
+            )
+        }
+        _ => None,
+    }
+}
+
+fn stepped(first: i64, last: i64, step: u64) -> Vec<i64> {
+    let step = step as usize;
+
+    if first <= last {
+        (first..=last).step_by(step).collect()
+    } else {
+        (last..=first).rev().step_by(step).collect()
+    }
+}
+
+fn to_word(pieces: Vec<Piece>, quoted: bool) -> Word {
+    let mut parts = vec![];
+
+    for piece in pieces {
+        match (piece, parts.last_mut()) {
+            (Piece::Character(character), Some(WordPart::Literal(text))) => text.push(character),
+            (Piece::Character(character), _) => {
+                parts.push(WordPart::Literal(character.to_string()))
+            }
+            (Piece::Part(part), _) => parts.push(part),
+        }
+    }
+
+    Word { parts, quoted }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expand(parts: Vec<WordPart>) -> Vec<String> {
+        let word = Word {
+            parts,
+            quoted: false,
+        };
+
+        expand_braces(&word)
+            .into_iter()
+            .map(|word| {
+                word.parts
+                    .iter()
+                    .map(|part| match part {
+                        WordPart::Literal(text) | WordPart::Quoted(text) => text.clone(),
+                        part => format!("{part:?}"),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn expand_literal(text: &str) -> Vec<String> {
+        expand(vec![WordPart::Literal(text.to_owned())])
+    }
+
+    #[test]
+    fn alternatives() {
+        assert_eq!(expand_literal("a{b,c}d"), ["abd", "acd"]);
+        assert_eq!(expand_literal("{a,b}{1,2}"), ["a1", "a2", "b1", "b2"]);
+        assert_eq!(expand_literal("x{,y}"), ["x", "xy"]);
+    }
+
+    #[test]
+    fn nested_alternatives() {
+        assert_eq!(expand_literal("{a,b{c,d}}"), ["a", "bc", "bd"]);
+        assert_eq!(expand_literal("{{1,2},3}x"), ["1x", "2x", "3x"]);
+    }
+
+    #[test]
+    fn sequences() {
+        assert_eq!(expand_literal("{1..4}"), ["1", "2", "3", "4"]);
+        assert_eq!(expand_literal("{1..10..3}"), ["1", "4", "7", "10"]);
+        assert_eq!(expand_literal("{5..1..2}"), ["5", "3", "1"]);
+        assert_eq!(expand_literal("{-1..1}"), ["-1", "0", "1"]);
+        assert_eq!(expand_literal("{08..10}"), ["08", "09", "10"]);
+        assert_eq!(expand_literal("{a..c}"), ["a", "b", "c"]);
+        assert_eq!(expand_literal("{z..a..12}"), ["z", "n", "b"]);
+        assert_eq!(
+            expand_literal("{z..a}").concat(),
+            "zyxwvutsrqponmlkjihgfedcba"
+        );
+    }
+
+    #[test]
+    fn sequences_inside_alternatives() {
+        assert_eq!(expand_literal("{a,{1..2}}"), ["a", "1", "2"]);
+    }
+
+    #[test]
+    fn not_brace_expressions() {
+        assert_eq!(expand_literal("{a}"), ["{a}"]);
+        assert_eq!(expand_literal("{}"), ["{}"]);
+        assert_eq!(expand_literal("{1..b}"), ["{1..b}"]);
+        assert_eq!(expand_literal("{ab..c}"), ["{ab..c}"]);
+        assert_eq!(expand_literal("{1..2..x}"), ["{1..2..x}"]);
+    }
+
+    #[test]
+    fn unmatched_braces() {
+        assert_eq!(expand_literal("{a,b"), ["{a,b"]);
+        assert_eq!(expand_literal("a,b}"), ["a,b}"]);
+        assert_eq!(expand_literal("{{a,b}"), ["{a", "{b"]);
+        assert_eq!(expand_literal("{a,b}}"), ["a}", "b}"]);
+    }
+
+    #[test]
+    fn escaped_braces() {
+        let parts = vec![
+            WordPart::Quoted("{".to_owned()),
+            WordPart::Literal("a,b}".to_owned()),
+        ];
+        assert_eq!(expand(parts), ["{a,b}"]);
+
+        let parts = vec![
+            WordPart::Literal("{a".to_owned()),
+            WordPart::Quoted(",".to_owned()),
+            WordPart::Literal("b}".to_owned()),
+        ];
+        assert_eq!(expand(parts), ["{a,b}"]);
+
+        let parts = vec![
+            WordPart::Literal("{a,".to_owned()),
+            WordPart::Quoted("x,y".to_owned()),
+            WordPart::Literal("}".to_owned()),
+        ];
+        assert_eq!(expand(parts), ["a", "x,y"]);
+    }
+}
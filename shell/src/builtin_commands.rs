@@ -1,27 +1,180 @@
+pub mod alias;
+pub mod bg;
+pub mod builtin_return;
 pub mod builtin_type;
 pub mod change_directory;
+pub mod directory_stack;
 pub mod echo;
+pub mod env;
+pub mod exit;
+pub mod export;
+pub mod fg;
+pub mod hash;
+pub mod history;
+pub mod jobs;
+pub mod kill;
+pub mod loop_control;
+pub mod printf;
 pub mod pwd;
+pub mod read;
 pub mod run_external_executable;
+pub mod source;
+pub mod test;
+pub mod trap;
+pub mod unset;
+pub mod wait;
+
+use crate::{
+    builtin_commands::{
+        alias::{alias, unalias},
+        bg::bg,
+        builtin_return::builtin_return,
+        builtin_type::builtin_type,
+        change_directory::change_directory,
+        directory_stack::{dirs, popd, pushd},
+        echo::echo,
+        env::env,
+        exit::exit,
+        export::export,
+        fg::fg,
+        hash::hash,
+        history::history,
+        jobs::jobs,
+        kill::kill,
+        loop_control::{builtin_break, builtin_continue},
+        printf::printf,
+        pwd::pwd,
+        read::read,
+        run_external_executable::run_external_executable,
+        source::source,
+        test::test,
+        trap::trap,
+        unset::unset,
+        wait::wait,
+    },
+    shell_state::ShellState,
+};
+use anyhow::Result;
+use std::io::Write;
+
+/// Every name `BuiltinCommand::from` recognises, used for completion.
+pub const BUILTIN_NAMES: &[&str] = &[
+    ".", "[", "alias", "bg", "break", "cd", "continue", "dirs", "echo", "env", "exit", "export",
+    "fg", "hash", "history", "jobs", "kill", "popd", "printf", "pushd", "pwd", "read", "return",
+    "source", "test", "trap", "type", "unalias", "unset", "wait",
+];
 
 #[derive(Debug)]
 pub enum BuiltinCommand {
+    Alias(Vec<String>),
+    Background(Vec<String>),
+    Break(Vec<String>),
     ChangeDirectory(Vec<String>),
+    Continue(Vec<String>),
+    Dirs(Vec<String>),
     Echo(Vec<String>),
-    Exit,
-    PWD,
+    Env(Vec<String>),
+    Exit(Vec<String>),
+    Export(Vec<String>),
+    Foreground(Vec<String>),
+    Hash(Vec<String>),
+    History(Vec<String>),
+    Jobs(Vec<String>),
+    Kill(Vec<String>),
+    PopDirectory(Vec<String>),
+    Printf(Vec<String>),
+    PushDirectory(Vec<String>),
+    Pwd(Vec<String>),
+    Read(Vec<String>),
+    Return(Vec<String>),
+    Source(Vec<String>),
+    /// `test` or `[`, with the name it was run as.
+    Test(String, Vec<String>),
+    Trap(Vec<String>),
     Type(Vec<String>),
+    Unalias(Vec<String>),
+    Unset(Vec<String>),
+    Wait(Vec<String>),
     NotFound(String, Vec<String>),
 }
 
+impl BuiltinCommand {
+    /// Runs the command in the shell process and returns its exit status.
+    pub fn run(
+        self,
+        state: &mut ShellState,
+        stdout: &mut impl Write,
+        stderr: &mut impl Write,
+    ) -> Result<i32> {
+        match self {
+            Self::Alias(arguments) => alias(&arguments, state, stdout, stderr),
+            Self::Background(arguments) => bg(&arguments, state, stdout, stderr),
+            Self::Break(arguments) => builtin_break(&arguments, state, stderr),
+            Self::ChangeDirectory(arguments) => change_directory(&arguments, state, stdout, stderr),
+            Self::Continue(arguments) => builtin_continue(&arguments, state, stderr),
+            Self::Dirs(arguments) => dirs(&arguments, state, stdout, stderr),
+            Self::Echo(command_string) => echo(command_string.as_slice(), stdout),
+            Self::Env(arguments) => env(&arguments, state, stdout, stderr),
+            Self::Exit(arguments) => exit(&arguments, state, stderr),
+            Self::Export(arguments) => export(&arguments, state, stdout, stderr),
+            Self::Foreground(arguments) => fg(&arguments, state, stdout, stderr),
+            Self::Hash(arguments) => hash(&arguments, state, stdout, stderr),
+            Self::History(arguments) => history(&arguments, state, stdout, stderr),
+            Self::Jobs(arguments) => jobs(&arguments, state, stdout),
+            Self::Kill(arguments) => kill(&arguments, state, stdout, stderr),
+            Self::PopDirectory(arguments) => popd(&arguments, state, stdout, stderr),
+            Self::PushDirectory(arguments) => pushd(&arguments, state, stdout, stderr),
+            Self::Printf(arguments) => printf(&arguments, stdout, stderr),
+            Self::Pwd(arguments) => pwd(&arguments, state, stdout),
+            Self::Read(arguments) => read(&arguments, state, stderr),
+            Self::Return(arguments) => builtin_return(&arguments, state, stderr),
+            Self::Source(arguments) => source(&arguments, state, stderr),
+            Self::Test(name, arguments) => test(&name, &arguments, stderr),
+            Self::Trap(arguments) => trap(&arguments, state, stdout, stderr),
+            Self::Type(arguments) => builtin_type(arguments, state, stdout, stderr),
+            Self::Unalias(arguments) => unalias(&arguments, state, stderr),
+            Self::Unset(arguments) => unset(&arguments, state, stderr),
+            Self::Wait(arguments) => wait(&arguments, state, stderr),
+            Self::NotFound(command_string, arguments) => {
+                let executable = state.find_command(&command_string)?;
+                let environment = state.environment();
+                run_external_executable(&executable, &arguments, environment, &mut state.jobs)
+            }
+        }
+    }
+}
+
 impl From<(String, Vec<String>)> for BuiltinCommand {
     fn from((command, arguments): (String, Vec<String>)) -> Self {
         match command.as_str() {
+            "alias" => Self::Alias(arguments),
+            "bg" => Self::Background(arguments),
+            "break" => Self::Break(arguments),
             "cd" => Self::ChangeDirectory(arguments),
+            "continue" => Self::Continue(arguments),
+            "dirs" => Self::Dirs(arguments),
             "echo" => Self::Echo(arguments),
-            "exit" => Self::Exit,
-            "pwd" => Self::PWD,
+            "env" => Self::Env(arguments),
+            "exit" => Self::Exit(arguments),
+            "export" => Self::Export(arguments),
+            "fg" => Self::Foreground(arguments),
+            "hash" => Self::Hash(arguments),
+            "history" => Self::History(arguments),
+            "jobs" => Self::Jobs(arguments),
+            "kill" => Self::Kill(arguments),
+            "popd" => Self::PopDirectory(arguments),
+            "pushd" => Self::PushDirectory(arguments),
+            "printf" => Self::Printf(arguments),
+            "pwd" => Self::Pwd(arguments),
+            "read" => Self::Read(arguments),
+            "return" => Self::Return(arguments),
+            "source" | "." => Self::Source(arguments),
+            "test" | "[" => Self::Test(command.to_owned(), arguments),
+            "trap" => Self::Trap(arguments),
             "type" => Self::Type(arguments),
+            "unalias" => Self::Unalias(arguments),
+            "unset" => Self::Unset(arguments),
+            "wait" => Self::Wait(arguments),
             _ => Self::NotFound(command.to_owned(), arguments),
         }
     }
@@ -0,0 +1,88 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::ShellState;
+
+/// `alias [name[=value]...]`: defines aliases, or prints them in a form that
+/// can be run to define them again.
+pub fn alias(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    if arguments.is_empty() {
+        let mut aliases: Vec<_> = state.aliases.iter().collect();
+        aliases.sort();
+
+        for (name, value) in aliases {
+            print_alias(name, value, stdout)?;
+        }
+        return Ok(0);
+    }
+
+    let mut status = 0;
+
+    for argument in arguments {
+        match argument.split_once('=') {
+            Some((name, _)) if !is_valid_alias_name(name) => {
+                writeln!(stderr, "alias: `{name}': invalid alias name")?;
+                status = 1;
+            }
+            Some((name, value)) => {
+                state.aliases.insert(name.to_owned(), value.to_owned());
+            }
+            None => match state.aliases.get(argument) {
+                Some(value) => print_alias(argument, value, stdout)?,
+                None => {
+                    writeln!(stderr, "alias: {argument}: not found")?;
+                    status = 1;
+                }
+            },
+        }
+    }
+
+    Ok(status)
+}
+
+/// `unalias [-a] name...`: removes aliases, or all of them with `-a`.
+pub fn unalias(
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    if arguments.first().is_some_and(|argument| argument == "-a") {
+        state.aliases.clear();
+        return Ok(0);
+    }
+
+    if arguments.is_empty() {
+        writeln!(stderr, "unalias: usage: unalias [-a] name [name ...]")?;
+        return Ok(2);
+    }
+
+    let mut status = 0;
+
+    for name in arguments {
+        if state.aliases.remove(name).is_none() {
+            writeln!(stderr, "unalias: {name}: not found")?;
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
+
+fn print_alias(name: &str, value: &str, stdout: &mut impl Write) -> Result<()> {
+    writeln!(stdout, "alias {name}='{}'", value.replace('\'', r"'\''"))?;
+
+    Ok(())
+}
+
+/// Alias names can't contain anything the tokenizer treats specially.
+fn is_valid_alias_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains(|character: char| {
+            character.is_whitespace() || "/$`=\\'\"|&;()<>".contains(character)
+        })
+}
@@ -0,0 +1,32 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{process::send_signal, shell_state::ShellState};
+
+pub fn bg(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let spec = arguments.first().map(String::as_str);
+    let index = match state.jobs.find(spec) {
+        Ok(index) => index,
+        Err(error) => {
+            writeln!(stderr, "bg: {}: {error}", spec.unwrap_or("current"))?;
+            return Ok(1);
+        }
+    };
+
+    let mut job = state.jobs.jobs.remove(index);
+
+    if job.is_stopped() {
+        send_signal(-job.pgid, libc::SIGCONT)?;
+        job.mark_running();
+    }
+
+    writeln!(stdout, "[{}]+ {} &", job.id, job.text)?;
+    state.jobs.add(job);
+
+    Ok(0)
+}
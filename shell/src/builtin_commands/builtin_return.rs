@@ -0,0 +1,36 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::{ControlFlow, ShellState};
+
+/// `return [n]`: leaves the running function with status `n`, or the status
+/// of the last command.
+pub fn builtin_return(
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    if state.call_depth == 0 {
+        writeln!(
+            stderr,
+            "return: can only `return' from a function or sourced script"
+        )?;
+        return Ok(1);
+    }
+
+    let status = match arguments.first().map(|argument| argument.parse::<i32>()) {
+        None => state.last_exit_status,
+        Some(Ok(status)) => status & 0xff,
+        Some(Err(_)) => {
+            writeln!(
+                stderr,
+                "return: {}: numeric argument required",
+                arguments[0]
+            )?;
+            2
+        }
+    };
+
+    state.control_flow = Some(ControlFlow::Return);
+    Ok(status)
+}
@@ -1,47 +1,72 @@
 use anyhow::Result;
 
-use crate::{builtin_commands::BuiltinCommand, utils::find_executable_file};
-use std::path::PathBuf;
+use crate::{
+    builtin_commands::BuiltinCommand, shell_state::ShellState, utils::find_executable_files,
+};
+use std::io::Write;
 
+/// `type [-a] name...`: says how each name would be run as a command. With
+/// `-a` every alias, function, builtin and executable on the path called
+/// `name` is listed rather than only the one that would be used.
 pub fn builtin_type(
     arguments: Vec<String>,
-    paths: &[PathBuf],
-    stdout: &mut Vec<String>,
-    stderr: &mut Vec<String>,
-) -> Result<()> {
-    let type_input = arguments.first().cloned().unwrap_or_default();
-    let builtin_command = BuiltinCommand::from(type_input.clone());
-    let mut message = vec![];
-    let mut is_error = false;
-
-    message.push(type_input.clone());
-
-    if matches!(builtin_command, BuiltinCommand::NotFound(_, _)) {
-        // search the path to see if we can find an executable
-        if let Some(dir_entry) = find_executable_file(&type_input, paths) {
-            let path_buf = dir_entry.path();
-            let path = path_buf
-                .into_os_string()
-                .into_string()
-                .unwrap_or("unknown path".to_owned());
-
-            message.push(" is ".to_owned());
-            message.push(path);
-        } else {
-            message.push(": not found".to_owned());
-            is_error = true;
-        };
-    } else {
-        message.push(" is a shell builtin".to_owned());
+    state: &ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let all = arguments.first().is_some_and(|argument| argument == "-a");
+    let names = if all { &arguments[1..] } else { &arguments[..] };
+    let mut status = 0;
+
+    for name in names {
+        let descriptions = describe(name, all, state);
+
+        if descriptions.is_empty() {
+            writeln!(stderr, "type: {name}: not found")?;
+            status = 1;
+        }
+        for description in descriptions {
+            writeln!(stdout, "{name} {description}")?;
+        }
     }
 
-    let message = message.join("");
+    Ok(status)
+}
 
-    if is_error {
-        stderr.push(message);
-    } else {
-        stdout.push(message);
+/// The ways `name` can be run, in the order they're tried, stopping at the
+/// first unless `all` is set.
+fn describe(name: &str, all: bool, state: &ShellState) -> Vec<String> {
+    let mut descriptions = vec![];
+
+    if let Some(value) = state.aliases.get(name) {
+        descriptions.push(format!("is aliased to `{value}'"));
+    }
+    if state.functions.contains_key(name) {
+        descriptions.push("is a function".to_owned());
     }
+    if !matches!(
+        BuiltinCommand::from(name.to_owned()),
+        BuiltinCommand::NotFound(_, _)
+    ) {
+        descriptions.push("is a shell builtin".to_owned());
+    }
+
+    if !all {
+        if !descriptions.is_empty() {
+            descriptions.truncate(1);
+            return descriptions;
+        }
+        if let Some(entry) = state.command_hash.get(name) {
+            return vec![format!("is hashed ({})", entry.path.display())];
+        }
+    }
+
+    let executables = find_executable_files(name, state.path());
+    let executables = executables
+        .iter()
+        .take(if all { usize::MAX } else { 1 })
+        .map(|path| format!("is {}", path.display()));
+    descriptions.extend(executables);
 
-    Ok(())
+    descriptions
 }
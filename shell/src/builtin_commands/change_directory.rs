@@ -1,27 +1,179 @@
-use anyhow::{Context, Result, bail};
+use anyhow::Result;
 use std::{
-    env::{home_dir, set_current_dir},
-    path::Path,
+    env::{current_dir, set_current_dir},
+    io::{self, Write},
+    os::unix::fs::MetadataExt,
+    path::{Component, Path, PathBuf},
 };
 
-pub fn change_directory(arguments: &[String], stderr: &mut Vec<String>) -> Result<()> {
-    let Some(home_directory) = home_dir() else {
-        bail!("Error: you don't seem to have a home directory");
+use crate::{errors::io_error_message, shell_state::ShellState};
+
+/// `cd [-L|-P] [directory]`: changes the working directory and updates `PWD`
+/// and `OLDPWD`.
+///
+/// - without a directory it goes to `$HOME`, and `cd -` to `$OLDPWD`
+/// - a relative directory not starting with `.` or `..` is looked for in
+///   each directory of `CDPATH` first
+/// - with `-L`, the default, `..` removes the last component of the logical
+///   path in `PWD` so symbolic links are followed back the way they came,
+///   while `-P` resolves every link and sets `PWD` to the physical path
+pub fn change_directory(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut physical = false;
+    let mut arguments = arguments;
+
+    while let Some((option, rest)) = arguments.split_first() {
+        match option.as_str() {
+            "-L" => physical = false,
+            "-P" => physical = true,
+            "--" => {
+                arguments = rest;
+                break;
+            }
+            option if option.starts_with('-') && option.len() > 1 => {
+                writeln!(stderr, "cd: {option}: invalid option")?;
+                writeln!(stderr, "cd: usage: cd [-L|-P] [dir]")?;
+                return Ok(2);
+            }
+            _ => break,
+        }
+        arguments = rest;
+    }
+
+    let (target, mut print) = match arguments {
+        [] => match state.get_variable("HOME") {
+            Some(home) => (home, false),
+            None => {
+                writeln!(stderr, "cd: HOME not set")?;
+                return Ok(1);
+            }
+        },
+        [directory] if directory == "-" => match state.get_variable("OLDPWD") {
+            Some(previous) => (previous, true),
+            None => {
+                writeln!(stderr, "cd: OLDPWD not set")?;
+                return Ok(1);
+            }
+        },
+        [directory] => (directory.clone(), false),
+        _ => {
+            writeln!(stderr, "cd: too many arguments")?;
+            return Ok(1);
+        }
     };
-    let Some(target_path) = arguments.first() else {
-        std::env::set_current_dir(home_directory).context("changing to home directory")?;
-        return Ok(());
+
+    let directory = match search_cdpath(&target, physical, state) {
+        Some(found) => {
+            print = true;
+            found
+        }
+        None => target.clone(),
     };
-    let target_path = Path::new(&target_path);
 
-    if target_path.is_dir() {
-        set_current_dir(target_path).context("Changing to target directory")?;
-    } else {
-        stderr.push(format!(
-            "cd: {}: No such file or directory",
-            target_path.to_str().unwrap_or_default()
-        ));
+    if let Err(error) = change_directory_to(&directory, physical, state) {
+        writeln!(stderr, "cd: {target}: {}", io_error_message(&error))?;
+        return Ok(1);
+    }
+
+    if print {
+        writeln!(stdout, "{}", current_directory(state).display())?;
     }
 
+    Ok(0)
+}
+
+/// Changes to `target`, relative to the logical working directory unless
+/// `physical` is set, and updates `PWD` and `OLDPWD`.
+pub fn change_directory_to(target: &str, physical: bool, state: &mut ShellState) -> io::Result<()> {
+    let previous = current_directory(state);
+
+    let directory = if physical {
+        set_current_dir(target)?;
+        current_dir()?
+    } else {
+        let logical = logical_path(&previous, Path::new(target));
+        match set_current_dir(&logical) {
+            Ok(()) => logical,
+            // `..` can lead somewhere that doesn't exist when the logical
+            // path went through a link, so fall back to the physical one
+            Err(error) => {
+                set_current_dir(target).map_err(|_| error)?;
+                current_dir()?
+            }
+        }
+    };
+
+    state.export_variable("OLDPWD", Some(previous.to_string_lossy().into_owned()));
+    state.export_variable("PWD", Some(directory.to_string_lossy().into_owned()));
+
     Ok(())
 }
+
+/// The logical working directory: `PWD` when it names the directory the
+/// shell is actually in, otherwise the physical path.
+pub fn current_directory(state: &ShellState) -> PathBuf {
+    let physical = current_dir().unwrap_or_default();
+
+    match state.get_variable("PWD").map(PathBuf::from) {
+        Some(pwd) if pwd.is_absolute() && is_same_file(&pwd, &physical) => pwd,
+        _ => physical,
+    }
+}
+
+/// Looks for `target` under each directory of `CDPATH`, an empty entry
+/// meaning the working directory. Returns the directory found, only when it
+/// came from a non-empty entry.
+fn search_cdpath(target: &str, physical: bool, state: &ShellState) -> Option<String> {
+    let first = Path::new(target).components().next();
+    if !matches!(first, Some(Component::Normal(_))) {
+        return None;
+    }
+
+    let cdpath = state.get_variable("CDPATH")?;
+    let base = current_directory(state);
+
+    for entry in cdpath.split(':') {
+        let candidate = Path::new(if entry.is_empty() { "." } else { entry }).join(target);
+        let is_directory = if physical {
+            candidate.is_dir()
+        } else {
+            logical_path(&base, &candidate).is_dir()
+        };
+
+        if is_directory {
+            return (!entry.is_empty()).then(|| candidate.to_string_lossy().into_owned());
+        }
+    }
+
+    None
+}
+
+/// `target` resolved against `base` without looking at the file system, so
+/// that `..` removes the previous component even if it was a link.
+fn logical_path(base: &Path, target: &Path) -> PathBuf {
+    let mut path = base.to_path_buf();
+
+    for component in target.components() {
+        match component {
+            Component::RootDir => path = PathBuf::from("/"),
+            Component::ParentDir => {
+                path.pop();
+            }
+            Component::Normal(name) => path.push(name),
+            Component::CurDir | Component::Prefix(_) => (),
+        }
+    }
+
+    path
+}
+
+fn is_same_file(first: &Path, second: &Path) -> bool {
+    match (first.metadata(), second.metadata()) {
+        (Ok(first), Ok(second)) => first.dev() == second.dev() && first.ino() == second.ino(),
+        _ => false,
+    }
+}
@@ -0,0 +1,245 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{
+    builtin_commands::change_directory::{change_directory_to, current_directory},
+    errors::io_error_message,
+    shell_state::ShellState,
+};
+
+/// `pushd [directory | +N | -N]`: changes to `directory` and pushes the old
+/// working directory onto the stack. Without an argument the top two
+/// entries are swapped, and `+N`/`-N` rotate the stack so that the Nth
+/// entry from the left or right comes to the top. Prints the stack
+/// afterwards.
+pub fn pushd(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut stack = full_stack(state);
+
+    match arguments {
+        [] => {
+            if stack.len() < 2 {
+                writeln!(stderr, "pushd: no other directory")?;
+                return Ok(1);
+            }
+            stack.swap(0, 1);
+        }
+        [argument] if is_stack_index(argument) => {
+            let Some(index) = stack_index(argument, stack.len()) else {
+                writeln!(
+                    stderr,
+                    "pushd: {argument}: directory stack index out of range"
+                )?;
+                return Ok(1);
+            };
+            stack.rotate_left(index);
+        }
+        [directory] => {
+            if let Err(error) = change_directory_to(directory, false, state) {
+                writeln!(stderr, "pushd: {directory}: {}", io_error_message(&error))?;
+                return Ok(1);
+            }
+            stack.insert(0, current_directory(state).to_string_lossy().into_owned());
+        }
+        _ => {
+            writeln!(stderr, "pushd: too many arguments")?;
+            return Ok(1);
+        }
+    }
+
+    set_full_stack(stack, state, stdout, stderr, "pushd")
+}
+
+/// `popd [+N | -N]`: removes the top of the stack and changes to the new
+/// top, or removes the Nth entry from the left or right. Prints the stack
+/// afterwards.
+pub fn popd(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut stack = full_stack(state);
+
+    if stack.len() < 2 {
+        writeln!(stderr, "popd: directory stack empty")?;
+        return Ok(1);
+    }
+
+    let index = match arguments {
+        [] => 0,
+        [argument] if is_stack_index(argument) => match stack_index(argument, stack.len()) {
+            Some(index) => index,
+            None => {
+                writeln!(
+                    stderr,
+                    "popd: {argument}: directory stack index out of range"
+                )?;
+                return Ok(1);
+            }
+        },
+        [argument] => {
+            writeln!(stderr, "popd: {argument}: invalid argument")?;
+            writeln!(stderr, "popd: usage: popd [+N | -N]")?;
+            return Ok(2);
+        }
+        _ => {
+            writeln!(stderr, "popd: too many arguments")?;
+            return Ok(1);
+        }
+    };
+    stack.remove(index);
+
+    set_full_stack(stack, state, stdout, stderr, "popd")
+}
+
+/// `dirs [-clpv] [+N | -N]`: prints the directory stack, starting with the
+/// working directory.
+///
+/// - `-c` clears the stack
+/// - `-l` doesn't shorten the home directory to `~`
+/// - `-p` prints one entry per line, and `-v` numbers them too
+/// - `+N`/`-N` print only the Nth entry from the left or right
+pub fn dirs(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut long = false;
+    let mut per_line = false;
+    let mut numbered = false;
+    let mut only = None;
+
+    for argument in arguments {
+        if is_stack_index(argument) {
+            only = Some(argument);
+            continue;
+        }
+
+        let Some(flags) = argument.strip_prefix('-').filter(|flags| !flags.is_empty()) else {
+            writeln!(stderr, "dirs: {argument}: invalid argument")?;
+            writeln!(stderr, "dirs: usage: dirs [-clpv] [+N] [-N]")?;
+            return Ok(2);
+        };
+        for flag in flags.chars() {
+            match flag {
+                'c' => state.directory_stack.clear(),
+                'l' => long = true,
+                'p' => per_line = true,
+                'v' => {
+                    per_line = true;
+                    numbered = true;
+                }
+                _ => {
+                    writeln!(stderr, "dirs: -{flag}: invalid option")?;
+                    writeln!(stderr, "dirs: usage: dirs [-clpv] [+N] [-N]")?;
+                    return Ok(2);
+                }
+            }
+        }
+    }
+
+    let stack: Vec<String> = full_stack(state)
+        .into_iter()
+        .map(|directory| {
+            if long {
+                directory
+            } else {
+                shorten_home(directory)
+            }
+        })
+        .collect();
+
+    if let Some(argument) = only {
+        let Some(index) = stack_index(argument, stack.len()) else {
+            writeln!(
+                stderr,
+                "dirs: {argument}: directory stack index out of range"
+            )?;
+            return Ok(1);
+        };
+        writeln!(stdout, "{}", stack[index])?;
+        return Ok(0);
+    }
+
+    if !per_line {
+        writeln!(stdout, "{}", stack.join(" "))?;
+        return Ok(0);
+    }
+
+    for (index, directory) in stack.iter().enumerate() {
+        if numbered {
+            writeln!(stdout, "{index:2}  {directory}")?;
+        } else {
+            writeln!(stdout, "{directory}")?;
+        }
+    }
+
+    Ok(0)
+}
+
+/// The working directory followed by the saved directories.
+fn full_stack(state: &ShellState) -> Vec<String> {
+    let working_directory = current_directory(state).to_string_lossy().into_owned();
+
+    std::iter::once(working_directory)
+        .chain(state.directory_stack.iter().cloned())
+        .collect()
+}
+
+/// Makes `stack` the directory stack, changing to its first entry if that
+/// isn't already the working directory, and prints it like `dirs`.
+fn set_full_stack(
+    mut stack: Vec<String>,
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+    builtin: &str,
+) -> Result<i32> {
+    let top = stack.remove(0);
+
+    if top != current_directory(state).to_string_lossy()
+        && let Err(error) = change_directory_to(&top, false, state)
+    {
+        writeln!(stderr, "{builtin}: {top}: {}", io_error_message(&error))?;
+        return Ok(1);
+    }
+    state.directory_stack = stack;
+
+    dirs(&[], state, stdout, stderr)
+}
+
+fn is_stack_index(argument: &str) -> bool {
+    argument
+        .strip_prefix(['+', '-'])
+        .is_some_and(|digits| !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// The position in a stack of `len` entries that `+N` (counting from the
+/// left, from zero) or `-N` (from the right) refers to.
+fn stack_index(argument: &str, len: usize) -> Option<usize> {
+    let n: usize = argument[1..].parse().ok()?;
+
+    if argument.starts_with('+') {
+        (n < len).then_some(n)
+    } else {
+        len.checked_sub(n + 1)
+    }
+}
+
+fn shorten_home(directory: String) -> String {
+    let Some(home) = std::env::home_dir().filter(|home| home.as_os_str() != "/") else {
+        return directory;
+    };
+    let home = home.to_string_lossy();
+
+    match directory.strip_prefix(home.as_ref()) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("~{rest}"),
+        _ => directory,
+    }
+}
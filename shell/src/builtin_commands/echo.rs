@@ -1,29 +1,22 @@
 use std::{fmt::Display, io::Write};
 
-use anyhow::{Context, Result};
+use anyhow::Result;
 
-pub fn echo(
-    user_input: &[impl Display],
-    stdout: &mut Vec<String>,
-    stderr: &mut Vec<String>,
-) -> Result<()> {
+pub fn echo(user_input: &[impl Display], stdout: &mut impl Write) -> Result<i32> {
     let mut inputs = user_input.iter();
     let mut buffer = vec![];
     if let Some(input) = inputs.next() {
-        // print!("{input}");
-        if let Err(error) = write!(&mut buffer, "{input}") {
-            stderr.push(format!("{error}"));
-        };
+        write!(&mut buffer, "{input}")?;
     }
 
     for input in inputs {
-        // print!(" {input}");
-        if let Err(error) = write!(buffer, " {input}") {
-            stderr.push(format!("{error}"));
-        };
+        write!(buffer, " {input}")?;
     }
 
-    stdout.push(String::from_utf8(buffer).context("Converting input from buffer to String")?);
+    buffer.push(b'\n');
 
-    Ok(())
+    stdout.write_all(&buffer)?;
+    stdout.flush()?;
+
+    Ok(0)
 }
@@ -0,0 +1,55 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{
+    builtin_commands::run_external_executable::run_external_executable,
+    errors::CustomError, input_parser::is_valid_name, shell_state::ShellState,
+    utils::{find_executable_file, split_path},
+};
+
+/// `env [NAME=value]... [command [argument]...]`: prints the environment, or
+/// runs an external command with extra variables added to it.
+pub fn env(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut environment = state.environment();
+    let mut arguments = arguments.iter();
+    let mut command = None;
+
+    for argument in arguments.by_ref() {
+        match argument.split_once('=') {
+            Some((name, value)) if is_valid_name(name) => {
+                environment.retain(|(existing, _)| existing != name);
+                environment.push((name.to_owned(), value.to_owned()));
+            }
+            _ => {
+                command = Some(argument);
+                break;
+            }
+        }
+    }
+
+    let Some(command) = command else {
+        for (name, value) in environment {
+            writeln!(stdout, "{name}={value}")?;
+        }
+        return Ok(0);
+    };
+
+    // the command is looked up in the `PATH` it will run with
+    let path = environment
+        .iter()
+        .find(|(name, _)| name == "PATH")
+        .map(|(_, value)| split_path(value))
+        .unwrap_or_default();
+    let Some(executable) = find_executable_file(command, &path) else {
+        writeln!(stderr, "env: {}", CustomError::CommandNotFound(command.clone()))?;
+        return Ok(127);
+    };
+    let arguments: Vec<String> = arguments.cloned().collect();
+
+    run_external_executable(&executable, &arguments, environment, &mut state.jobs)
+}
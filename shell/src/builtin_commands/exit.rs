@@ -0,0 +1,19 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::ShellState;
+
+/// `exit [n]`: stops the shell with status `n`, or the status of the last
+/// command.
+pub fn exit(arguments: &[String], state: &mut ShellState, stderr: &mut impl Write) -> Result<i32> {
+    state.should_exit = true;
+
+    match arguments.first().map(|argument| argument.parse::<i32>()) {
+        None => Ok(state.last_exit_status),
+        Some(Ok(status)) => Ok(status & 0xff),
+        Some(Err(_)) => {
+            writeln!(stderr, "exit: {}: numeric argument required", arguments[0])?;
+            Ok(2)
+        }
+    }
+}
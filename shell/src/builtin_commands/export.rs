@@ -0,0 +1,36 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{input_parser::is_valid_name, shell_state::ShellState};
+
+pub fn export(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut status = 0;
+
+    if arguments.is_empty() || arguments.iter().all(|argument| argument == "-p") {
+        for (name, value) in state.environment() {
+            writeln!(stdout, "export {name}=\"{value}\"")?;
+        }
+        return Ok(status);
+    }
+
+    for argument in arguments.iter().filter(|argument| *argument != "-p") {
+        let (name, value) = match argument.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_owned())),
+            None => (argument.as_str(), None),
+        };
+
+        if is_valid_name(name) {
+            state.export_variable(name, value);
+        } else {
+            writeln!(stderr, "export: `{argument}': not a valid identifier")?;
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
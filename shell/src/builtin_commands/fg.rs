@@ -0,0 +1,31 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{process::send_signal, shell_state::ShellState};
+
+pub fn fg(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let spec = arguments.first().map(String::as_str);
+    let index = match state.jobs.find(spec) {
+        Ok(index) => index,
+        Err(error) => {
+            writeln!(stderr, "fg: {}: {error}", spec.unwrap_or("current"))?;
+            return Ok(1);
+        }
+    };
+
+    let mut job = state.jobs.jobs.remove(index);
+    writeln!(stdout, "{}", job.text)?;
+    stdout.flush()?;
+
+    if job.is_stopped() {
+        send_signal(-job.pgid, libc::SIGCONT)?;
+        job.mark_running();
+    }
+
+    state.jobs.wait_foreground(job)
+}
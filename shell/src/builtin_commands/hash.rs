@@ -0,0 +1,74 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{builtin_commands::BuiltinCommand, shell_state::ShellState};
+
+/// `hash [-r] [name...]`: lists the remembered locations of commands, forgets
+/// them all with `-r`, or looks names up on the path and remembers them.
+pub fn hash(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut names = arguments;
+
+    while let Some((option, rest)) = names.split_first() {
+        match option.as_str() {
+            "-r" => state.command_hash.clear(),
+            "--" => {
+                names = rest;
+                break;
+            }
+            option if option.starts_with('-') && option.len() > 1 => {
+                writeln!(stderr, "hash: {option}: invalid option")?;
+                writeln!(stderr, "hash: usage: hash [-r] [name ...]")?;
+                return Ok(2);
+            }
+            _ => break,
+        }
+        names = rest;
+    }
+
+    if arguments.is_empty() {
+        return list(state, stdout, stderr);
+    }
+
+    let mut status = 0;
+
+    for name in names {
+        // builtins are never looked up on the path, so there is nothing to
+        // remember
+        let is_builtin = !matches!(
+            BuiltinCommand::from(name.clone()),
+            BuiltinCommand::NotFound(_, _)
+        );
+        if is_builtin || name.contains('/') {
+            continue;
+        }
+
+        let paths = state.path().to_vec();
+        if state.command_hash.remember(name, &paths).is_none() {
+            writeln!(stderr, "hash: {name}: not found")?;
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
+
+fn list(state: &ShellState, stdout: &mut impl Write, stderr: &mut impl Write) -> Result<i32> {
+    let entries = state.command_hash.entries();
+
+    if entries.is_empty() {
+        writeln!(stderr, "hash: hash table empty")?;
+        return Ok(0);
+    }
+
+    writeln!(stdout, "hits\tcommand")?;
+    for (_, entry) in entries {
+        writeln!(stdout, "{:4}\t{}", entry.hits, entry.path.display())?;
+    }
+
+    Ok(0)
+}
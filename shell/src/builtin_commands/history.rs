@@ -0,0 +1,37 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::ShellState;
+
+/// `history [-c] [count]`: lists previously entered lines, numbered.
+pub fn history(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let entries = state.history.entries();
+    let mut count = entries.len();
+
+    match arguments.first().map(String::as_str) {
+        Some("-c") => {
+            state.history.clear();
+            return Ok(0);
+        }
+        Some(argument) => match argument.parse::<usize>() {
+            Ok(requested) => count = requested.min(count),
+            Err(_) => {
+                writeln!(stderr, "history: {argument}: numeric argument required")?;
+                return Ok(1);
+            }
+        },
+        None => (),
+    }
+
+    let start = entries.len() - count;
+    for (index, entry) in entries.iter().enumerate().skip(start) {
+        writeln!(stdout, "{:>5}  {entry}", index + 1)?;
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,24 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::ShellState;
+
+pub fn jobs(arguments: &[String], state: &mut ShellState, stdout: &mut impl Write) -> Result<i32> {
+    let with_pid = arguments.iter().any(|argument| argument == "-l");
+    let only_pids = arguments.iter().any(|argument| argument == "-p");
+
+    state.jobs.reap();
+
+    let mut listed: Vec<_> = state.jobs.jobs.iter().collect();
+    listed.sort_by_key(|job| job.id);
+
+    for job in listed {
+        if only_pids {
+            writeln!(stdout, "{}", job.pgid)?;
+        } else {
+            writeln!(stdout, "{}", state.jobs.describe(job, with_pid))?;
+        }
+    }
+
+    Ok(0)
+}
@@ -0,0 +1,105 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{
+    process::{SIGNALS, send_signal, signal_from_name, signal_name},
+    shell_state::ShellState,
+};
+
+/// `kill [-s SIGNAL | -SIGNAL] pid|%job...` and `kill -l [status]`.
+pub fn kill(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut arguments = arguments.iter().peekable();
+    let mut signal = libc::SIGTERM;
+
+    match arguments.peek().map(|argument| argument.as_str()) {
+        Some("-l") | Some("-L") => {
+            arguments.next();
+            return list_signals(arguments.next(), stdout, stderr);
+        }
+        Some("-s") | Some("-n") => {
+            arguments.next();
+            let name = arguments.next().map(String::as_str).unwrap_or_default();
+            let Some(number) = signal_from_name(name) else {
+                writeln!(stderr, "kill: {name}: invalid signal specification")?;
+                return Ok(1);
+            };
+            signal = number;
+        }
+        Some(option) if option.starts_with('-') && option.len() > 1 => {
+            arguments.next();
+            let Some(number) = signal_from_name(&option[1..]) else {
+                writeln!(stderr, "kill: {}: invalid signal specification", &option[1..])?;
+                return Ok(1);
+            };
+            signal = number;
+        }
+        _ => (),
+    }
+
+    if arguments.peek().is_none() {
+        writeln!(stderr, "kill: usage: kill [-s sigspec | -sigspec] pid | jobspec ...")?;
+        return Ok(2);
+    }
+
+    let mut status = 0;
+
+    for target in arguments {
+        let pid = if target.starts_with('%') {
+            match state.jobs.find(Some(target)) {
+                Ok(index) if state.jobs.job_control => -state.jobs.jobs[index].pgid,
+                Ok(index) => state.jobs.jobs[index].pgid,
+                Err(error) => {
+                    writeln!(stderr, "kill: {target}: {error}")?;
+                    status = 1;
+                    continue;
+                }
+            }
+        } else if let Ok(pid) = target.parse() {
+            pid
+        } else {
+            writeln!(stderr, "kill: {target}: arguments must be process or job IDs")?;
+            status = 1;
+            continue;
+        };
+
+        if let Err(error) = send_signal(pid, signal) {
+            writeln!(stderr, "kill: {error} - {}", error.root_cause())?;
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
+
+fn list_signals(
+    status: Option<&String>,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let Some(status) = status else {
+        let names: Vec<_> = SIGNALS.iter().map(|(name, _)| *name).collect();
+        writeln!(stdout, "{}", names.join(" "))?;
+        return Ok(0);
+    };
+
+    let name = status
+        .parse::<libc::c_int>()
+        .ok()
+        .and_then(|number| signal_name(if number > 128 { number - 128 } else { number }));
+
+    match name {
+        Some(name) => {
+            writeln!(stdout, "{name}")?;
+            Ok(0)
+        }
+        None => {
+            writeln!(stderr, "kill: {status}: invalid signal specification")?;
+            Ok(1)
+        }
+    }
+}
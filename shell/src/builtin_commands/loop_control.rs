@@ -0,0 +1,50 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::shell_state::{ControlFlow, ShellState};
+
+/// `break [n]`: leaves the innermost `n` loops.
+pub fn builtin_break(
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    loop_control("break", arguments, state, stderr, ControlFlow::Break)
+}
+
+/// `continue [n]`: starts the next iteration of the `n`th enclosing loop.
+pub fn builtin_continue(
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    loop_control("continue", arguments, state, stderr, ControlFlow::Continue)
+}
+
+fn loop_control(
+    name: &str,
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+    control_flow: fn(usize) -> ControlFlow,
+) -> Result<i32> {
+    let levels = match arguments.first().map(|argument| argument.parse::<usize>()) {
+        None => 1,
+        Some(Ok(levels)) if levels > 0 => levels,
+        Some(_) => {
+            writeln!(stderr, "{name}: {}: loop count out of range", arguments[0])?;
+            return Ok(1);
+        }
+    };
+
+    if state.loop_depth == 0 {
+        writeln!(
+            stderr,
+            "{name}: only meaningful in a `for', `while', or `until' loop"
+        )?;
+        return Ok(0);
+    }
+
+    state.control_flow = Some(control_flow(levels.min(state.loop_depth)));
+    Ok(0)
+}
@@ -0,0 +1,819 @@
+use anyhow::Result;
+use std::{io::Write, iter::Peekable, num::IntErrorKind, str::Chars};
+
+/// `printf format [argument...]`: writes the arguments as described by the
+/// format, reusing the format until every argument has been used.
+///
+/// The format understands the escapes `\\ \a \b \e \f \n \r \t \v \"`,
+/// `\NNN` in octal and `\xHH` in hexadecimal, and conversions made of `%`,
+/// any of the flags `-+ #0`, a width, a `.precision` (either of which can be
+/// `*` to take it from an argument) and one of
+///
+/// - `s` a string, `b` a string with escapes expanded where `\c` stops all
+///   further output, `c` the first character of a string
+/// - `d` or `i` a signed integer, `u` unsigned, `o` octal, `x`/`X` hexadecimal
+/// - `f`, `e`/`E` and `g`/`G` a floating point number
+/// - `%` a percent sign
+///
+/// Numeric arguments can be written in decimal, octal with a leading `0`,
+/// hexadecimal with a leading `0x`, or as a quote followed by a character
+/// for its code. Integers that don't fit are clamped with a warning.
+pub fn printf(
+    arguments: &[String],
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let arguments = match arguments.first() {
+        Some(first) if first == "--" => &arguments[1..],
+        _ => arguments,
+    };
+    let Some((format, arguments)) = arguments.split_first() else {
+        writeln!(stderr, "printf: usage: printf format [arguments]")?;
+        return Ok(2);
+    };
+
+    let mut formatter = Formatter {
+        arguments,
+        next_argument: 0,
+        output: vec![],
+        messages: vec![],
+        failed: false,
+        stopped: false,
+    };
+
+    loop {
+        let used_before = formatter.next_argument;
+        formatter.format(format);
+
+        let used_none = formatter.next_argument == used_before;
+        if formatter.stopped || used_none || formatter.next_argument >= arguments.len() {
+            break;
+        }
+    }
+
+    stdout.write_all(&formatter.output)?;
+    stdout.flush()?;
+
+    for message in &formatter.messages {
+        writeln!(stderr, "printf: {message}")?;
+    }
+
+    Ok(if formatter.failed { 1 } else { 0 })
+}
+
+/// The largest width or precision, so that a mistyped one is an error
+/// rather than an attempt to build a string that exhausts memory.
+const MAXIMUM_FIELD: u64 = 1 << 20;
+
+struct Formatter<'a> {
+    arguments: &'a [String],
+    next_argument: usize,
+    output: Vec<u8>,
+    /// Errors and warnings, printed after the output.
+    messages: Vec<String>,
+    /// Set by an error, which makes the status 1. Warnings don't set it.
+    failed: bool,
+    /// Set by `\c` in a `%b` argument.
+    stopped: bool,
+}
+
+/// The flags, width and precision of a conversion.
+#[derive(Default)]
+struct Spec {
+    left: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    zero: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl<'a> Formatter<'a> {
+    fn format(&mut self, format: &str) {
+        let mut characters = format.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            if self.stopped {
+                return;
+            }
+
+            match character {
+                '\\' => {
+                    let escape = parse_escape(&mut characters, false);
+                    self.output.extend(escape.unwrap_or_default());
+                }
+                '%' => self.convert(&mut characters),
+                character => self.push_str(character.encode_utf8(&mut [0; 4])),
+            }
+        }
+    }
+
+    /// Formats one `%` conversion, with the `%` already read.
+    fn convert(&mut self, characters: &mut Peekable<Chars>) {
+        let mut spec = Spec::default();
+
+        while let Some(&flag) = characters.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                '0' => spec.zero = true,
+                _ => break,
+            }
+            characters.next();
+        }
+
+        let Some(width) = self.read_number(characters, "field width") else {
+            self.stopped = true;
+            return;
+        };
+        if width < 0 {
+            spec.left = true;
+        }
+        spec.width = width.unsigned_abs() as usize;
+
+        if characters.next_if_eq(&'.').is_some() {
+            let Some(precision) = self.read_number(characters, "precision") else {
+                self.stopped = true;
+                return;
+            };
+            // a negative precision from `*` counts as none, as in C
+            spec.precision = usize::try_from(precision).ok();
+        }
+
+        let Some(conversion) = characters.next() else {
+            self.push_str("%");
+            return;
+        };
+
+        // zeros only pad numbers, and not integers given a precision
+        let integer_zero_fill = spec.zero && spec.precision.is_none();
+        let (text, zero_fill) = match conversion {
+            '%' => ("%".to_owned(), false),
+            's' => {
+                let argument = self.next_argument().unwrap_or_default();
+                (truncate(argument, spec.precision).to_owned(), false)
+            }
+            'b' => {
+                let argument = self.next_argument().unwrap_or_default();
+                let expanded = self.expand_escapes(argument);
+                let text = String::from_utf8_lossy(&expanded).into_owned();
+                (truncate(&text, spec.precision).to_owned(), false)
+            }
+            'c' => {
+                let character = self
+                    .next_argument()
+                    .and_then(|argument| argument.chars().next());
+                (character.map(String::from).unwrap_or_default(), false)
+            }
+            'd' | 'i' => {
+                let value = self.integer_argument();
+                let text = format_integer(value < 0, value.unsigned_abs(), conversion, &spec);
+                (text, integer_zero_fill)
+            }
+            'u' | 'o' | 'x' | 'X' => {
+                let value = self.unsigned_argument();
+                (
+                    format_integer(false, value, conversion, &spec),
+                    integer_zero_fill,
+                )
+            }
+            'f' | 'F' | 'e' | 'E' | 'g' | 'G' => {
+                let value = self.float_argument();
+                (
+                    format_float(value, conversion, &spec),
+                    spec.zero && value.is_finite(),
+                )
+            }
+            other => {
+                self.error(format!("%{other}: invalid format character"));
+                self.stopped = true;
+                return;
+            }
+        };
+
+        self.push_str(&pad(&text, &spec, zero_fill));
+    }
+
+    /// A width or precision: digits, or `*` for the next argument. Reports
+    /// one larger than `MAXIMUM_FIELD` as an invalid `what` and returns
+    /// `None`.
+    fn read_number(&mut self, characters: &mut Peekable<Chars>, what: &str) -> Option<i64> {
+        let (text, number) = if characters.next_if_eq(&'*').is_some() {
+            let text = self.arguments.get(self.next_argument).cloned();
+            (text.unwrap_or_default(), self.integer_argument())
+        } else {
+            let mut text = String::new();
+            while let Some(digit) = characters.next_if(char::is_ascii_digit) {
+                text.push(digit);
+            }
+            let number = if text.is_empty() {
+                0
+            } else {
+                text.parse().unwrap_or(i64::MAX)
+            };
+            (text, number)
+        };
+
+        if number.unsigned_abs() > MAXIMUM_FIELD {
+            self.error(format!("{text}: invalid {what}"));
+            return None;
+        }
+
+        Some(number)
+    }
+
+    fn next_argument(&mut self) -> Option<&'a str> {
+        let argument = self.arguments.get(self.next_argument)?;
+        self.next_argument += 1;
+
+        Some(argument)
+    }
+
+    fn integer_argument(&mut self) -> i64 {
+        let Some((argument, value)) = self.parsed_integer_argument() else {
+            return 0;
+        };
+
+        i64::try_from(value).unwrap_or_else(|_| {
+            self.out_of_range(argument);
+            if value < 0 { i64::MIN } else { i64::MAX }
+        })
+    }
+
+    /// An argument for an unsigned conversion, where a negative value wraps
+    /// around as in C so that `-1` is the largest value.
+    fn unsigned_argument(&mut self) -> u64 {
+        let Some((argument, value)) = self.parsed_integer_argument() else {
+            return 0;
+        };
+
+        if value.unsigned_abs() > u64::MAX.into() {
+            self.out_of_range(argument);
+            return u64::MAX;
+        }
+        value as u64
+    }
+
+    /// The next argument and its value, reporting it and returning `None`
+    /// when it isn't a number.
+    fn parsed_integer_argument(&mut self) -> Option<(&'a str, i128)> {
+        let argument = self.next_argument()?;
+
+        match parse_integer(argument) {
+            Some(value) => Some((argument, value)),
+            None => {
+                self.error(format!("{argument}: invalid number"));
+                None
+            }
+        }
+    }
+
+    fn out_of_range(&mut self, argument: &str) {
+        let warning = format!("warning: {argument}: Numerical result out of range");
+        self.messages.push(warning);
+    }
+
+    fn float_argument(&mut self) -> f64 {
+        let Some(argument) = self.next_argument() else {
+            return 0.0;
+        };
+
+        let value = argument
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .or_else(|| parse_integer(argument).map(|value| value as f64));
+        match value {
+            Some(value) => value,
+            None => {
+                self.error(format!("{argument}: invalid number"));
+                0.0
+            }
+        }
+    }
+
+    fn error(&mut self, message: String) {
+        self.messages.push(message);
+        self.failed = true;
+    }
+
+    /// Expands the escapes in a `%b` argument, stopping all output at `\c`.
+    fn expand_escapes(&mut self, text: &str) -> Vec<u8> {
+        let mut expanded = vec![];
+        let mut characters = text.chars().peekable();
+
+        while let Some(character) = characters.next() {
+            if character != '\\' {
+                expanded.extend(character.encode_utf8(&mut [0; 4]).as_bytes());
+                continue;
+            }
+
+            match parse_escape(&mut characters, true) {
+                Some(bytes) => expanded.extend(bytes),
+                None => {
+                    self.stopped = true;
+                    break;
+                }
+            }
+        }
+
+        expanded
+    }
+
+    fn push_str(&mut self, text: &str) {
+        self.output.extend(text.as_bytes());
+    }
+}
+
+/// Reads the escape after a backslash and returns the bytes it stands for,
+/// or `None` for `\c`, which only means something in `%b` arguments. In
+/// those, octal escapes are written `\0NNN`.
+fn parse_escape(characters: &mut Peekable<Chars>, in_argument: bool) -> Option<Vec<u8>> {
+    let Some(character) = characters.next() else {
+        return Some(b"\\".to_vec());
+    };
+
+    let byte = match character {
+        '\\' => b'\\',
+        'a' => 0x07,
+        'b' => 0x08,
+        'e' | 'E' => 0x1b,
+        'f' => 0x0c,
+        'n' => b'\n',
+        'r' => b'\r',
+        't' => b'\t',
+        'v' => 0x0b,
+        '"' => b'"',
+        '\'' => b'\'',
+        'c' if in_argument => return None,
+        '0'..='7' => {
+            let mut value = if in_argument && character == '0' {
+                0
+            } else {
+                character.to_digit(8).unwrap_or_default()
+            };
+            let limit = if in_argument && character == '0' {
+                3
+            } else {
+                2
+            };
+
+            for _ in 0..limit {
+                let Some(digit) = characters.peek().and_then(|digit| digit.to_digit(8)) else {
+                    break;
+                };
+                value = value * 8 + digit;
+                characters.next();
+            }
+            value as u8
+        }
+        'x' => {
+            let mut value = 0;
+            let mut digits = 0;
+
+            while digits < 2
+                && let Some(digit) = characters.peek().and_then(|digit| digit.to_digit(16))
+            {
+                value = value * 16 + digit;
+                digits += 1;
+                characters.next();
+            }
+            if digits == 0 {
+                return Some(b"\\x".to_vec());
+            }
+            value as u8
+        }
+        other => return Some(format!("\\{other}").into_bytes()),
+    };
+
+    Some(vec![byte])
+}
+
+/// Parses a numeric argument: decimal, octal with a leading `0`, hexadecimal
+/// with a leading `0x`, or the code of the character after a quote. Values
+/// too large for 64 bits come back as one more than `u64::MAX`, with their
+/// sign, so that they are out of range for every conversion.
+fn parse_integer(text: &str) -> Option<i128> {
+    let text = text.trim_start();
+
+    if let Some(quoted) = text.strip_prefix(['\'', '"']) {
+        return Some(
+            quoted
+                .chars()
+                .next()
+                .map_or(0, |character| character as i128),
+        );
+    }
+    if text.is_empty() {
+        return Some(0);
+    }
+
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+    let (digits, radix) = if let Some(hex) = digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        (hex, 16)
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        (&digits[1..], 8)
+    } else {
+        (digits, 10)
+    };
+    let value = match u64::from_str_radix(digits, radix) {
+        Ok(value) => i128::from(value),
+        Err(error) if *error.kind() == IntErrorKind::PosOverflow => i128::from(u64::MAX) + 1,
+        Err(_) => return None,
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+fn truncate(text: &str, precision: Option<usize>) -> &str {
+    match precision.and_then(|precision| text.char_indices().nth(precision)) {
+        Some((end, _)) => &text[..end],
+        None => text,
+    }
+}
+
+fn format_integer(negative: bool, value: u64, conversion: char, spec: &Spec) -> String {
+    let mut digits = match conversion {
+        'o' => format!("{value:o}"),
+        'x' => format!("{value:x}"),
+        'X' => format!("{value:X}"),
+        _ => value.to_string(),
+    };
+
+    match spec.precision {
+        Some(0) if value == 0 => digits.clear(),
+        Some(precision) if digits.len() < precision => {
+            digits = "0".repeat(precision - digits.len()) + &digits;
+        }
+        _ => (),
+    }
+
+    let prefix = match conversion {
+        'd' | 'i' => sign(negative, spec),
+        'o' if spec.alternate && !digits.starts_with('0') => "0",
+        'x' if spec.alternate && value != 0 => "0x",
+        'X' if spec.alternate && value != 0 => "0X",
+        _ => "",
+    };
+
+    format!("{prefix}{digits}")
+}
+
+fn format_float(value: f64, conversion: char, spec: &Spec) -> String {
+    let precision = spec.precision.unwrap_or(6);
+    let magnitude = value.abs();
+
+    let body = if magnitude.is_infinite() {
+        "inf".to_owned()
+    } else if magnitude.is_nan() {
+        "nan".to_owned()
+    } else {
+        match conversion.to_ascii_lowercase() {
+            'f' => {
+                let text = format!("{magnitude:.precision$}");
+                if spec.alternate && precision == 0 {
+                    text + "."
+                } else {
+                    text
+                }
+            }
+            'e' => format_exponent(magnitude, precision, spec.alternate),
+            _ => format_general(magnitude, precision, spec.alternate),
+        }
+    };
+    let body = if conversion.is_ascii_uppercase() {
+        body.to_uppercase()
+    } else {
+        body
+    };
+
+    format!(
+        "{}{body}",
+        sign(value.is_sign_negative() && !value.is_nan(), spec)
+    )
+}
+
+/// `%e`: one digit before the point and an exponent of at least two digits.
+fn format_exponent(value: f64, precision: usize, alternate: bool) -> String {
+    let text = format!("{value:.precision$e}");
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or_default();
+    let point = if alternate && precision == 0 { "." } else { "" };
+    let exponent_sign = if exponent < 0 { '-' } else { '+' };
+
+    format!("{mantissa}{point}e{exponent_sign}{:02}", exponent.abs())
+}
+
+/// `%g`: `%e` for very large or small values and `%f` otherwise, with
+/// `precision` significant digits and trailing zeros removed unless
+/// `alternate` is set.
+fn format_general(value: f64, precision: usize, alternate: bool) -> String {
+    let precision = precision.max(1);
+    let rounded = format!("{value:.*e}", precision - 1);
+    let exponent: i32 = rounded
+        .split_once('e')
+        .and_then(|(_, exponent)| exponent.parse().ok())
+        .unwrap_or_default();
+
+    let text = if exponent < -4 || exponent >= precision as i32 {
+        format_exponent(value, precision - 1, alternate)
+    } else {
+        let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+        format!("{value:.decimals$}")
+    };
+
+    if alternate {
+        return text;
+    }
+
+    let (number, exponent) = match text.find('e') {
+        Some(index) => text.split_at(index),
+        None => (text.as_str(), ""),
+    };
+    let number = if number.contains('.') {
+        number.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        number
+    };
+
+    format!("{number}{exponent}")
+}
+
+fn sign(negative: bool, spec: &Spec) -> &'static str {
+    if negative {
+        "-"
+    } else if spec.plus {
+        "+"
+    } else if spec.space {
+        " "
+    } else {
+        ""
+    }
+}
+
+/// Pads `text` to the width with spaces, or with zeros after any sign or
+/// `0x` when `zero_fill` is set.
+fn pad(text: &str, spec: &Spec, zero_fill: bool) -> String {
+    let length = text.chars().count();
+    if length >= spec.width {
+        return text.to_owned();
+    }
+    let padding = spec.width - length;
+
+    if spec.left {
+        return format!("{text}{}", " ".repeat(padding));
+    }
+    if !zero_fill {
+        return format!("{}{text}", " ".repeat(padding));
+    }
+
+    let unsigned = text.trim_start_matches(['+', '-', ' ']);
+    let prefix_length = text.len() - unsigned.len()
+        + if unsigned.starts_with("0x") || unsigned.starts_with("0X") {
+            2
+        } else {
+            0
+        };
+    let (prefix, digits) = text.split_at(prefix_length);
+
+    format!("{prefix}{}{digits}", "0".repeat(padding))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `printf` with the arguments, giving its status, output and
+    /// errors.
+    fn run(arguments: &[&str]) -> (i32, String, String) {
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|&argument| argument.to_owned())
+            .collect();
+        let mut stdout = vec![];
+        let mut stderr = vec![];
+        let status = printf(&arguments, &mut stdout, &mut stderr).unwrap();
+
+        (
+            status,
+            String::from_utf8(stdout).unwrap(),
+            String::from_utf8(stderr).unwrap(),
+        )
+    }
+
+    /// The output of a `printf` that should succeed without complaint.
+    fn output(arguments: &[&str]) -> String {
+        let (status, stdout, stderr) = run(arguments);
+        assert_eq!((status, stderr.as_str()), (0, ""), "{arguments:?}");
+        stdout
+    }
+
+    #[test]
+    fn strings() {
+        assert_eq!(
+            output(&["%s|%5s|%-5s|%.2s", "a", "ab", "ab", "abcd"]),
+            "a|   ab|ab   |ab"
+        );
+        assert_eq!(output(&["%s", "a%b"]), "a%b");
+        assert_eq!(output(&["%c%c|%5c", "hello", "", "x"]), "h|    x");
+        assert_eq!(output(&["%%|%s|"]), "%||");
+        assert_eq!(output(&["--", "%s", "dash"]), "dash");
+    }
+
+    #[test]
+    fn integers() {
+        assert_eq!(
+            output(&[
+                "%05d|%-5d|%+d|% d|%.3d|%5.3d|%-+5d",
+                "42",
+                "42",
+                "42",
+                "42",
+                "42",
+                "42",
+                "3"
+            ]),
+            "00042|42   |+42| 42|042|  042|+3   "
+        );
+        assert_eq!(
+            output(&["%x %X %#x %o %#o %u", "255", "255", "255", "8", "8", "-1"]),
+            "ff FF 0xff 10 010 18446744073709551615"
+        );
+        assert_eq!(
+            output(&["%d %d %d %i", "0x1f", "010", "'A", "-0x10"]),
+            "31 8 65 -16"
+        );
+        assert_eq!(output(&["%.0d|%#x", "0", "0"]), "|0");
+        assert_eq!(output(&["%d", ""]), "0");
+    }
+
+    #[test]
+    fn floats() {
+        assert_eq!(
+            output(&[
+                "%f %.2f %e %g %g %G",
+                "1.5",
+                "3.14159",
+                "12345.678",
+                "0.0001",
+                "123456789",
+                "1e-10"
+            ]),
+            "1.500000 3.14 1.234568e+04 0.0001 1.23457e+08 1E-10"
+        );
+        assert_eq!(output(&["%.0f %.0f", "0.5", "1.5"]), "0 2");
+        assert_eq!(output(&["%#.0f|%#g", "1", "1"]), "1.|1.00000");
+        assert_eq!(
+            output(&["%08.3f|%-8.3f|", "3.14159", "-3.14159"]),
+            "0003.142|-3.142  |"
+        );
+        assert_eq!(output(&["%f %5f", "inf", "-inf"]), "inf  -inf");
+    }
+
+    #[test]
+    fn widths_and_precisions_from_arguments() {
+        assert_eq!(
+            output(&["%*d|%-*d|%.*f", "4", "7", "4", "7", "2", "3.14159"]),
+            "   7|7   |3.14"
+        );
+        assert_eq!(output(&["%*s|", "-3", "a"]), "a  |");
+        assert_eq!(output(&["%.*s|", "-1", "abc"]), "abc|");
+    }
+
+    #[test]
+    fn format_escapes() {
+        assert_eq!(output(&["a\\tb\\x41\\101\\n"]), "a\tbAA\n");
+        assert_eq!(output(&["\\e\\a\\\\\\\"\\q"]), "\x1b\x07\\\"\\q");
+        assert_eq!(output(&["\\x"]), "\\x");
+        assert_eq!(output(&["%s\\c", "c is kept"]), "c is kept\\c");
+    }
+
+    #[test]
+    fn b_escapes() {
+        assert_eq!(output(&["%b", "a\\tb\\n"]), "a\tb\n");
+        assert_eq!(output(&["%b|%b", "\\0101", "\\101"]), "A|A");
+        assert_eq!(output(&["%b", "\\x41\\e"]), "A\x1b");
+        assert_eq!(output(&["%.3b|", "a\\nbcd"]), "a\nb|");
+    }
+
+    #[test]
+    fn c_escape_stops_output() {
+        assert_eq!(output(&["%b-%s\\n", "no\\cmore", "x", "after"]), "no");
+        assert_eq!(output(&["[%b]", "a", "b\\c", "c"]), "[a][b");
+    }
+
+    #[test]
+    fn format_is_reused_for_remaining_arguments() {
+        assert_eq!(output(&["%s-%d|", "a", "1", "b", "2", "c"]), "a-1|b-2|c-0|");
+        assert_eq!(output(&["%s\\n", "a", "b", "c"]), "a\nb\nc\n");
+        assert_eq!(output(&["x%sy\\n"]), "xy\n");
+        assert_eq!(output(&["fixed\\n", "ignored", "too"]), "fixed\n");
+    }
+
+    #[test]
+    fn invalid_numbers() {
+        assert_eq!(
+            run(&["%d|", "abc"]),
+            (
+                1,
+                "0|".to_owned(),
+                "printf: abc: invalid number\n".to_owned()
+            )
+        );
+        assert_eq!(
+            run(&["%f|%d|", "x", "1", "2", "y"]),
+            (
+                1,
+                "0.000000|1|2.000000|0|".to_owned(),
+                "printf: x: invalid number\nprintf: y: invalid number\n".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn out_of_range_integers() {
+        assert_eq!(
+            run(&[
+                "%d %i %u",
+                "9223372036854775808",
+                "-9223372036854775809",
+                "18446744073709551616"
+            ]),
+            (
+                0,
+                "9223372036854775807 -9223372036854775808 18446744073709551615".to_owned(),
+                "printf: warning: 9223372036854775808: Numerical result out of range\n\
+                 printf: warning: -9223372036854775809: Numerical result out of range\n\
+                 printf: warning: 18446744073709551616: Numerical result out of range\n"
+                    .to_owned()
+            )
+        );
+        assert_eq!(
+            output(&["%u %x", "18446744073709551615", "-1"]),
+            "18446744073709551615 ffffffffffffffff"
+        );
+        assert_eq!(
+            output(&["%d", "-9223372036854775808"]),
+            "-9223372036854775808"
+        );
+    }
+
+    #[test]
+    fn oversized_widths_and_precisions() {
+        assert_eq!(
+            run(&["a%99999999999999999999d", "1"]),
+            (
+                1,
+                "a".to_owned(),
+                "printf: 99999999999999999999: invalid field width\n".to_owned()
+            )
+        );
+        assert_eq!(
+            run(&["%.99999999999d", "1"]),
+            (
+                1,
+                String::new(),
+                "printf: 99999999999: invalid precision\n".to_owned()
+            )
+        );
+        assert_eq!(
+            run(&["%*d", "-99999999", "1"]),
+            (
+                1,
+                String::new(),
+                "printf: -99999999: invalid field width\n".to_owned()
+            )
+        );
+        assert_eq!(output(&["%1000s", ""]).len(), 1000);
+    }
+
+    #[test]
+    fn usage_errors() {
+        assert_eq!(
+            run(&[]),
+            (
+                2,
+                String::new(),
+                "printf: usage: printf format [arguments]\n".to_owned()
+            )
+        );
+        assert_eq!(
+            run(&["a%yb", "1"]),
+            (
+                1,
+                "a".to_owned(),
+                "printf: %y: invalid format character\n".to_owned()
+            )
+        );
+        assert_eq!(output(&["100%"]), "100%");
+    }
+}
@@ -1,11 +1,29 @@
-use crate::builtin_commands::echo::echo;
+use crate::{
+    builtin_commands::{change_directory::current_directory, echo::echo},
+    shell_state::ShellState,
+};
 use anyhow::{Context, Result};
+use std::io::Write;
 
-pub fn pwd(stdout: &mut Vec<String>, stderr: &mut Vec<String>) -> Result<()> {
-    let path = std::env::current_dir().context("Getting current directory")?;
-    let stringified_path = path.as_os_str().to_str().unwrap_or_default();
+/// `pwd [-L|-P]`: prints the logical working directory, or with `-P` the
+/// physical one with every symbolic link resolved.
+pub fn pwd(arguments: &[String], state: &ShellState, stdout: &mut impl Write) -> Result<i32> {
+    let physical = arguments
+        .iter()
+        .rev()
+        .find_map(|argument| match argument.as_str() {
+            "-L" => Some(false),
+            "-P" => Some(true),
+            _ => None,
+        })
+        .unwrap_or_default();
 
-    echo(&[stringified_path], stdout, stderr)?;
+    let path = if physical {
+        std::env::current_dir().context("Getting current directory")?
+    } else {
+        current_directory(state)
+    };
+    let stringified_path = path.as_os_str().to_str().unwrap_or_default();
 
-    Ok(())
+    echo(&[stringified_path], stdout)
 }
@@ -0,0 +1,288 @@
+use anyhow::Result;
+use std::io::{self, Write};
+
+use crate::{expansion::DEFAULT_IFS, input_parser::is_valid_name, shell_state::ShellState};
+
+/// `read [-r] [-p prompt] [name...]`: reads a line from standard input and
+/// splits it on `IFS`, assigning one field to each name and whatever is left
+/// to the last. Without names the whole line goes to `REPLY`. Unless `-r` is
+/// given a backslash quotes the next character and a backslash at the end
+/// of a line continues it on the next. Returns 1 at the end of input.
+pub fn read(arguments: &[String], state: &mut ShellState, stderr: &mut impl Write) -> Result<i32> {
+    let mut raw = false;
+    let mut prompt = None;
+    let mut arguments = arguments.iter();
+    let mut names = vec![];
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "-r" => raw = true,
+            "-p" => match arguments.next() {
+                Some(text) => prompt = Some(text),
+                None => {
+                    writeln!(stderr, "read: -p: option requires an argument")?;
+                    return Ok(2);
+                }
+            },
+            "--" => {
+                names.extend(arguments.by_ref().map(String::as_str));
+                break;
+            }
+            option if option.starts_with('-') && option.len() > 1 => {
+                writeln!(stderr, "read: {option}: invalid option")?;
+                writeln!(stderr, "read: usage: read [-r] [-p prompt] [name ...]")?;
+                return Ok(2);
+            }
+            name => names.push(name),
+        }
+    }
+
+    if let Some(name) = names.iter().find(|name| !is_valid_name(name)) {
+        writeln!(stderr, "read: `{name}': not a valid identifier")?;
+        return Ok(1);
+    }
+
+    if let Some(prompt) = prompt {
+        write!(stderr, "{prompt}")?;
+        stderr.flush()?;
+    }
+
+    let (line, complete) = read_line(raw, read_byte)?;
+
+    if names.is_empty() {
+        let line: String = line.iter().map(|(character, _)| character).collect();
+        state.set_variable("REPLY", line);
+    } else {
+        let ifs = state
+            .get_variable("IFS")
+            .unwrap_or_else(|| DEFAULT_IFS.to_owned());
+        let fields = split_fields(&line, names.len(), &ifs);
+
+        for (index, name) in names.iter().enumerate() {
+            let value = fields.get(index).cloned().unwrap_or_default();
+            state.set_variable(name, value);
+        }
+    }
+
+    Ok(if complete { 0 } else { 1 })
+}
+
+/// Reads a line a byte at a time from `next_byte`, which for standard input
+/// means nothing after the line is taken from a file or pipe the rest of the
+/// script may read. Returns each character with whether a backslash quoted it, and
+/// whether the line ended with a newline rather than the end of input.
+fn read_line(
+    raw: bool,
+    mut next_byte: impl FnMut() -> Result<Option<u8>>,
+) -> Result<(Vec<(char, bool)>, bool)> {
+    let mut bytes = vec![];
+    let mut quoted = vec![];
+    let mut escaping = false;
+
+    let complete = loop {
+        let Some(byte) = next_byte()? else {
+            break false;
+        };
+
+        match byte {
+            b'\n' if escaping => escaping = false,
+            b'\n' => break true,
+            b'\\' if !raw && !escaping => escaping = true,
+            byte => {
+                bytes.push(byte);
+                quoted.push(std::mem::take(&mut escaping));
+            }
+        }
+    };
+
+    // quoting is tracked per byte, so a character takes the quoting of its
+    // first byte
+    let text = String::from_utf8_lossy(&bytes);
+    let line = text
+        .char_indices()
+        .map(|(index, character)| (character, quoted.get(index).copied().unwrap_or_default()))
+        .collect();
+
+    Ok((line, complete))
+}
+
+fn read_byte() -> Result<Option<u8>> {
+    let mut byte = 0u8;
+
+    loop {
+        match unsafe { libc::read(libc::STDIN_FILENO, (&raw mut byte).cast(), 1) } {
+            0 => return Ok(None),
+            1 => return Ok(Some(byte)),
+            _ => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error.into());
+                }
+            }
+        }
+    }
+}
+
+/// Splits a line into at most `count` fields. Runs of `IFS` whitespace
+/// separate fields and are trimmed from both ends, other `IFS` characters
+/// separate a field each. The last field keeps the rest of the line, apart
+/// from a separator ending it when it would otherwise be a single field.
+fn split_fields(line: &[(char, bool)], count: usize, ifs: &str) -> Vec<String> {
+    let is_separator = |&(character, quoted): &(char, bool)| !quoted && ifs.contains(character);
+    let is_blank = |entry: &(char, bool)| is_separator(entry) && entry.0.is_whitespace();
+    // the separator is blanks around at most one other IFS character
+    let separator_end = |rest: &[(char, bool)], start: usize| {
+        let mut end = start;
+        while rest.get(end).is_some_and(is_blank) {
+            end += 1;
+        }
+        if rest
+            .get(end)
+            .is_some_and(|entry| is_separator(entry) && !is_blank(entry))
+        {
+            end += 1;
+            while rest.get(end).is_some_and(is_blank) {
+                end += 1;
+            }
+        }
+        end
+    };
+
+    let start = line
+        .iter()
+        .position(|entry| !is_blank(entry))
+        .unwrap_or(line.len());
+    let end = line
+        .iter()
+        .rposition(|entry| !is_blank(entry))
+        .map_or(start, |end| end + 1);
+    let mut rest = &line[start..end];
+    let mut fields = vec![];
+
+    while fields.len() + 1 < count && !rest.is_empty() {
+        let field_end = rest.iter().position(is_separator).unwrap_or(rest.len());
+        fields.push(
+            rest[..field_end]
+                .iter()
+                .map(|(character, _)| character)
+                .collect(),
+        );
+
+        rest = &rest[separator_end(rest, field_end)..];
+    }
+
+    if let Some(field_end) = rest.iter().position(is_separator)
+        && separator_end(rest, field_end) == rest.len()
+    {
+        rest = &rest[..field_end];
+    }
+    if !rest.is_empty() {
+        fields.push(rest.iter().map(|(character, _)| character).collect());
+    }
+
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads the first line of `input`, giving its text with quoted
+    /// characters in brackets, and whether it ended with a newline.
+    fn line(input: &str, raw: bool) -> (String, bool) {
+        let mut bytes = input.bytes();
+        let (line, complete) = read_line(raw, || Ok(bytes.next())).unwrap();
+
+        let text = line
+            .iter()
+            .map(|&(character, quoted)| {
+                if quoted {
+                    format!("[{character}]")
+                } else {
+                    character.to_string()
+                }
+            })
+            .collect();
+        (text, complete)
+    }
+
+    fn fields(text: &str, count: usize, ifs: &str) -> Vec<String> {
+        let line: Vec<(char, bool)> = text.chars().map(|character| (character, false)).collect();
+        split_fields(&line, count, ifs)
+    }
+
+    #[test]
+    fn reads_one_line() {
+        assert_eq!(line("first\nsecond\n", false), ("first".to_owned(), true));
+        assert_eq!(line("\n", false), (String::new(), true));
+        assert_eq!(line("héllo\n", false), ("héllo".to_owned(), true));
+    }
+
+    #[test]
+    fn end_of_input() {
+        assert_eq!(line("", false), (String::new(), false));
+        assert_eq!(line("partial", false), ("partial".to_owned(), false));
+        assert_eq!(line("continued\\", false), ("continued".to_owned(), false));
+    }
+
+    #[test]
+    fn backslashes() {
+        assert_eq!(line("a\\ b\\\\c\n", false), ("a[ ]b[\\]c".to_owned(), true));
+        assert_eq!(
+            line("one\\\ntwo\nthree", false),
+            ("onetwo".to_owned(), true)
+        );
+    }
+
+    #[test]
+    fn raw_backslashes() {
+        assert_eq!(line("a\\ b\\\\c\n", true), ("a\\ b\\\\c".to_owned(), true));
+        assert_eq!(line("one\\\ntwo", true), ("one\\".to_owned(), true));
+    }
+
+    #[test]
+    fn splits_on_whitespace() {
+        assert_eq!(fields("  a   b   c  ", 2, DEFAULT_IFS), ["a", "b   c"]);
+        assert_eq!(fields("a b c", 3, DEFAULT_IFS), ["a", "b", "c"]);
+        assert_eq!(fields("a\tb", 5, DEFAULT_IFS), ["a", "b"]);
+        assert_eq!(fields("   ", 2, DEFAULT_IFS), Vec::<String>::new());
+        assert_eq!(fields("  a b  ", 1, DEFAULT_IFS), ["a b"]);
+    }
+
+    #[test]
+    fn splits_on_other_characters() {
+        assert_eq!(fields("a::b", 3, ":"), ["a", "", "b"]);
+        assert_eq!(fields("a::b", 2, ":"), ["a", ":b"]);
+        assert_eq!(fields(" a : b  c d ", 3, ": "), ["a", "b", "c d"]);
+        assert_eq!(fields("a:b:c:", 2, ":"), ["a", "b:c:"]);
+    }
+
+    #[test]
+    fn drops_separator_ending_last_field() {
+        assert_eq!(fields("a:b:", 2, ":"), ["a", "b"]);
+        assert_eq!(fields("a:", 1, ":"), ["a"]);
+        assert_eq!(fields("a::", 1, ":"), ["a::"]);
+        assert_eq!(fields("a::", 2, ":"), ["a"]);
+        assert_eq!(fields("a b :  ", 2, ": "), ["a", "b"]);
+    }
+
+    #[test]
+    fn empty_ifs_keeps_the_line() {
+        assert_eq!(fields("  a b  ", 2, ""), ["  a b  "]);
+    }
+
+    #[test]
+    fn quoted_separators_stay_in_fields() {
+        let line = [
+            ('a', false),
+            (' ', true),
+            ('b', false),
+            (' ', false),
+            ('c', false),
+        ];
+        assert_eq!(split_fields(&line, 2, DEFAULT_IFS), ["a b", "c"]);
+
+        let line = [('a', false), (':', false), ('b', false), (':', true)];
+        assert_eq!(split_fields(&line, 2, ":"), ["a", "b:"]);
+    }
+}
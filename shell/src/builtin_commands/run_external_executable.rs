@@ -1,35 +1,83 @@
-use anyhow::{Context, Result};
-use std::fs::DirEntry;
+use anyhow::Result;
+use std::{os::unix::process::CommandExt, path::Path};
 
+use crate::{
+    errors::{CustomError, io_error_message},
+    jobs::{Job, JobTable},
+    process::{Fork, exit_child, fork},
+};
+
+/// Runs the executable as a foreground job in a child process that inherits
+/// the shell's file descriptors, so output streams live and interactive
+/// programs get the terminal. Returns the child's exit status.
 pub fn run_external_executable(
-    executable: DirEntry,
+    executable: &Path,
     arguments: &[String],
-    stdout: &mut Vec<String>,
-    stderr: &mut Vec<String>,
-) -> Result<()> {
-    let name = executable.file_name();
-    let name = name.to_str().unwrap();
-    let mut command = std::process::Command::new(name);
-
-    command.args(arguments);
-
-    let command_result = command
-        .output()
-        .context("getting command result from external command")?;
-
-    if !command_result.stdout.is_empty() {
-        stdout.push(
-            String::from_utf8(command_result.stdout)
-                .context("Converting external command standard out to String")?,
-        );
+    environment: Vec<(String, String)>,
+    jobs: &mut JobTable,
+) -> Result<i32> {
+    match fork()? {
+        Fork::Child => {
+            jobs.enter_job(0, true);
+            let error = exec_external(executable, arguments, environment);
+            error.report();
+            exit_child(error.exit_status());
+        }
+        Fork::Parent(pid) => {
+            jobs.adopt(pid, pid);
+
+            let name = executable
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned();
+            let text = std::iter::once(name)
+                .chain(arguments.iter().cloned())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            jobs.wait_foreground(Job::new(pid, vec![pid], text))
+        }
     }
+}
+
+/// Replaces the current process with the executable. Only returns, with the
+/// reason, if the exec failed. A file the system can't execute, such as a script without a
+/// `#!` line, is run as a script by a new copy of this shell.
+pub fn exec_external(
+    executable: &Path,
+    arguments: &[String],
+    environment: Vec<(String, String)>,
+) -> CustomError {
+    let error = std::process::Command::new(executable)
+        .arg0(executable.file_name().unwrap_or_default())
+        .args(arguments)
+        .env_clear()
+        .envs(environment.clone())
+        .exec();
 
-    if !command_result.stderr.is_empty() {
-        stderr.push(
-            String::from_utf8(command_result.stderr)
-                .context("Converting external command standard error to String")?,
-        );
+    if error.raw_os_error() != Some(libc::ENOEXEC) {
+        return exec_error(executable, &error);
     }
 
-    Ok(())
+    let error = match std::env::current_exe() {
+        Ok(shell) => std::process::Command::new(shell)
+            .arg(executable)
+            .args(arguments)
+            .env_clear()
+            .envs(environment)
+            .exec(),
+        Err(error) => error,
+    };
+
+    exec_error(executable, &error)
+}
+
+fn exec_error(executable: &Path, error: &std::io::Error) -> CustomError {
+    let name = executable.display().to_string();
+
+    match error.raw_os_error() {
+        Some(libc::EACCES) => CustomError::PermissionDenied(name),
+        _ => CustomError::CannotExecute(name, io_error_message(error)),
+    }
 }
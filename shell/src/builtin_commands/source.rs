@@ -0,0 +1,53 @@
+use anyhow::Result;
+use std::{io::Write, path::PathBuf};
+
+use crate::{
+    errors::io_error_message,
+    input::Input,
+    shell_state::{ControlFlow, ShellState},
+    utils::find_file,
+};
+
+/// `source file [argument...]` or `. file [argument...]`: runs the commands
+/// in a file in the current shell. A file name without a slash is looked up
+/// in `PATH` before the current directory.
+pub fn source(
+    arguments: &[String],
+    state: &mut ShellState,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let Some((file_name, arguments)) = arguments.split_first() else {
+        writeln!(stderr, "source: filename argument required")?;
+        return Ok(2);
+    };
+
+    let path = if file_name.contains('/') {
+        PathBuf::from(file_name)
+    } else {
+        find_file(file_name, state.path()).unwrap_or_else(|| PathBuf::from(file_name))
+    };
+
+    let mut input = match Input::from_file(&path) {
+        Ok(input) => input,
+        Err(error) => {
+            writeln!(stderr, "source: {file_name}: {}", io_error_message(&error))?;
+            return Ok(1);
+        }
+    };
+
+    let saved_parameters = (!arguments.is_empty())
+        .then(|| std::mem::replace(&mut state.positional_parameters, arguments.to_vec()));
+    state.call_depth += 1;
+
+    let result = input.run(state);
+
+    state.call_depth -= 1;
+    if let Some(saved_parameters) = saved_parameters {
+        state.positional_parameters = saved_parameters;
+    }
+    if state.control_flow == Some(ControlFlow::Return) {
+        state.control_flow = None;
+    }
+
+    result.map(|()| state.last_exit_status)
+}
@@ -0,0 +1,429 @@
+use anyhow::Result;
+use std::{
+    ffi::CString,
+    fs::Metadata,
+    io::Write,
+    os::unix::fs::{FileTypeExt, MetadataExt},
+    path::Path,
+};
+
+/// `test expression` or `[ expression ]`: returns 0 if the expression is
+/// true, 1 if it is false and 2 if it can't be understood.
+///
+/// - `-e`, `-f`, `-d`, `-h`/`-L`, `-b`, `-c`, `-p`, `-S` test whether a file
+///   exists, or is a regular file, directory, symbolic link, block or
+///   character device, pipe or socket
+/// - `-r`, `-w`, `-x` whether the shell may read, write or execute it, `-s`
+///   whether it is non-empty, `-u`, `-g`, `-k` whether its set-user-id,
+///   set-group-id or sticky bit is set, `-O`, `-G` whether the shell's user
+///   or group owns it, and `-t fd` whether a descriptor is a terminal
+/// - `-n` and `-z` whether a string is non-empty or empty, and a string on
+///   its own whether it is non-empty
+/// - `=` (or `==`), `!=`, `<` and `>` compare strings, `-eq`, `-ne`, `-lt`,
+///   `-le`, `-gt` and `-ge` integers, and `-nt`, `-ot` and `-ef` whether a
+///   file is newer than, older than or the same as another
+/// - `!` negates, `-a` and `-o` combine, and `(` `)` group expressions
+pub fn test(name: &str, arguments: &[String], stderr: &mut impl Write) -> Result<i32> {
+    let arguments = if name == "[" {
+        match arguments.split_last() {
+            Some((last, rest)) if last == "]" => rest,
+            _ => {
+                writeln!(stderr, "[: missing `]'")?;
+                return Ok(2);
+            }
+        }
+    } else {
+        arguments
+    };
+
+    let arguments: Vec<&str> = arguments.iter().map(String::as_str).collect();
+
+    match evaluate(&arguments) {
+        Ok(true) => Ok(0),
+        Ok(false) => Ok(1),
+        Err(error) => {
+            writeln!(stderr, "{name}: {error}")?;
+            Ok(2)
+        }
+    }
+}
+
+/// Evaluates an expression, treating up to four arguments the way POSIX
+/// requires so that operands which look like operators, as in `[ -n = x ]`,
+/// mean what they appear to.
+fn evaluate(arguments: &[&str]) -> Result<bool, String> {
+    match arguments {
+        [] => Ok(false),
+        [operand] => Ok(!operand.is_empty()),
+        ["!", operand] => Ok(operand.is_empty()),
+        [operator, operand] if is_unary_operator(operator) => unary(operator, operand),
+        [operator, _] => Err(format!("{operator}: unary operator expected")),
+        [left, operator, right] if is_binary_operator(operator) => binary(left, operator, right),
+        [left, "-a", right] => Ok(!left.is_empty() && !right.is_empty()),
+        [left, "-o", right] => Ok(!left.is_empty() || !right.is_empty()),
+        ["!", rest @ ..] if arguments.len() <= 4 => evaluate(rest).map(|result| !result),
+        ["(", operand, ")"] => Ok(!operand.is_empty()),
+        ["(", inner @ .., ")"] if arguments.len() == 4 => evaluate(inner),
+        [_, operator, _] => Err(format!("{operator}: binary operator expected")),
+        _ => {
+            let mut parser = Parser {
+                arguments,
+                position: 0,
+            };
+            let result = parser.or()?;
+
+            match parser.arguments.get(parser.position) {
+                Some(_) => Err("too many arguments".to_owned()),
+                None => Ok(result),
+            }
+        }
+    }
+}
+
+/// Parses longer expressions, where `-o` binds more loosely than `-a`,
+/// which binds more loosely than `!`.
+struct Parser<'a> {
+    arguments: &'a [&'a str],
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn or(&mut self) -> Result<bool, String> {
+        let mut result = self.and()?;
+
+        while self.next_if("-o") {
+            result |= self.and()?;
+        }
+
+        Ok(result)
+    }
+
+    fn and(&mut self) -> Result<bool, String> {
+        let mut result = self.not()?;
+
+        while self.next_if("-a") {
+            result &= self.not()?;
+        }
+
+        Ok(result)
+    }
+
+    fn not(&mut self) -> Result<bool, String> {
+        if self.peek(1).is_some_and(is_binary_operator) || !self.next_if("!") {
+            return self.primary();
+        }
+
+        Ok(!self.not()?)
+    }
+
+    fn primary(&mut self) -> Result<bool, String> {
+        let Some(first) = self.peek(0) else {
+            return Err("argument expected".to_owned());
+        };
+
+        if let (Some(operator), Some(right)) = (self.peek(1), self.peek(2))
+            && is_binary_operator(operator)
+        {
+            self.position += 3;
+            return binary(first, operator, right);
+        }
+
+        if first == "(" {
+            self.position += 1;
+            let result = self.or()?;
+            if !self.next_if(")") {
+                return Err("`)' expected".to_owned());
+            }
+            return Ok(result);
+        }
+
+        if is_unary_operator(first)
+            && let Some(operand) = self.peek(1)
+        {
+            self.position += 2;
+            return unary(first, operand);
+        }
+
+        self.position += 1;
+        Ok(!first.is_empty())
+    }
+
+    fn peek(&self, offset: usize) -> Option<&'a str> {
+        self.arguments.get(self.position + offset).copied()
+    }
+
+    fn next_if(&mut self, expected: &str) -> bool {
+        let matched = self.peek(0) == Some(expected);
+        if matched {
+            self.position += 1;
+        }
+        matched
+    }
+}
+
+fn is_unary_operator(operator: &str) -> bool {
+    matches!(
+        operator,
+        "-b" | "-c"
+            | "-d"
+            | "-e"
+            | "-f"
+            | "-g"
+            | "-G"
+            | "-h"
+            | "-k"
+            | "-L"
+            | "-n"
+            | "-O"
+            | "-p"
+            | "-r"
+            | "-s"
+            | "-S"
+            | "-t"
+            | "-u"
+            | "-w"
+            | "-x"
+            | "-z"
+    )
+}
+
+fn is_binary_operator(operator: &str) -> bool {
+    matches!(
+        operator,
+        "=" | "=="
+            | "!="
+            | "<"
+            | ">"
+            | "-eq"
+            | "-ne"
+            | "-lt"
+            | "-le"
+            | "-gt"
+            | "-ge"
+            | "-nt"
+            | "-ot"
+            | "-ef"
+    )
+}
+
+fn unary(operator: &str, operand: &str) -> Result<bool, String> {
+    let path = Path::new(operand);
+    let metadata = path.metadata().ok();
+    let has = |test: fn(&Metadata) -> bool| metadata.as_ref().is_some_and(test);
+
+    Ok(match operator {
+        "-n" => !operand.is_empty(),
+        "-z" => operand.is_empty(),
+        "-e" => metadata.is_some(),
+        "-f" => has(Metadata::is_file),
+        "-d" => has(Metadata::is_dir),
+        "-h" | "-L" => path.is_symlink(),
+        "-b" => has(|metadata| metadata.file_type().is_block_device()),
+        "-c" => has(|metadata| metadata.file_type().is_char_device()),
+        "-p" => has(|metadata| metadata.file_type().is_fifo()),
+        "-S" => has(|metadata| metadata.file_type().is_socket()),
+        "-s" => has(|metadata| metadata.len() > 0),
+        "-u" => has(|metadata| metadata.mode() & libc::S_ISUID != 0),
+        "-g" => has(|metadata| metadata.mode() & libc::S_ISGID != 0),
+        "-k" => has(|metadata| metadata.mode() & libc::S_ISVTX != 0),
+        "-O" => has(|metadata| metadata.uid() == unsafe { libc::geteuid() }),
+        "-G" => has(|metadata| metadata.gid() == unsafe { libc::getegid() }),
+        "-r" => is_accessible(operand, libc::R_OK),
+        "-w" => is_accessible(operand, libc::W_OK),
+        "-x" => is_accessible(operand, libc::X_OK),
+        "-t" => {
+            let fd = parse_integer(operand)?;
+            i32::try_from(fd).is_ok_and(|fd| unsafe { libc::isatty(fd) } == 1)
+        }
+        _ => return Err(format!("{operator}: unary operator expected")),
+    })
+}
+
+fn binary(left: &str, operator: &str, right: &str) -> Result<bool, String> {
+    Ok(match operator {
+        "=" | "==" => left == right,
+        "!=" => left != right,
+        "<" => left < right,
+        ">" => left > right,
+        "-eq" => parse_integer(left)? == parse_integer(right)?,
+        "-ne" => parse_integer(left)? != parse_integer(right)?,
+        "-lt" => parse_integer(left)? < parse_integer(right)?,
+        "-le" => parse_integer(left)? <= parse_integer(right)?,
+        "-gt" => parse_integer(left)? > parse_integer(right)?,
+        "-ge" => parse_integer(left)? >= parse_integer(right)?,
+        "-nt" | "-ot" => {
+            let modified = |path: &str| Path::new(path).metadata().and_then(|m| m.modified()).ok();
+            let (newer, older) = if operator == "-nt" {
+                (modified(left), modified(right))
+            } else {
+                (modified(right), modified(left))
+            };
+
+            match (newer, older) {
+                (Some(newer), Some(older)) => newer > older,
+                (Some(_), None) => true,
+                _ => false,
+            }
+        }
+        "-ef" => match (Path::new(left).metadata(), Path::new(right).metadata()) {
+            (Ok(left), Ok(right)) => left.dev() == right.dev() && left.ino() == right.ino(),
+            _ => false,
+        },
+        _ => return Err(format!("{operator}: binary operator expected")),
+    })
+}
+
+fn parse_integer(text: &str) -> Result<i64, String> {
+    text.trim()
+        .parse()
+        .map_err(|_| format!("{text}: integer expression expected"))
+}
+
+/// Whether the shell's real user may access the file as `mode` asks.
+fn is_accessible(path: &str, mode: libc::c_int) -> bool {
+    let Ok(path) = CString::new(path) else {
+        return false;
+    };
+
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(name: &str, arguments: &[&str]) -> (i32, String) {
+        let arguments: Vec<String> = arguments
+            .iter()
+            .map(|&argument| argument.to_owned())
+            .collect();
+        let mut stderr = vec![];
+        let status = test(name, &arguments, &mut stderr).unwrap();
+
+        (status, String::from_utf8(stderr).unwrap())
+    }
+
+    fn error(arguments: &[&str]) -> String {
+        evaluate(arguments).expect_err(&format!("{arguments:?}"))
+    }
+
+    #[test]
+    fn few_arguments() {
+        assert_eq!(evaluate(&[]), Ok(false));
+        assert_eq!(evaluate(&["x"]), Ok(true));
+        assert_eq!(evaluate(&[""]), Ok(false));
+        assert_eq!(evaluate(&["-n"]), Ok(true));
+        assert_eq!(evaluate(&["!"]), Ok(true));
+        assert_eq!(evaluate(&["!", ""]), Ok(true));
+        assert_eq!(evaluate(&["!", "!"]), Ok(false));
+        assert_eq!(evaluate(&["-z", ""]), Ok(true));
+        assert_eq!(evaluate(&["-n", ""]), Ok(false));
+    }
+
+    #[test]
+    fn operands_that_look_like_operators() {
+        assert_eq!(evaluate(&["-n", "=", "x"]), Ok(false));
+        assert_eq!(evaluate(&["=", "=", "="]), Ok(true));
+        assert_eq!(evaluate(&["-n", "-a", "-z"]), Ok(true));
+        assert_eq!(evaluate(&["x", "-a", "-a"]), Ok(true));
+        assert_eq!(evaluate(&["(", "-n", ")"]), Ok(true));
+        assert_eq!(evaluate(&["!", "-z", "x"]), Ok(true));
+    }
+
+    #[test]
+    fn string_comparisons() {
+        assert_eq!(evaluate(&["a", "=", "a"]), Ok(true));
+        assert_eq!(evaluate(&["a", "==", "b"]), Ok(false));
+        assert_eq!(evaluate(&["a", "!=", "b"]), Ok(true));
+        assert_eq!(evaluate(&["10", "<", "9"]), Ok(true));
+        assert_eq!(evaluate(&["b", ">", "a"]), Ok(true));
+    }
+
+    #[test]
+    fn integer_comparisons() {
+        assert_eq!(evaluate(&["10", "-lt", "9"]), Ok(false));
+        assert_eq!(evaluate(&["-5", "-le", "-5"]), Ok(true));
+        assert_eq!(evaluate(&[" 3 ", "-eq", "3"]), Ok(true));
+        assert_eq!(evaluate(&["3", "-ne", "4"]), Ok(true));
+        assert_eq!(evaluate(&["4", "-gt", "3"]), Ok(true));
+        assert_eq!(evaluate(&["3", "-ge", "4"]), Ok(false));
+        assert_eq!(
+            error(&["3", "-eq", "3.0"]),
+            "3.0: integer expression expected"
+        );
+        assert_eq!(error(&["a", "-eq", "1"]), "a: integer expression expected");
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        assert_eq!(evaluate(&["x", "-o", "", "-a", ""]), Ok(true));
+        assert_eq!(evaluate(&["", "-a", "", "-o", "x"]), Ok(true));
+        assert_eq!(
+            evaluate(&["1", "-eq", "1", "-a", "2", "-eq", "3", "-o", "x", "=", "x"]),
+            Ok(true)
+        );
+        assert_eq!(evaluate(&["x", "=", "y", "-o", "a", "!=", "b"]), Ok(true));
+    }
+
+    #[test]
+    fn not_binds_tighter_than_and() {
+        assert_eq!(evaluate(&["!", "", "-a", "", "-a", "x"]), Ok(false));
+        assert_eq!(evaluate(&["!", "x", "-o", "x", "-o", ""]), Ok(true));
+        assert_eq!(evaluate(&["x", "-a", "!", ""]), Ok(true));
+        assert_eq!(
+            evaluate(&["!", "-e", "/nonexistent", "-a", "-d", "/"]),
+            Ok(true)
+        );
+    }
+
+    #[test]
+    fn four_arguments_starting_with_not_negate_the_rest() {
+        assert_eq!(evaluate(&["!", "x", "-o", "x"]), Ok(false));
+        assert_eq!(evaluate(&["!", "", "-a", ""]), Ok(true));
+    }
+
+    #[test]
+    fn parentheses() {
+        assert_eq!(evaluate(&["(", "x", "-o", "", ")", "-a", ""]), Ok(false));
+        assert_eq!(evaluate(&["(", "", ")"]), Ok(false));
+        assert_eq!(evaluate(&["(", "a", "=", "a", ")"]), Ok(true));
+        assert_eq!(
+            evaluate(&["!", "(", "", "-o", "x", ")", "-a", "x"]),
+            Ok(false)
+        );
+    }
+
+    #[test]
+    fn files() {
+        assert_eq!(evaluate(&["-d", "/"]), Ok(true));
+        assert_eq!(evaluate(&["-f", "/"]), Ok(false));
+        assert_eq!(evaluate(&["-e", "/nonexistent"]), Ok(false));
+        assert_eq!(evaluate(&["/", "-ef", "/."]), Ok(true));
+        assert_eq!(evaluate(&["/", "-nt", "/nonexistent"]), Ok(true));
+        assert_eq!(evaluate(&["/", "-ot", "/nonexistent"]), Ok(false));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        assert_eq!(error(&["x", "y"]), "x: unary operator expected");
+        assert_eq!(error(&["-y", "z"]), "-y: unary operator expected");
+        assert_eq!(error(&["x", "-y", "z"]), "-y: binary operator expected");
+        assert_eq!(error(&["(", "x"]), "(: unary operator expected");
+        assert_eq!(error(&["a", "b", "c", "d", "e"]), "too many arguments");
+        assert_eq!(error(&["x", "-a", "y", "-a"]), "argument expected");
+        assert_eq!(error(&["(", "x", "-a", "y", "z"]), "`)' expected");
+    }
+
+    #[test]
+    fn exit_statuses() {
+        assert_eq!(run("test", &["a", "=", "a"]), (0, String::new()));
+        assert_eq!(run("test", &["a", "=", "b"]), (1, String::new()));
+        assert_eq!(run("[", &["a", "]"]), (0, String::new()));
+        assert_eq!(run("[", &["]"]), (1, String::new()));
+        assert_eq!(run("[", &["a"]), (2, "[: missing `]'\n".to_owned()));
+        assert_eq!(
+            run("test", &["a", "-gt"]),
+            (2, "test: a: unary operator expected\n".to_owned())
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{
+    process::{
+        SIGNALS, catch_signal, default_signal, ignore_signal, install_child_handler,
+        signal_from_name, signal_name,
+    },
+    shell_state::ShellState,
+    traps::EXIT,
+};
+
+/// `trap [-lp] [[action] condition...]`: runs `action` when one of the
+/// signals arrives, or with `EXIT` when the shell exits. An empty action
+/// ignores the signals and `-` puts back their default behaviour. With no
+/// arguments, or `-p`, the current traps are printed.
+pub fn trap(
+    arguments: &[String],
+    state: &mut ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let arguments = match arguments.first().map(String::as_str) {
+        Some("-l") => {
+            for (name, signal) in SIGNALS {
+                writeln!(stdout, "{signal:2}) SIG{name}")?;
+            }
+            return Ok(0);
+        }
+        Some("-p") => return print_traps(&arguments[1..], state, stdout, stderr),
+        None => return print_traps(&[], state, stdout, stderr),
+        Some("--") => &arguments[1..],
+        _ => arguments,
+    };
+
+    // a lone condition, or a number where the action would be, resets
+    let (action, conditions) = match arguments {
+        [] => return print_traps(&[], state, stdout, stderr),
+        [condition] => ("-", std::slice::from_ref(condition)),
+        [action, ..] if action.parse::<u32>().is_ok() => ("-", arguments),
+        [action, conditions @ ..] => (action.as_str(), conditions),
+    };
+
+    let mut status = 0;
+
+    for condition in conditions {
+        let Some(signal) = condition_from_name(condition) else {
+            writeln!(stderr, "trap: {condition}: invalid signal specification")?;
+            status = 1;
+            continue;
+        };
+
+        if action == "-" {
+            state.traps.remove(&signal);
+            restore_disposition(signal, state);
+            continue;
+        }
+
+        state.traps.insert(signal, action.to_owned());
+        match signal {
+            EXIT => (),
+            // the shell relies on SIGCHLD to notice finished jobs
+            libc::SIGCHLD => catch_signal(signal),
+            _ if action.is_empty() => ignore_signal(signal),
+            _ => catch_signal(signal),
+        }
+    }
+
+    Ok(status)
+}
+
+/// Accepts `EXIT` or `0`, and anything `kill` does.
+fn condition_from_name(name: &str) -> Option<libc::c_int> {
+    if name.eq_ignore_ascii_case("EXIT") || name == "0" {
+        return Some(EXIT);
+    }
+
+    signal_from_name(name).filter(|signal| signal_name(*signal).is_some())
+}
+
+fn condition_name(condition: libc::c_int) -> String {
+    match signal_name(condition) {
+        Some(name) => format!("SIG{name}"),
+        None => "EXIT".to_owned(),
+    }
+}
+
+/// What the signal does when not trapped, which for an interactive shell
+/// is the same as when it started.
+fn restore_disposition(signal: libc::c_int, state: &ShellState) {
+    let interactive = state.jobs.job_control;
+
+    match signal {
+        EXIT => (),
+        libc::SIGCHLD => install_child_handler(),
+        libc::SIGINT | libc::SIGQUIT if interactive => catch_signal(signal),
+        libc::SIGTSTP | libc::SIGTTIN | libc::SIGTTOU if interactive => ignore_signal(signal),
+        _ => default_signal(signal),
+    }
+}
+
+/// Prints traps in a form that can be run to set them again.
+fn print_traps(
+    conditions: &[String],
+    state: &ShellState,
+    stdout: &mut impl Write,
+    stderr: &mut impl Write,
+) -> Result<i32> {
+    let mut status = 0;
+    let mut signals = vec![];
+
+    if conditions.is_empty() {
+        signals.extend(state.traps.keys().copied());
+        signals.sort();
+    }
+    for condition in conditions {
+        match condition_from_name(condition) {
+            Some(signal) => signals.push(signal),
+            None => {
+                writeln!(stderr, "trap: {condition}: invalid signal specification")?;
+                status = 1;
+            }
+        }
+    }
+
+    for signal in signals {
+        if let Some(action) = state.traps.get(&signal) {
+            let action = action.replace('\'', r"'\''");
+            writeln!(stdout, "trap -- '{action}' {}", condition_name(signal))?;
+        }
+    }
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(words: &[&str], state: &mut ShellState) -> (i32, String, String) {
+        let arguments: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        let status = trap(&arguments, state, &mut stdout, &mut stderr).unwrap();
+
+        let text = |bytes| String::from_utf8(bytes).unwrap();
+        (status, text(stdout), text(stderr))
+    }
+
+    #[test]
+    fn setting_and_resetting() {
+        let mut state = ShellState::new(false).unwrap();
+
+        assert_eq!(run(&["echo caught", "INT", "SIGTERM"], &mut state).0, 0);
+        assert_eq!(run(&["echo bye", "exit"], &mut state).0, 0);
+        assert_eq!(state.traps[&libc::SIGINT], "echo caught");
+        assert_eq!(state.traps[&libc::SIGTERM], "echo caught");
+        assert_eq!(state.traps[&EXIT], "echo bye");
+
+        assert_eq!(run(&["-", "INT"], &mut state).0, 0);
+        assert!(!state.traps.contains_key(&libc::SIGINT));
+        // a lone condition, or a number first, resets too
+        run(&["TERM"], &mut state);
+        run(&["0"], &mut state);
+        assert!(state.traps.is_empty());
+
+        let (status, _, stderr) = run(&["echo", "NOPE", "USR1"], &mut state);
+        assert_eq!(status, 1);
+        assert_eq!(stderr, "trap: NOPE: invalid signal specification\n");
+        assert_eq!(state.traps[&libc::SIGUSR1], "echo");
+        run(&["-", "USR1"], &mut state);
+    }
+
+    #[test]
+    fn printing() {
+        let mut state = ShellState::new(false).unwrap();
+        state.traps.insert(EXIT, "echo 'bye'".to_owned());
+        state.traps.insert(libc::SIGHUP, String::new());
+
+        let (status, stdout, _) = run(&["-p"], &mut state);
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            "trap -- 'echo '\\''bye'\\''' EXIT\ntrap -- '' SIGHUP\n"
+        );
+        assert_eq!(run(&[], &mut state).1, stdout);
+        assert_eq!(
+            run(&["-p", "HUP", "INT"], &mut state).1,
+            "trap -- '' SIGHUP\n"
+        );
+
+        let (status, _, stderr) = run(&["-p", "NOPE"], &mut state);
+        assert_eq!(status, 1);
+        assert_eq!(stderr, "trap: NOPE: invalid signal specification\n");
+    }
+}
@@ -0,0 +1,26 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{input_parser::is_valid_name, shell_state::ShellState};
+
+/// `unset [-v|-f] name...`: removes variables, or functions with `-f`.
+pub fn unset(arguments: &[String], state: &mut ShellState, stderr: &mut impl Write) -> Result<i32> {
+    let mut status = 0;
+    let functions = arguments.first().is_some_and(|argument| argument == "-f");
+
+    for name in arguments
+        .iter()
+        .filter(|argument| !["-v", "-f"].contains(&argument.as_str()))
+    {
+        if functions {
+            state.functions.remove(name);
+        } else if is_valid_name(name) {
+            state.unset_variable(name);
+        } else {
+            writeln!(stderr, "unset: `{name}': not a valid identifier")?;
+            status = 1;
+        }
+    }
+
+    Ok(status)
+}
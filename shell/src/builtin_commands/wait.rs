@@ -0,0 +1,38 @@
+use anyhow::Result;
+use std::io::Write;
+
+use crate::{jobs::JobSpecError, shell_state::ShellState};
+
+/// `wait [%job|pid]...`: with no arguments waits for every background job.
+pub fn wait(arguments: &[String], state: &mut ShellState, stderr: &mut impl Write) -> Result<i32> {
+    if arguments.is_empty() {
+        while !state.jobs.jobs.is_empty() {
+            state.jobs.wait_job(0)?;
+        }
+        return Ok(0);
+    }
+
+    let mut status = 0;
+
+    for argument in arguments {
+        let index = match argument.parse::<libc::pid_t>() {
+            Ok(pid) => state
+                .jobs
+                .jobs
+                .iter()
+                .position(|job| job.contains(pid))
+                .ok_or(JobSpecError::NoSuchJob),
+            Err(_) => state.jobs.find(Some(argument)),
+        };
+
+        status = match index {
+            Ok(index) => state.jobs.wait_job(index)?,
+            Err(error) => {
+                writeln!(stderr, "wait: {argument}: {error}")?;
+                127
+            }
+        };
+    }
+
+    Ok(status)
+}
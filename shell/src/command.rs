@@ -1,83 +1,175 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
+use std::{
+    fmt,
+    io::{self, Write},
+};
 
-use crate::{builtin_commands::BuiltinCommand, input_parser::parse_input};
+use crate::{
+    builtin_commands::{BuiltinCommand, run_external_executable::exec_external},
+    command_node::CommandNode,
+    errors::{CustomError, io_error_message},
+    expansion::{expand_word_to_string, expand_words},
+    input_parser::Word,
+    redirect::Redirects,
+    shell_state::{ControlFlow, ShellState},
+};
 
+/// A simple command: assignments, words and redirections.
 #[derive(Debug)]
 pub struct Command {
-    pub builtin_command: BuiltinCommand,
-    pub standard_out: Output,
-    pub standard_error: Output,
+    pub assignments: Vec<(String, Word)>,
+    pub words: Vec<Word>,
+    pub redirects: Redirects,
 }
 
 impl Command {
-    pub fn new(user_input: String, stderr_collector: &mut Vec<String>) -> Result<Self> {
-        let mut parsed_input = parse_input(user_input);
-        let command_input = parsed_input.remove(0);
-        let (arguments, standard_out, standard_error) =
-            Self::extract_redirect(parsed_input, stderr_collector)?;
-        let builtin_command = BuiltinCommand::from((command_input, arguments.clone()));
-
-        Ok(Self {
-            builtin_command,
-            standard_out,
-            standard_error,
-        })
+    pub fn new(parsed_input: Vec<Word>, redirects: Redirects) -> Self {
+        let mut parsed_input = parsed_input.into_iter().peekable();
+        let mut assignments = vec![];
+
+        while let Some(assignment) = parsed_input.peek().and_then(Word::as_assignment) {
+            assignments.push(assignment);
+            parsed_input.next();
+        }
+
+        Self {
+            assignments,
+            words: parsed_input.collect(),
+            redirects,
+        }
     }
 
-    fn extract_redirect(
-        input: Vec<String>,
-        stderr: &mut Vec<String>,
-    ) -> Result<(Vec<String>, Output, Output)> {
-        let mut arguments = vec![];
-        let mut arguments_iter = input.into_iter();
-        let mut standard_out_output = Output::Standard;
-        let mut standard_error_output = Output::Standard;
-
-        while let Some(argument) = arguments_iter.next() {
-            match argument.as_str() {
-                "1>" | ">" => {
-                    let Some(next_argument) = arguments_iter.next() else {
-                        stderr
-                            .push("When redirecting standard out, a file must be given".to_owned());
-                        break;
-                    };
-                    standard_out_output = Output::CreateFile(next_argument);
-                }
-                "1>>" | ">>" => {
-                    let Some(next_argument) = arguments_iter.next() else {
-                        stderr
-                            .push("When redirecting standard out, a file must be given".to_owned());
-                        break;
-                    };
-                    standard_out_output = Output::AppendFile(next_argument);
-                }
-                "2>" => {
-                    let Some(next_argument) = arguments_iter.next() else {
-                        stderr.push("When redirecting standard error to a file, you must provide a file name".to_owned());
-                        break;
-                    };
-
-                    standard_error_output = Output::CreateFile(next_argument);
-                }
-                "2>>" => {
-                    let Some(next_argument) = arguments_iter.next() else {
-                        stderr.push("When redirecting standard error to a file, you must provide a file name".to_owned());
-                        break;
-                    };
-
-                    standard_error_output = Output::AppendFile(next_argument);
-                }
-                _ => arguments.push(argument),
+    /// Expands the command and runs it in the current process, returning its
+    /// exit status. With `replace_process` set an external command replaces
+    /// the current process instead of running in a child, which is what
+    /// pipeline stages want since they are already forked.
+    pub fn run(&self, state: &mut ShellState, replace_process: bool) -> Result<i32> {
+        let assignments = self.expand_assignments(state)?;
+        let mut words = expand_words(&self.words, state)?;
+        let _saved_descriptors = self.redirects.apply(state)?;
+
+        if words.is_empty() {
+            for (name, value) in assignments {
+                state.set_variable(&name, value);
             }
+            // without a command the status is that of the last command
+            // substitution, if there was one
+            let has_substitution = self
+                .assignments
+                .iter()
+                .map(|(_, value)| value)
+                .chain(&self.words)
+                .any(Word::has_command_substitution);
+            return Ok(if has_substitution {
+                state.last_exit_status
+            } else {
+                0
+            });
+        }
+
+        let command_input = words.remove(0);
+
+        if let Some(body) = state.functions.get(&command_input).cloned() {
+            let saved_variables = state.push_temporary_variables(assignments);
+            let status = call_function(&body, words, state);
+            state.pop_temporary_variables(saved_variables);
+
+            return status;
         }
 
-        Ok((arguments, standard_out_output, standard_error_output))
+        let builtin_command = BuiltinCommand::from((command_input.clone(), words));
+        let saved_variables = state.push_temporary_variables(assignments);
+
+        let status = match builtin_command {
+            BuiltinCommand::NotFound(command_string, arguments) if replace_process => state
+                .find_command(&command_string)
+                .map_err(anyhow::Error::from)
+                .and_then(|executable| {
+                    Err(exec_external(&executable, &arguments, state.environment()).into())
+                }),
+            builtin_command => builtin_command
+                .run(state, &mut BuiltinOutput, &mut io::stderr())
+                .map_err(|error| builtin_error(&command_input, error)),
+        };
+
+        io::stdout().flush().context("flushing standard out")?;
+        state.pop_temporary_variables(saved_variables);
+
+        status
+    }
+
+    fn expand_assignments(&self, state: &mut ShellState) -> Result<Vec<(String, String)>> {
+        self.assignments
+            .iter()
+            .map(|(name, value)| Ok((name.clone(), expand_word_to_string(value, state)?)))
+            .collect()
     }
 }
 
-#[derive(Debug)]
-pub enum Output {
-    Standard,
-    CreateFile(String),
-    AppendFile(String),
+/// Turns a builtin's failure to read or write into an error that only fails
+/// the builtin, leaving anything else to stop the shell.
+fn builtin_error(name: &str, error: anyhow::Error) -> anyhow::Error {
+    match error.downcast_ref::<io::Error>() {
+        Some(io_error) => CustomError::Builtin(name.to_owned(), io_error_message(io_error)).into(),
+        None => error,
+    }
+}
+
+/// Standard output for builtins. Unlike `io::stdout`, which quietly drops
+/// writes to a closed descriptor, every failed write is a write error.
+struct BuiltinOutput;
+
+impl Write for BuiltinOutput {
+    fn write(&mut self, buffer: &[u8]) -> io::Result<usize> {
+        let written =
+            unsafe { libc::write(libc::STDOUT_FILENO, buffer.as_ptr().cast(), buffer.len()) };
+        if written < 0 {
+            let error = io::Error::last_os_error();
+            let message = format!("write error: {}", io_error_message(&error));
+            return Err(io::Error::new(error.kind(), message));
+        }
+
+        Ok(written as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Runs a function body with `arguments` as the positional parameters.
+fn call_function(
+    body: &CommandNode,
+    arguments: Vec<String>,
+    state: &mut ShellState,
+) -> Result<i32> {
+    let saved_parameters = std::mem::replace(&mut state.positional_parameters, arguments);
+    state.call_depth += 1;
+
+    let status = body.run(state, false);
+
+    state.call_depth -= 1;
+    state.positional_parameters = saved_parameters;
+    if state.control_flow == Some(ControlFlow::Return) {
+        state.control_flow = None;
+    }
+
+    status
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let assignments = self
+            .assignments
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"));
+        let words = self.words.iter().map(Word::to_string);
+
+        write!(
+            f,
+            "{}{}",
+            assignments.chain(words).collect::<Vec<_>>().join(" "),
+            self.redirects
+        )
+    }
 }
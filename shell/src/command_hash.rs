@@ -0,0 +1,218 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    errors::CustomError,
+    utils::{find_executable_file, is_executable},
+};
+
+/// Where commands were last found on the path, so that running one again
+/// doesn't search every directory. It is emptied whenever `PATH` changes.
+#[derive(Debug, Default)]
+pub struct CommandHash {
+    entries: HashMap<String, HashedCommand>,
+}
+
+#[derive(Debug)]
+pub struct HashedCommand {
+    pub path: PathBuf,
+    /// How many times the command has been run from this entry.
+    pub hits: usize,
+}
+
+impl CommandHash {
+    /// Finds the executable to run for `name`, remembering it for next time.
+    /// A name with a slash is never looked up or remembered.
+    pub fn find(&mut self, name: &str, paths: &[PathBuf]) -> Result<PathBuf, CustomError> {
+        if name.contains('/') {
+            return find_executable_file(name, paths).ok_or_else(|| path_error(name));
+        }
+
+        let entry = self
+            .remember(name, paths)
+            .ok_or_else(|| CustomError::CommandNotFound(name.to_owned()))?;
+        entry.hits += 1;
+
+        Ok(entry.path.clone())
+    }
+
+    /// Looks `name` up without running it, searching the path again if the
+    /// remembered file has been removed.
+    pub fn remember(&mut self, name: &str, paths: &[PathBuf]) -> Option<&mut HashedCommand> {
+        if self
+            .entries
+            .get(name)
+            .is_some_and(|entry| !is_executable(&entry.path))
+        {
+            self.entries.remove(name);
+        }
+
+        if !self.entries.contains_key(name) {
+            let path = find_executable_file(name, paths)?;
+            self.entries
+                .insert(name.to_owned(), HashedCommand { path, hits: 0 });
+        }
+
+        self.entries.get_mut(name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&HashedCommand> {
+        self.entries.get(name)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// The remembered commands, sorted by name.
+    pub fn entries(&self) -> Vec<(&String, &HashedCommand)> {
+        let mut entries: Vec<_> = self.entries.iter().collect();
+        entries.sort_by_key(|(name, _)| *name);
+        entries
+    }
+}
+
+/// Why the path `name` can't be run as a command.
+fn path_error(name: &str) -> CustomError {
+    match Path::new(name).metadata() {
+        Ok(metadata) if metadata.is_dir() => CustomError::IsADirectory(name.to_owned()),
+        Ok(_) => CustomError::PermissionDenied(name.to_owned()),
+        Err(_) => CustomError::NoSuchFile(name.to_owned()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt, path::Path};
+
+    use super::*;
+    use crate::{
+        builtin_commands::{builtin_type::builtin_type, hash::hash},
+        shell_state::ShellState,
+    };
+
+    /// Two directories that both hold an executable `tool`.
+    fn path_directories(test: &str) -> (PathBuf, PathBuf) {
+        let root = std::env::temp_dir().join(format!("shell-hash-{test}-{}", std::process::id()));
+        let (first, second) = (root.join("first"), root.join("second"));
+
+        for directory in [&first, &second] {
+            fs::create_dir_all(directory).unwrap();
+            let tool = directory.join("tool");
+            fs::write(&tool, "#!/bin/sh\n").unwrap();
+            fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        (first, second)
+    }
+
+    fn state_with_path(directories: &[&Path]) -> ShellState {
+        let mut state = ShellState::new(false).unwrap();
+        let path: Vec<_> = directories
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect();
+        state.set_variable("PATH", path.join(":"));
+        state
+    }
+
+    /// The status and output of `hash` or `type` run with `words`.
+    fn run(builtin: &str, words: &[&str], state: &mut ShellState) -> (i32, String, String) {
+        let arguments: Vec<String> = words.iter().map(|word| word.to_string()).collect();
+        let (mut stdout, mut stderr) = (vec![], vec![]);
+        let status = match builtin {
+            "hash" => hash(&arguments, state, &mut stdout, &mut stderr),
+            _ => builtin_type(arguments, state, &mut stdout, &mut stderr),
+        }
+        .unwrap();
+
+        let text = |bytes| String::from_utf8(bytes).unwrap();
+        (status, text(stdout), text(stderr))
+    }
+
+    #[test]
+    fn assigning_path_clears_the_hash() {
+        let (first, second) = path_directories("assign");
+        let mut state = state_with_path(&[&first, &second]);
+
+        assert_eq!(state.find_command("tool").unwrap(), first.join("tool"));
+        assert_eq!(state.find_command("tool").unwrap(), first.join("tool"));
+        assert_eq!(state.command_hash.get("tool").unwrap().hits, 2);
+
+        state.set_variable("PATH", second.display().to_string());
+        assert!(state.command_hash.get("tool").is_none());
+        assert_eq!(state.find_command("tool").unwrap(), second.join("tool"));
+
+        fs::remove_dir_all(first.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn hash_remembers_and_forgets() {
+        let (first, second) = path_directories("builtin");
+        let mut state = state_with_path(&[&first, &second]);
+
+        let (status, _, stderr) = run("hash", &[], &mut state);
+        assert_eq!((status, stderr.as_str()), (0, "hash: hash table empty\n"));
+
+        let (status, _, stderr) = run("hash", &["tool", "echo", "missing-tool"], &mut state);
+        assert_eq!(
+            (status, stderr.as_str()),
+            (1, "hash: missing-tool: not found\n")
+        );
+        assert_eq!(state.command_hash.get("tool").unwrap().hits, 0);
+        assert!(state.command_hash.get("echo").is_none());
+
+        let (_, stdout, _) = run("hash", &[], &mut state);
+        assert_eq!(
+            stdout,
+            format!("hits\tcommand\n   0\t{}\n", first.join("tool").display())
+        );
+
+        run("hash", &["-r"], &mut state);
+        assert!(state.command_hash.entries().is_empty());
+
+        fs::remove_dir_all(first.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn type_all_lists_every_match() {
+        let (first, second) = path_directories("type");
+        let mut state = state_with_path(&[&first, &second]);
+        state
+            .aliases
+            .insert("tool".to_owned(), "tool -v".to_owned());
+
+        let (status, stdout, _) = run("type", &["-a", "tool"], &mut state);
+        assert_eq!(status, 0);
+        assert_eq!(
+            stdout,
+            format!(
+                "tool is aliased to `tool -v'\ntool is {}\ntool is {}\n",
+                first.join("tool").display(),
+                second.join("tool").display()
+            )
+        );
+
+        let (_, stdout, _) = run("type", &["-a", "echo"], &mut state);
+        assert_eq!(stdout, "echo is a shell builtin\n");
+
+        // without -a a remembered command is reported as hashed
+        state.aliases.clear();
+        state.find_command("tool").unwrap();
+        let (_, stdout, _) = run("type", &["tool"], &mut state);
+        assert_eq!(
+            stdout,
+            format!("tool is hashed ({})\n", first.join("tool").display())
+        );
+
+        let (status, _, stderr) = run("type", &["missing-tool"], &mut state);
+        assert_eq!(
+            (status, stderr.as_str()),
+            (1, "type: missing-tool: not found\n")
+        );
+
+        fs::remove_dir_all(first.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,178 @@
+use anyhow::Result;
+use std::fmt;
+
+use crate::{
+    pipeline::{Pipeline, run_in_child},
+    shell_state::ShellState,
+    traps::run_pending_traps,
+};
+
+/// A sequence of `&&`/`||` lists separated by `;`, `&` or newlines.
+#[derive(Debug, Default)]
+pub struct CommandList {
+    pub items: Vec<ListItem>,
+}
+
+#[derive(Debug)]
+pub struct ListItem {
+    pub and_or: AndOr,
+    /// Set when the item was followed by `&`.
+    pub background: bool,
+}
+
+/// Pipelines joined by `&&` and `||`, each run depending on the exit status
+/// of the one before.
+#[derive(Debug)]
+pub struct AndOr {
+    pub first: Pipeline,
+    pub rest: Vec<(Connector, Pipeline)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Connector {
+    And,
+    Or,
+}
+
+impl CommandList {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// What has to follow the list when it is written out before a closing
+    /// keyword: nothing after a trailing `&`, otherwise `;`.
+    pub fn terminator(&self) -> &'static str {
+        if self.items.last().is_some_and(|item| item.background) {
+            ""
+        } else {
+            ";"
+        }
+    }
+
+    /// Runs each item in turn, updating `$?` as it goes, and returns the
+    /// status of the last one. Traps for signals caught while an item ran
+    /// are run after it.
+    pub fn run(&self, state: &mut ShellState) -> Result<i32> {
+        for item in &self.items {
+            if state.should_stop() {
+                break;
+            }
+
+            state.last_exit_status = item.run(state)?;
+            run_pending_traps(state)?;
+        }
+
+        Ok(state.last_exit_status)
+    }
+}
+
+impl ListItem {
+    fn run(&self, state: &mut ShellState) -> Result<i32> {
+        if !self.background {
+            return self.and_or.run(state);
+        }
+
+        if self.and_or.rest.is_empty() {
+            return self.and_or.first.run(state, true);
+        }
+
+        run_in_child(state, false, self.and_or.to_string(), |state| {
+            self.and_or.run(state)
+        })
+    }
+}
+
+impl AndOr {
+    pub fn run(&self, state: &mut ShellState) -> Result<i32> {
+        let mut status = self.first.run(state, false)?;
+
+        for (connector, pipeline) in &self.rest {
+            if state.should_stop() {
+                break;
+            }
+
+            let should_run = match connector {
+                Connector::And => status == 0,
+                Connector::Or => status != 0,
+            };
+            if should_run {
+                state.last_exit_status = status;
+                status = pipeline.run(state, false)?;
+            }
+        }
+
+        Ok(status)
+    }
+}
+
+impl fmt::Display for CommandList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, item) in self.items.iter().enumerate() {
+            if index > 0 {
+                write!(f, " ")?;
+            }
+            write!(f, "{}", item.and_or)?;
+
+            if item.background {
+                write!(f, " &")?;
+            } else if index + 1 < self.items.len() {
+                write!(f, ";")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for AndOr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.first)?;
+
+        for (connector, pipeline) in &self.rest {
+            let operator = match connector {
+                Connector::And => "&&",
+                Connector::Or => "||",
+            };
+            write!(f, " {operator} {pipeline}")?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_parser::parse_input, parser::parse};
+
+    fn run(input: &str, state: &mut ShellState) -> i32 {
+        let list = parse(parse_input(input.to_owned()).unwrap()).unwrap();
+        list.run(state).unwrap()
+    }
+
+    #[test]
+    fn and_or_lists_short_circuit() {
+        let mut state = ShellState::new(false).unwrap();
+
+        assert_eq!(run("false && LIST_AND=1", &mut state), 1);
+        assert_eq!(run("true || LIST_OR=1", &mut state), 0);
+        assert_eq!(run("false || LIST_OR_ELSE=1", &mut state), 0);
+        assert_eq!(run("true && false || LIST_BOTH=1", &mut state), 0);
+
+        assert_eq!(state.get_variable("LIST_AND"), None);
+        assert_eq!(state.get_variable("LIST_OR"), None);
+        assert_eq!(state.get_variable("LIST_OR_ELSE").unwrap(), "1");
+        assert_eq!(state.get_variable("LIST_BOTH").unwrap(), "1");
+    }
+
+    #[test]
+    fn only_groups_change_the_shell() {
+        let mut state = ShellState::new(false).unwrap();
+
+        assert_eq!(run("{ LIST_GROUP=1; false; }", &mut state), 1);
+        assert_eq!(run("(LIST_SUBSHELL=1; sh -c 'exit 4')", &mut state), 4);
+
+        assert_eq!(state.get_variable("LIST_GROUP").unwrap(), "1");
+        assert_eq!(state.get_variable("LIST_SUBSHELL"), None);
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use std::{fmt, rc::Rc};
+
+use crate::{
+    command::Command, compound_command::CompoundCommand, errors::CustomError, redirect::Redirects,
+    shell_state::ShellState,
+};
+
+/// A single stage of a pipeline.
+#[derive(Debug)]
+pub enum CommandNode {
+    Simple(Command),
+    /// A group, subshell, conditional or loop, with redirections that apply
+    /// to everything inside it.
+    Compound(CompoundCommand, Redirects),
+    /// `name() body`, which stores the body to run when `name` is used as a
+    /// command.
+    FunctionDefinition(String, Rc<CommandNode>),
+}
+
+impl CommandNode {
+    /// Runs the command. `replace_process` is set when already running in a
+    /// forked child, so the command may take over the process. A failure
+    /// such as a missing command or file is reported and becomes the
+    /// command's exit status instead of stopping the shell.
+    pub fn run(&self, state: &mut ShellState, replace_process: bool) -> Result<i32> {
+        match self.run_unchecked(state, replace_process) {
+            Err(error) => match error.downcast_ref::<CustomError>() {
+                Some(custom_error) => {
+                    custom_error.report();
+                    Ok(custom_error.exit_status())
+                }
+                None => Err(error),
+            },
+            status => status,
+        }
+    }
+
+    fn run_unchecked(&self, state: &mut ShellState, replace_process: bool) -> Result<i32> {
+        match self {
+            CommandNode::Simple(command) => command.run(state, replace_process),
+            CommandNode::Compound(compound, redirects) => {
+                let _saved_descriptors = redirects.apply(state)?;
+                compound.run(state, replace_process)
+            }
+            CommandNode::FunctionDefinition(name, body) => {
+                state.functions.insert(name.clone(), Rc::clone(body));
+                Ok(0)
+            }
+        }
+    }
+}
+
+impl fmt::Display for CommandNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandNode::Simple(command) => write!(f, "{command}"),
+            CommandNode::Compound(compound, redirects) => write!(f, "{compound}{redirects}"),
+            CommandNode::FunctionDefinition(name, body) => write!(f, "{name}() {body}"),
+        }
+    }
+}
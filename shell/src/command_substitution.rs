@@ -0,0 +1,51 @@
+use anyhow::{Context, Result};
+use std::io::{self, Read};
+
+use crate::{
+    input::Input,
+    process::{Fork, duplicate_onto, exit_child, fork, wait_for},
+    shell_state::ShellState,
+    traps::run_subshell,
+};
+
+/// Runs `command` in a forked copy of the shell and returns what it wrote to
+/// standard output, without trailing newlines. The command's exit status
+/// becomes `$?`.
+pub fn substitute_command(command: &str, state: &mut ShellState) -> Result<String> {
+    let (mut reader, writer) = io::pipe().context("creating pipe for command substitution")?;
+
+    match fork()? {
+        Fork::Child => {
+            drop(reader);
+            // the command belongs to whatever job is expanding it
+            state.jobs.job_control = false;
+            state.jobs.enter_job(0, true);
+
+            let status = run_subshell(state, |state| {
+                duplicate_onto(&writer, libc::STDOUT_FILENO)?;
+                drop(writer);
+                Input::from_text(command.to_owned()).run(state)?;
+                Ok(state.last_exit_status)
+            })
+            .unwrap_or_else(|error| {
+                eprintln!("shell: {error:#}");
+                1
+            });
+            exit_child(status);
+        }
+        Fork::Parent(pid) => {
+            drop(writer);
+            let mut output = vec![];
+            let read = reader
+                .read_to_end(&mut output)
+                .context("reading command substitution output");
+            drop(reader);
+
+            state.last_exit_status = wait_for(pid, false)?.exit_status();
+            read?;
+
+            let output = String::from_utf8_lossy(&output);
+            Ok(output.trim_end_matches('\n').to_owned())
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use std::{fs::read_dir, os::unix::fs::MetadataExt, path::PathBuf};
+
+use crate::builtin_commands::BUILTIN_NAMES;
+
+/// Completion candidates for `word`: builtins and executables on the path in
+/// command position, file names otherwise. Candidates are sorted, and
+/// directories end in `/`.
+pub fn complete(word: &str, command_position: bool, path: &[PathBuf]) -> Vec<String> {
+    let mut candidates = if command_position && !word.contains('/') {
+        complete_command(word, path)
+    } else {
+        complete_file(word)
+    };
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+fn complete_command(word: &str, path: &[PathBuf]) -> Vec<String> {
+    let builtins = BUILTIN_NAMES
+        .iter()
+        .filter(|name| name.starts_with(word))
+        .map(|name| name.to_string());
+
+    let executables = path
+        .iter()
+        .filter_map(|directory| read_dir(directory).ok())
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| {
+            entry
+                .metadata()
+                .is_ok_and(|metadata| metadata.is_file() && metadata.mode() & 0o111 != 0)
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.starts_with(word));
+
+    builtins.chain(executables).collect()
+}
+
+fn complete_file(word: &str) -> Vec<String> {
+    let (directory, prefix) = match word.rfind('/') {
+        Some(index) => (&word[..=index], &word[index + 1..]),
+        None => ("", word),
+    };
+    let search_directory = match directory {
+        "" => PathBuf::from("."),
+        directory => match directory.strip_prefix("~/") {
+            Some(rest) => std::env::home_dir().unwrap_or_default().join(rest),
+            None => PathBuf::from(directory),
+        },
+    };
+
+    let Ok(entries) = read_dir(search_directory) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+
+            let suffix = if entry.path().is_dir() { "/" } else { "" };
+            Some(format!("{directory}{}{suffix}", name.replace(' ', "\\ ")))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    use super::*;
+
+    #[test]
+    fn candidates() {
+        let directory = std::env::temp_dir().join(format!("shell-complete-{}", std::process::id()));
+        fs::create_dir_all(directory.join("subdirectory")).unwrap();
+        for (name, mode) in [("tool-a", 0o755), ("tool-b", 0o755), ("tool-notes", 0o644)] {
+            let path = directory.join(name);
+            fs::write(&path, "").unwrap();
+            fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+        }
+        fs::write(directory.join("two words"), "").unwrap();
+        fs::write(directory.join(".hidden"), "").unwrap();
+        let path = [directory.clone()];
+        let root = directory.display();
+
+        // commands: executables on the path and builtins
+        assert_eq!(complete("tool", true, &path), ["tool-a", "tool-b"]);
+        assert_eq!(complete("hist", true, &path), ["history"]);
+        assert!(complete("ec", true, &path).contains(&"echo".to_owned()));
+
+        // files, with directories ending in `/`
+        assert_eq!(
+            complete(&format!("{root}/su"), false, &path),
+            [format!("{root}/subdirectory/")]
+        );
+        assert_eq!(
+            complete(&format!("{root}/tool-"), true, &path),
+            [
+                format!("{root}/tool-a"),
+                format!("{root}/tool-b"),
+                format!("{root}/tool-notes")
+            ]
+        );
+        assert_eq!(
+            complete(&format!("{root}/tw"), false, &path),
+            [format!("{root}/two\\ words")]
+        );
+        assert_eq!(
+            complete(&format!("{root}/."), false, &path),
+            [format!("{root}/.hidden")]
+        );
+        assert!(complete(&format!("{root}/missing/"), false, &path).is_empty());
+
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,323 @@
+use anyhow::Result;
+use std::fmt;
+
+use crate::{
+    command_list::CommandList,
+    expansion::{expand_pattern, expand_word_to_string, expand_words},
+    input_parser::Word,
+    pattern::pattern_matches,
+    pipeline::run_in_child,
+    shell_state::{ControlFlow, ShellState},
+};
+
+#[derive(Debug)]
+pub enum CompoundCommand {
+    /// `{ list; }`, run in the current shell.
+    Group(CommandList),
+    /// `( list )`, run in a forked copy of the shell so changes to the
+    /// working directory and variables don't leak out.
+    Subshell(CommandList),
+    /// `if list; then list; [elif list; then list;]... [else list;] fi`
+    If {
+        branches: Vec<(CommandList, CommandList)>,
+        otherwise: Option<CommandList>,
+    },
+    /// `while list; do list; done` or `until list; do list; done`
+    Loop {
+        until: bool,
+        condition: CommandList,
+        body: CommandList,
+    },
+    /// `for name [in word...]; do list; done`, looping over the positional
+    /// parameters when there is no `in`.
+    For {
+        variable: String,
+        words: Option<Vec<Word>>,
+        body: CommandList,
+    },
+    /// `case word in pattern [| pattern]...) list;; ... esac`
+    Case { word: Word, items: Vec<CaseItem> },
+}
+
+#[derive(Debug)]
+pub struct CaseItem {
+    pub patterns: Vec<Word>,
+    pub body: CommandList,
+}
+
+impl CompoundCommand {
+    pub fn run(&self, state: &mut ShellState, replace_process: bool) -> Result<i32> {
+        match self {
+            CompoundCommand::Group(body) => body.run(state),
+            CompoundCommand::Subshell(body) if replace_process => body.run(state),
+            CompoundCommand::Subshell(body) => {
+                run_in_child(state, true, self.to_string(), |state| body.run(state))
+            }
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (condition, body) in branches {
+                    let status = condition.run(state)?;
+                    if state.should_stop() {
+                        return Ok(status);
+                    }
+                    if status == 0 {
+                        return body.run(state);
+                    }
+                }
+
+                match otherwise {
+                    Some(body) => body.run(state),
+                    None => Ok(0),
+                }
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                state.loop_depth += 1;
+                let status = run_while(state, *until, condition, body);
+                state.loop_depth -= 1;
+                status
+            }
+            CompoundCommand::For {
+                variable,
+                words,
+                body,
+            } => {
+                let values = match words {
+                    Some(words) => expand_words(words, state)?,
+                    None => state.positional_parameters.clone(),
+                };
+
+                state.loop_depth += 1;
+                let status = run_for(state, variable, values, body);
+                state.loop_depth -= 1;
+                status
+            }
+            CompoundCommand::Case { word, items } => {
+                let text = expand_word_to_string(word, state)?;
+
+                for item in items {
+                    for pattern in &item.patterns {
+                        if pattern_matches(&expand_pattern(pattern, state)?, &text) {
+                            return item.body.run(state);
+                        }
+                    }
+                }
+
+                Ok(0)
+            }
+        }
+    }
+}
+
+fn run_while(
+    state: &mut ShellState,
+    until: bool,
+    condition: &CommandList,
+    body: &CommandList,
+) -> Result<i32> {
+    let mut status = 0;
+
+    loop {
+        let condition_status = condition.run(state)?;
+        if leave_loop(state) || (condition_status == 0) == until {
+            break;
+        }
+
+        status = body.run(state)?;
+        if leave_loop(state) {
+            break;
+        }
+    }
+
+    Ok(status)
+}
+
+fn run_for(
+    state: &mut ShellState,
+    variable: &str,
+    values: Vec<String>,
+    body: &CommandList,
+) -> Result<i32> {
+    let mut status = 0;
+
+    for value in values {
+        state.set_variable(variable, value);
+
+        status = body.run(state)?;
+        if leave_loop(state) {
+            break;
+        }
+    }
+
+    Ok(status)
+}
+
+/// Handles a pending `break` or `continue` at the end of an iteration,
+/// returning whether the loop should stop, as it also does for `exit` and
+/// Ctrl-C.
+fn leave_loop(state: &mut ShellState) -> bool {
+    match state.control_flow {
+        Some(ControlFlow::Break(levels)) => {
+            state.control_flow = (levels > 1).then(|| ControlFlow::Break(levels - 1));
+            true
+        }
+        Some(ControlFlow::Continue(levels)) if levels > 1 => {
+            state.control_flow = Some(ControlFlow::Continue(levels - 1));
+            true
+        }
+        Some(ControlFlow::Continue(_)) => {
+            state.control_flow = None;
+            false
+        }
+        Some(ControlFlow::Return) => true,
+        None => state.should_stop(),
+    }
+}
+
+impl fmt::Display for CompoundCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CompoundCommand::Group(body) => write!(f, "{{ {body}{} }}", body.terminator()),
+            CompoundCommand::Subshell(body) => write!(f, "({body})"),
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            } => {
+                for (index, (condition, body)) in branches.iter().enumerate() {
+                    let keyword = if index == 0 { "if" } else { " elif" };
+                    write!(
+                        f,
+                        "{keyword} {condition}{} then {body}{}",
+                        condition.terminator(),
+                        body.terminator()
+                    )?;
+                }
+                if let Some(body) = otherwise {
+                    write!(f, " else {body}{}", body.terminator())?;
+                }
+                write!(f, " fi")
+            }
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            } => {
+                let keyword = if *until { "until" } else { "while" };
+                write!(
+                    f,
+                    "{keyword} {condition}{} do {body}{} done",
+                    condition.terminator(),
+                    body.terminator()
+                )
+            }
+            CompoundCommand::For {
+                variable,
+                words,
+                body,
+            } => {
+                write!(f, "for {variable}")?;
+                if let Some(words) = words {
+                    write!(f, " in")?;
+                    for word in words {
+                        write!(f, " {word}")?;
+                    }
+                }
+                write!(f, "; do {body}{} done", body.terminator())
+            }
+            CompoundCommand::Case { word, items } => {
+                write!(f, "case {word} in")?;
+                for item in items {
+                    let patterns: Vec<String> = item.patterns.iter().map(Word::to_string).collect();
+                    write!(f, " {}) {};;", patterns.join(" | "), item.body)?;
+                }
+                write!(f, " esac")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_parser::parse_input, parser::parse};
+
+    fn run(input: &str, state: &mut ShellState) -> i32 {
+        let list = parse(parse_input(input.to_owned()).unwrap()).unwrap();
+        list.run(state).unwrap()
+    }
+
+    fn variable(name: &str, state: &ShellState) -> String {
+        state.get_variable(name).unwrap_or_default()
+    }
+
+    #[test]
+    fn conditionals() {
+        let mut state = ShellState::new(false).unwrap();
+
+        run(
+            "if false; then R=a; elif true; then R=b; else R=c; fi",
+            &mut state,
+        );
+        assert_eq!(variable("R", &state), "b");
+        assert_eq!(run("if false; then true; fi", &mut state), 0);
+        assert_eq!(run("if true; then sh -c 'exit 2'; fi", &mut state), 2);
+
+        run(
+            "case main.rs in *.txt) K=t;; *.r[sx] | x) K=r;; esac",
+            &mut state,
+        );
+        assert_eq!(variable("K", &state), "r");
+        assert_eq!(run("case x in y) false;; esac", &mut state), 0);
+    }
+
+    #[test]
+    fn loops() {
+        let mut state = ShellState::new(false).unwrap();
+
+        run("N=; for x in 1 '2 3' 4; do N=$N[$x]; done", &mut state);
+        assert_eq!(variable("N", &state), "[1][2 3][4]");
+
+        run(
+            "L=; for x in a b c d; do
+                case $x in b) continue;; d) break;; esac
+                L=$L$x
+            done",
+            &mut state,
+        );
+        assert_eq!(variable("L", &state), "ac");
+
+        run(
+            "W=; while false; do W=ran; done; until true; do W=ran; done",
+            &mut state,
+        );
+        assert_eq!(variable("W", &state), "");
+
+        state.positional_parameters = vec!["p".to_owned(), "q".to_owned()];
+        run("A=; for x; do A=$A$x; done", &mut state);
+        assert_eq!(variable("A", &state), "pq");
+    }
+
+    #[test]
+    fn functions() {
+        let mut state = ShellState::new(false).unwrap();
+        state.positional_parameters = vec!["outer".to_owned()];
+
+        assert_eq!(
+            run("f() { F=$1$#; return 3; F=late; }; f x y", &mut state),
+            3
+        );
+        assert_eq!(variable("F", &state), "x2");
+        assert_eq!(state.positional_parameters, ["outer"]);
+
+        run(
+            "g() { for x in a b; do return; done; G=late; }; g",
+            &mut state,
+        );
+        assert_eq!(variable("G", &state), "");
+    }
+}
@@ -1,7 +1,162 @@
+use std::io;
+
 use thiserror::Error;
 
+/// Errors that stop a single command rather than the whole shell. They are
+/// reported as `shell: ...` and give the failed command the status from
+/// `exit_status`.
 #[derive(Debug, Error)]
 pub enum CustomError {
     #[error("{0}: command not found")]
     CommandNotFound(String),
+    #[error("{0}: No such file or directory")]
+    NoSuchFile(String),
+    #[error("{0}: Permission denied")]
+    PermissionDenied(String),
+    #[error("{0}: Is a directory")]
+    IsADirectory(String),
+    /// The file was found but the system refused to run it.
+    #[error("{0}: {1}")]
+    CannotExecute(String, String),
+    #[error("syntax error near unexpected token `{0}'")]
+    UnexpectedToken(String),
+    #[error("syntax error: unexpected end of file")]
+    UnexpectedEndOfInput,
+    /// A `${...}` with an operator the shell doesn't support, such as
+    /// `${#name}` or `${name%word}`.
+    #[error("{0}: bad substitution")]
+    BadSubstitution(String),
+    /// A `${name:=word}` whose parameter isn't a variable, like `${1:=x}`.
+    #[error("{0}: cannot assign in this way")]
+    CannotAssign(String),
+    #[error("{0}: {1}")]
+    Arithmetic(String, String),
+    /// A file named in a redirection couldn't be opened.
+    #[error("{0}: {1}")]
+    Redirection(String, String),
+    /// A history reference like `!foo` that matches no earlier line.
+    #[error("{0}: event not found")]
+    EventNotFound(String),
+    #[error("{0}: ambiguous redirect")]
+    AmbiguousRedirect(String),
+    #[error("{0}: Bad file descriptor")]
+    BadFileDescriptor(String),
+    /// A builtin failed to read or write, such as `echo` into a full disk.
+    #[error("{0}: {1}")]
+    Builtin(String, String),
+}
+
+impl CustomError {
+    /// The status of a command that failed this way: 127 when there was
+    /// nothing to run, 126 when it couldn't be run and 2 for bad syntax.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Self::CommandNotFound(_) | Self::NoSuchFile(_) => 127,
+            Self::PermissionDenied(_) | Self::IsADirectory(_) | Self::CannotExecute(..) => 126,
+            Self::UnexpectedToken(_) | Self::UnexpectedEndOfInput => 2,
+            _ => 1,
+        }
+    }
+
+    /// Prints the error to standard error.
+    pub fn report(&self) {
+        eprintln!("shell: {self}");
+    }
+}
+
+/// The system's description of an I/O error, without the `(os error N)`
+/// that `Display` adds.
+pub fn io_error_message(error: &io::Error) -> String {
+    let message = error.to_string();
+
+    match error.raw_os_error() {
+        Some(code) => message
+            .strip_suffix(&format!(" (os error {code})"))
+            .unwrap_or(&message)
+            .to_owned(),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_statuses() {
+        let name = || "name".to_owned();
+        let cases = [
+            (CustomError::CommandNotFound(name()), 127),
+            (CustomError::NoSuchFile(name()), 127),
+            (CustomError::PermissionDenied(name()), 126),
+            (CustomError::IsADirectory(name()), 126),
+            (
+                CustomError::CannotExecute(name(), "Exec format error".to_owned()),
+                126,
+            ),
+            (CustomError::UnexpectedToken(")".to_owned()), 2),
+            (CustomError::UnexpectedEndOfInput, 2),
+            (CustomError::BadSubstitution("${x%y}".to_owned()), 1),
+            (
+                CustomError::Redirection(name(), "Permission denied".to_owned()),
+                1,
+            ),
+            (CustomError::BadFileDescriptor("3".to_owned()), 1),
+            (
+                CustomError::Builtin("echo".to_owned(), "write error".to_owned()),
+                1,
+            ),
+        ];
+
+        for (error, status) in cases {
+            assert_eq!(error.exit_status(), status, "{error}");
+        }
+    }
+
+    #[test]
+    fn messages() {
+        let cases = [
+            (
+                CustomError::CommandNotFound("nope".to_owned()),
+                "nope: command not found",
+            ),
+            (
+                CustomError::NoSuchFile("./x".to_owned()),
+                "./x: No such file or directory",
+            ),
+            (
+                CustomError::PermissionDenied("./x".to_owned()),
+                "./x: Permission denied",
+            ),
+            (
+                CustomError::UnexpectedToken("fi".to_owned()),
+                "syntax error near unexpected token `fi'",
+            ),
+            (
+                CustomError::UnexpectedEndOfInput,
+                "syntax error: unexpected end of file",
+            ),
+            (
+                CustomError::AmbiguousRedirect("$x".to_owned()),
+                "$x: ambiguous redirect",
+            ),
+            (
+                CustomError::Builtin("cd".to_owned(), "too many arguments".to_owned()),
+                "cd: too many arguments",
+            ),
+        ];
+
+        for (error, message) in cases {
+            assert_eq!(error.to_string(), message);
+        }
+    }
+
+    #[test]
+    fn io_error_messages_drop_the_error_code() {
+        let error = io::Error::from_raw_os_error(libc::ENOENT);
+        assert_eq!(io_error_message(&error), "No such file or directory");
+
+        let error = io::Error::other("custom");
+        assert_eq!(io_error_message(&error), "custom");
+    }
 }
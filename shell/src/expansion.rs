@@ -0,0 +1,383 @@
+use anyhow::Result;
+
+use crate::{
+    arithmetic,
+    brace_expansion::expand_braces,
+    command_substitution::substitute_command,
+    errors::CustomError,
+    input_parser::{ParameterOperator, Word, WordPart, is_valid_name, parse_here_document},
+    pattern::glob,
+    shell_state::ShellState,
+};
+
+pub const DEFAULT_IFS: &str = " \t\n";
+
+/// Expands words into the arguments of a command: brace expansion, then
+/// parameter, command and arithmetic expansion and field splitting, then
+/// filename globbing.
+pub fn expand_words(words: &[Word], state: &mut ShellState) -> Result<Vec<String>> {
+    let mut fields = vec![];
+
+    for word in words.iter().flat_map(expand_braces) {
+        fields.extend(expand_word(&word, state)?);
+    }
+
+    Ok(fields)
+}
+
+/// Expands a word into fields. The results of unquoted expansions are split
+/// on `IFS`, everything else stays in the field it was written in. Fields
+/// containing unquoted `*`, `?` or `[` are replaced by the sorted paths they
+/// match, if there are any.
+pub fn expand_word(word: &Word, state: &mut ShellState) -> Result<Vec<String>> {
+    let ifs = state
+        .get_variable("IFS")
+        .unwrap_or_else(|| DEFAULT_IFS.to_owned());
+    let mut fields = vec![];
+    let mut current_field = Field::default();
+    let mut has_field = word.quoted;
+
+    if let [
+        WordPart::Parameter {
+            name, quoted: true, ..
+        },
+    ] = word.parts.as_slice()
+        && name == "@"
+    {
+        // "$@" is one field per positional parameter, and none without any
+        return Ok(state.positional_parameters.clone());
+    }
+
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(text) => {
+                current_field.push_unquoted(text);
+                has_field = true;
+            }
+            WordPart::Quoted(text) => {
+                current_field.push_quoted(text);
+                has_field = true;
+            }
+            WordPart::Parameter {
+                name, quoted: true, ..
+            } if name == "@" => {
+                // inside a larger word "$@" still breaks between parameters
+                if let Some((last, rest)) = state.positional_parameters.split_last() {
+                    for parameter in rest {
+                        current_field.push_quoted(parameter);
+                        std::mem::take(&mut current_field).finish(&mut fields);
+                    }
+                    current_field.push_quoted(last);
+                }
+            }
+            WordPart::Parameter { quoted: true, .. }
+            | WordPart::CommandSubstitution { quoted: true, .. }
+            | WordPart::Arithmetic { quoted: true, .. } => {
+                current_field.push_quoted(&expand_part(part, state)?);
+            }
+            WordPart::Parameter { .. }
+            | WordPart::CommandSubstitution { .. }
+            | WordPart::Arithmetic { .. } => {
+                let value = expand_part(part, state)?;
+
+                for character in value.chars() {
+                    if ifs.contains(character) {
+                        if has_field {
+                            std::mem::take(&mut current_field).finish(&mut fields);
+                            has_field = false;
+                        }
+                    } else {
+                        current_field.push_unquoted(character.encode_utf8(&mut [0; 4]));
+                        has_field = true;
+                    }
+                }
+            }
+        }
+    }
+
+    if has_field {
+        current_field.finish(&mut fields);
+    }
+
+    Ok(fields)
+}
+
+/// A field being built, along with the same text as a glob pattern where
+/// quoted characters are escaped.
+#[derive(Default)]
+struct Field {
+    text: String,
+    pattern: String,
+    has_wildcards: bool,
+}
+
+impl Field {
+    fn push_unquoted(&mut self, text: &str) {
+        self.text.push_str(text);
+        self.pattern.push_str(text);
+        self.has_wildcards |= text.contains(['*', '?', '[']);
+    }
+
+    fn push_quoted(&mut self, text: &str) {
+        self.text.push_str(text);
+        push_escaped(&mut self.pattern, text);
+    }
+
+    /// Adds the paths the field matches to `fields`, or the field itself
+    /// when it has no wildcards or matches nothing.
+    fn finish(self, fields: &mut Vec<String>) {
+        let matches = if self.has_wildcards {
+            glob(&self.pattern)
+        } else {
+            vec![]
+        };
+
+        if matches.is_empty() {
+            fields.push(self.text);
+        } else {
+            fields.extend(matches);
+        }
+    }
+}
+
+/// Expands a word without field splitting, as done for assignment values
+/// and redirection targets.
+pub fn expand_word_to_string(word: &Word, state: &mut ShellState) -> Result<String> {
+    word.parts
+        .iter()
+        .map(|part| expand_part(part, state))
+        .collect()
+}
+
+/// Expands a word for use as a pattern in `case`, escaping quoted text so
+/// that only unquoted `*`, `?` and `[` act as wildcards.
+pub fn expand_pattern(word: &Word, state: &mut ShellState) -> Result<String> {
+    let mut pattern = String::new();
+
+    for part in &word.parts {
+        match part {
+            WordPart::Literal(text) => pattern.push_str(text),
+            WordPart::Quoted(text) => push_escaped(&mut pattern, text),
+            WordPart::Parameter { quoted, .. }
+            | WordPart::CommandSubstitution { quoted, .. }
+            | WordPart::Arithmetic { quoted, .. } => {
+                let value = expand_part(part, state)?;
+                if *quoted {
+                    push_escaped(&mut pattern, &value);
+                } else {
+                    pattern.push_str(&value);
+                }
+            }
+        }
+    }
+
+    Ok(pattern)
+}
+
+fn push_escaped(pattern: &mut String, text: &str) {
+    for character in text.chars() {
+        if "*?[]\\".contains(character) {
+            pattern.push('\\');
+        }
+        pattern.push(character);
+    }
+}
+
+/// The text a single part of a word expands to, before any field splitting.
+fn expand_part(part: &WordPart, state: &mut ShellState) -> Result<String> {
+    match part {
+        WordPart::Literal(text) | WordPart::Quoted(text) => Ok(text.clone()),
+        WordPart::Parameter {
+            name, operation, ..
+        } => {
+            let value = state.get_variable(name).filter(|value| !value.is_empty());
+
+            match (value, operation) {
+                (Some(_), Some((ParameterOperator::Alternative, word))) => {
+                    expand_word_to_string(word, state)
+                }
+                (None, Some((ParameterOperator::Alternative, _))) => Ok(String::new()),
+                (Some(value), _) => Ok(value),
+                (None, Some((ParameterOperator::Default, word))) => {
+                    expand_word_to_string(word, state)
+                }
+                (None, Some((ParameterOperator::Assign, word))) => {
+                    if !is_valid_name(name) {
+                        return Err(CustomError::CannotAssign(format!("${name}")).into());
+                    }
+                    let value = expand_word_to_string(word, state)?;
+                    state.set_variable(name, value.clone());
+                    Ok(value)
+                }
+                (None, None) => Ok(String::new()),
+            }
+        }
+        WordPart::CommandSubstitution { command, .. } => substitute_command(command, state),
+        WordPart::Arithmetic { expression, .. } => {
+            let expression = expand_word_to_string(&parse_here_document(expression)?, state)?;
+            Ok(arithmetic::evaluate(&expression, state)?.to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        input::Input,
+        input_parser::{Token, parse_input},
+    };
+
+    fn state() -> ShellState {
+        ShellState::new(false).unwrap()
+    }
+
+    fn expand(text: &str, state: &mut ShellState) -> Vec<String> {
+        let words: Vec<Word> = parse_input(text.to_owned())
+            .unwrap()
+            .into_iter()
+            .filter_map(|token| match token {
+                Token::Word(word) => Some(word),
+                _ => None,
+            })
+            .collect();
+
+        expand_words(&words, state).unwrap()
+    }
+
+    fn run(commands: &str, state: &mut ShellState) {
+        Input::from_text(commands.to_owned()).run(state).unwrap();
+    }
+
+    #[test]
+    fn field_splitting() {
+        let mut state = state();
+        state.set_variable("EXPANSION_WORDS", " a  b\tc ".to_owned());
+        state.set_variable("EXPANSION_NOTHING", String::new());
+
+        assert_eq!(expand("$EXPANSION_WORDS", &mut state), ["a", "b", "c"]);
+        assert_eq!(
+            expand("x${EXPANSION_WORDS}y", &mut state),
+            ["x", "a", "b", "c", "y"]
+        );
+        assert_eq!(expand("\"$EXPANSION_WORDS\"", &mut state), [" a  b\tc "]);
+        assert_eq!(
+            expand("'$EXPANSION_WORDS'", &mut state),
+            ["$EXPANSION_WORDS"]
+        );
+        assert!(expand("$EXPANSION_NOTHING", &mut state).is_empty());
+        assert_eq!(expand("\"$EXPANSION_NOTHING\"", &mut state), [""]);
+
+        state.set_variable("IFS", ":".to_owned());
+        state.set_variable("EXPANSION_PATH", "/bin:/usr/bin x".to_owned());
+        assert_eq!(
+            expand("$EXPANSION_PATH", &mut state),
+            ["/bin", "/usr/bin x"]
+        );
+    }
+
+    #[test]
+    fn positional_parameters() {
+        let mut state = state();
+        state.positional_parameters = vec!["a b".to_owned(), "c".to_owned()];
+
+        assert_eq!(expand("\"$@\"", &mut state), ["a b", "c"]);
+        assert_eq!(expand("\"x$@y\"", &mut state), ["xa b", "cy"]);
+        assert_eq!(expand("\"$*\"", &mut state), ["a b c"]);
+        assert_eq!(expand("$@", &mut state), ["a", "b", "c"]);
+        assert_eq!(expand("$*", &mut state), ["a", "b", "c"]);
+        assert_eq!(expand("$#", &mut state), ["2"]);
+
+        state.positional_parameters.clear();
+        assert!(expand("\"$@\"", &mut state).is_empty());
+        assert_eq!(expand("\"$*\"", &mut state), [""]);
+    }
+
+    #[test]
+    fn parameter_operators() {
+        let mut state = state();
+        state.set_variable("EXPANSION_SET", "value".to_owned());
+        state.set_variable("EXPANSION_EMPTY", String::new());
+
+        for (name, default, alternative) in [
+            ("EXPANSION_SET", "value", "alternative"),
+            ("EXPANSION_EMPTY", "default", ""),
+            ("EXPANSION_UNSET", "default", ""),
+        ] {
+            assert_eq!(
+                expand(&format!("\"${{{name}:-default}}\""), &mut state),
+                [default]
+            );
+            assert_eq!(
+                expand(&format!("\"${{{name}:+alternative}}\""), &mut state),
+                [alternative]
+            );
+        }
+        assert_eq!(state.get_variable("EXPANSION_UNSET"), None);
+
+        assert_eq!(expand("${EXPANSION_SET:=x}", &mut state), ["value"]);
+        assert_eq!(expand("${EXPANSION_EMPTY:=x}", &mut state), ["x"]);
+        assert_eq!(expand("${EXPANSION_UNSET:='y z'}", &mut state), ["y", "z"]);
+        assert_eq!(state.get_variable("EXPANSION_SET").unwrap(), "value");
+        assert_eq!(state.get_variable("EXPANSION_EMPTY").unwrap(), "x");
+        assert_eq!(state.get_variable("EXPANSION_UNSET").unwrap(), "y z");
+    }
+
+    #[test]
+    fn command_substitution_and_arithmetic() {
+        let mut state = state();
+        state.set_variable("EXPANSION_NUMBER", "4".to_owned());
+
+        assert_eq!(expand("$(printf 'a  b')", &mut state), ["a", "b"]);
+        assert_eq!(expand("\"$(printf 'a  b')\"", &mut state), ["a  b"]);
+        assert_eq!(expand("`echo a`", &mut state), ["a"]);
+        assert_eq!(expand("x$(printf 'y\\n\\n')z", &mut state), ["xyz"]);
+        assert_eq!(expand("$(echo $(echo nested))", &mut state), ["nested"]);
+        assert_eq!(expand("$((1 + 2 * 3))", &mut state), ["7"]);
+        assert_eq!(
+            expand("$((EXPANSION_NUMBER * $EXPANSION_NUMBER))", &mut state),
+            ["16"]
+        );
+        assert_eq!(expand("'$(echo a)'", &mut state), ["$(echo a)"]);
+    }
+
+    #[test]
+    fn prefix_assignments_do_not_persist() {
+        let mut state = state();
+        run(
+            "EXPANSION_PREFIX=before
+            EXPANSION_PREFIX=during EXPANSION_FRESH=new true
+            EXPANSION_SEEN=\"$(EXPANSION_PREFIX=child printenv EXPANSION_PREFIX)\"",
+            &mut state,
+        );
+
+        assert_eq!(state.get_variable("EXPANSION_PREFIX").unwrap(), "before");
+        assert_eq!(state.get_variable("EXPANSION_FRESH"), None);
+        assert_eq!(state.get_variable("EXPANSION_SEEN").unwrap(), "child");
+        assert!(
+            !state
+                .environment()
+                .iter()
+                .any(|(name, _)| name == "EXPANSION_PREFIX")
+        );
+    }
+
+    #[test]
+    fn exported_variables_reach_child_processes() {
+        let mut state = state();
+        run(
+            "EXPANSION_LOCAL=local
+            export EXPANSION_EXPORTED=exported
+            EXPANSION_CHILD=\"$(printenv EXPANSION_LOCAL EXPANSION_EXPORTED)\"
+            unset EXPANSION_EXPORTED
+            EXPANSION_AFTER_UNSET=\"$(printenv EXPANSION_EXPORTED)\"
+            EXPANSION_ENV=\"$(env EXPANSION_EXTRA=extra printenv EXPANSION_EXTRA)\"",
+            &mut state,
+        );
+
+        assert_eq!(state.get_variable("EXPANSION_CHILD").unwrap(), "exported");
+        assert_eq!(state.get_variable("EXPANSION_AFTER_UNSET").unwrap(), "");
+        assert_eq!(state.get_variable("EXPANSION_ENV").unwrap(), "extra");
+        assert_eq!(state.get_variable("EXPANSION_EXTRA"), None);
+    }
+}
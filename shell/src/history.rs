@@ -0,0 +1,274 @@
+use std::{
+    fs::{self, File, read_to_string},
+    io::Write,
+    path::PathBuf,
+};
+
+use crate::errors::CustomError;
+
+/// How many entries are kept when `HISTSIZE` isn't set to a number.
+pub const DEFAULT_SIZE: usize = 1000;
+
+/// Previously entered lines, kept in memory and appended to a history file
+/// as they are added so they survive restarts. At most `size` lines are
+/// kept, dropping the oldest.
+#[derive(Debug)]
+pub struct History {
+    entries: Vec<String>,
+    file: Option<PathBuf>,
+    size: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            entries: vec![],
+            file: None,
+            size: DEFAULT_SIZE,
+        }
+    }
+}
+
+impl History {
+    /// Reads the history file, rewriting it with only the newest `size`
+    /// lines if it has grown longer than that.
+    pub fn load(file: Option<PathBuf>, size: usize) -> Self {
+        let entries: Vec<String> = file
+            .as_ref()
+            .and_then(|file| read_to_string(file).ok())
+            .unwrap_or_default()
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_owned)
+            .collect();
+        let loaded = entries.len();
+
+        let mut history = Self {
+            entries,
+            file,
+            size,
+        };
+        history.set_size(size);
+        if history.entries.len() < loaded {
+            history.save();
+        }
+
+        history
+    }
+
+    /// Changes how many entries are kept, dropping the oldest ones that no
+    /// longer fit.
+    pub fn set_size(&mut self, size: usize) {
+        self.size = size;
+        let overflow = self.entries.len().saturating_sub(size);
+        self.entries.drain(..overflow);
+    }
+
+    /// Writes every entry to the history file, replacing what it held.
+    fn save(&self) {
+        if let Some(file) = &self.file {
+            let contents: String = self
+                .entries
+                .iter()
+                .map(|entry| format!("{entry}\n"))
+                .collect();
+            let _ = fs::write(file, contents);
+        }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Records a line unless it is blank or repeats the previous entry.
+    pub fn add(&mut self, line: &str) {
+        if line.trim().is_empty() || self.entries.last().is_some_and(|last| last == line) {
+            return;
+        }
+
+        self.entries.push(line.to_owned());
+        if self.entries.len() > self.size {
+            self.entries.remove(0);
+        }
+
+        if let Some(file) = &self.file
+            && let Ok(mut file) = File::options().create(true).append(true).open(file)
+        {
+            let _ = writeln!(file, "{line}");
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    /// Replaces history references in a line typed at the prompt: `!!` is
+    /// the previous line, `!n` line `n`, `!-n` the line `n` back and
+    /// `!prefix` the most recent line starting with `prefix`. Nothing is
+    /// replaced inside single quotes or after a backslash, nor a `!` followed
+    /// by a blank, `=` or `(`.
+    pub fn expand(&self, line: &str) -> Result<String, CustomError> {
+        let mut expanded = String::new();
+        let mut characters = line.chars().peekable();
+        let mut in_single_quotes = false;
+        let mut in_double_quotes = false;
+
+        while let Some(character) = characters.next() {
+            match character {
+                '\'' if !in_double_quotes => in_single_quotes = !in_single_quotes,
+                '"' if !in_single_quotes => in_double_quotes = !in_double_quotes,
+                '\\' if !in_single_quotes => {
+                    expanded.push(character);
+                    expanded.extend(characters.next());
+                    continue;
+                }
+                '!' if !in_single_quotes => {
+                    let is_reference = characters.peek().is_some_and(|next| {
+                        !next.is_whitespace() && !matches!(next, '=' | '(' | '"')
+                    });
+                    if is_reference {
+                        let mut event = String::new();
+                        if characters.next_if_eq(&'!').is_some() {
+                            event.push('!');
+                        } else {
+                            while let Some(next) = characters.next_if(|next| {
+                                !next.is_whitespace() && !";&|<>()`'\"".contains(*next)
+                            }) {
+                                event.push(next);
+                            }
+                        }
+
+                        let entry = self
+                            .find_event(&event)
+                            .ok_or_else(|| CustomError::EventNotFound(format!("!{event}")))?;
+                        expanded.push_str(entry);
+                        continue;
+                    }
+                }
+                _ => (),
+            }
+
+            expanded.push(character);
+        }
+
+        Ok(expanded)
+    }
+
+    /// The entry an event like `!`, `3`, `-2` or `prefix` refers to.
+    fn find_event(&self, event: &str) -> Option<&String> {
+        if event == "!" {
+            return self.entries.last();
+        }
+
+        match event.parse::<isize>() {
+            Ok(number) if number > 0 => self.entries.get(number as usize - 1),
+            Ok(number) if number < 0 => self
+                .entries
+                .len()
+                .checked_sub(number.unsigned_abs())
+                .and_then(|index| self.entries.get(index)),
+            Ok(_) => None,
+            Err(_) => self
+                .entries
+                .iter()
+                .rev()
+                .find(|entry| entry.starts_with(event)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(entries: &[&str]) -> History {
+        let mut history = History::default();
+        for entry in entries {
+            history.add(entry);
+        }
+        history
+    }
+
+    #[test]
+    fn load_and_save() {
+        let file = std::env::temp_dir().join(format!("shell-history-{}", std::process::id()));
+        fs::write(&file, "one\n\ntwo\nthree\nfour\n").unwrap();
+
+        // only the newest lines are kept, and the file shrinks to match
+        let mut history = History::load(Some(file.clone()), 3);
+        assert_eq!(history.entries(), ["two", "three", "four"]);
+        assert_eq!(read_to_string(&file).unwrap(), "two\nthree\nfour\n");
+
+        history.add("five");
+        history.add("five");
+        history.add("  ");
+        assert_eq!(history.entries(), ["three", "four", "five"]);
+
+        let history = History::load(Some(file.clone()), DEFAULT_SIZE);
+        assert_eq!(history.entries(), ["two", "three", "four", "five"]);
+
+        fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn size_changes_drop_the_oldest_entries() {
+        let mut history = history(&["a", "b", "c", "d"]);
+
+        history.set_size(2);
+        assert_eq!(history.entries(), ["c", "d"]);
+        history.add("e");
+        assert_eq!(history.entries(), ["d", "e"]);
+
+        history.set_size(0);
+        history.add("f");
+        assert!(history.entries().is_empty());
+    }
+
+    #[test]
+    fn expansion() {
+        let history = history(&["ls -l", "echo one", "make all", "echo two"]);
+        let expand = |line: &str| history.expand(line).unwrap();
+
+        assert_eq!(expand("!!"), "echo two");
+        assert_eq!(expand("sudo !! | less"), "sudo echo two | less");
+        assert_eq!(expand("!1"), "ls -l");
+        assert_eq!(expand("!-2"), "make all");
+        assert_eq!(expand("!ec"), "echo two");
+        assert_eq!(expand("!ls; !m"), "ls -l; make all");
+        assert_eq!(expand("\"!!\""), "\"echo two\"");
+    }
+
+    #[test]
+    fn lines_without_references() {
+        let history = history(&["echo one"]);
+
+        for line in [
+            "echo hi",
+            "echo '!!'",
+            "echo \\!!",
+            "[ ! -f x ]",
+            "a != b",
+            "echo hi!",
+        ] {
+            assert_eq!(history.expand(line).unwrap(), line);
+        }
+    }
+
+    #[test]
+    fn missing_events() {
+        let history = history(&["echo one"]);
+
+        for (line, event) in [
+            ("!5", "!5"),
+            ("!-2", "!-2"),
+            ("!0", "!0"),
+            ("x !nope y", "!nope"),
+        ] {
+            assert!(
+                matches!(history.expand(line), Err(CustomError::EventNotFound(found)) if found == event),
+                "{line}"
+            );
+        }
+        assert!(History::default().expand("!!").is_err());
+    }
+}
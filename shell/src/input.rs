@@ -0,0 +1,162 @@
+use anyhow::{Context, Result};
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor},
+    path::Path,
+};
+
+use crate::{
+    aliases::expand_aliases, command_list::CommandList, completion::complete, errors::CustomError,
+    input_parser::parse_input, line_editor::read_line, parser::parse, prompt::prompt,
+    shell_state::ShellState, traps::run_pending_traps,
+};
+
+/// Where the shell reads its commands from.
+pub enum Input {
+    /// The terminal, read through the line editor with a prompt.
+    Terminal,
+    /// A script file, a `-c` string or piped standard input, read without
+    /// prompting.
+    Lines(Box<dyn BufRead>),
+}
+
+enum Parsed {
+    Commands(CommandList),
+    SyntaxError(CustomError),
+    EndOfInput,
+}
+
+impl Input {
+    /// Standard input, using the line editor when it is a terminal.
+    pub fn standard_input(interactive: bool) -> Self {
+        if interactive {
+            Self::Terminal
+        } else {
+            Self::Lines(Box::new(BufReader::new(io::stdin())))
+        }
+    }
+
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+
+        Ok(Self::Lines(Box::new(BufReader::new(file))))
+    }
+
+    pub fn from_text(text: String) -> Self {
+        Self::Lines(Box::new(Cursor::new(text)))
+    }
+
+    /// Reads and runs commands until the input is exhausted or something
+    /// stops the shell. A syntax error ends a script with status 2, while an
+    /// interactive shell reports it and carries on, as it does when Ctrl-C
+    /// interrupts a command.
+    pub fn run(&mut self, state: &mut ShellState) -> Result<()> {
+        while !state.should_stop() {
+            state.jobs.reap();
+            state.jobs.notify(&mut io::stderr())?;
+            run_pending_traps(state)?;
+
+            let parsed = self.read_command_list(state).context("getting command")?;
+
+            match parsed {
+                Parsed::Commands(command_list) => {
+                    command_list.run(state)?;
+                    if matches!(self, Self::Terminal) && state.interrupted {
+                        state.interrupted = false;
+                        state.last_exit_status = 128 + libc::SIGINT;
+                    }
+                }
+                Parsed::SyntaxError(error) => {
+                    error.report();
+                    state.last_exit_status = error.exit_status();
+                    // a bad substitution only fails its own line, as when
+                    // bash finds it while expanding
+                    if !matches!(self, Self::Terminal)
+                        && !matches!(error, CustomError::BadSubstitution(_))
+                    {
+                        break;
+                    }
+                }
+                Parsed::EndOfInput => break,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads the next line, without its line ending, prompting with the
+    /// variable `prompt_name` on a terminal. Returns `None` once input is
+    /// exhausted.
+    fn read_line(&mut self, state: &mut ShellState, prompt_name: &str) -> Result<Option<String>> {
+        let reader = match self {
+            Self::Lines(reader) => reader,
+            Self::Terminal => {
+                let path = state.path().to_vec();
+                let completer = move |word: &str, command_position: bool| {
+                    complete(word, command_position, &path)
+                };
+                let prompt = prompt(prompt_name, state);
+                let Some(user_input) = read_line(&prompt, state.history.entries(), &completer)?
+                else {
+                    return Ok(None);
+                };
+
+                // a line with a history reference is shown as it will run,
+                // while one referring to nothing isn't run at all
+                let user_input = match state.history.expand(&user_input) {
+                    Ok(expanded) if expanded != user_input => {
+                        println!("{expanded}");
+                        expanded
+                    }
+                    Ok(_) => user_input,
+                    Err(error) => {
+                        error.report();
+                        state.history.add(&user_input);
+                        return Ok(Some(String::new()));
+                    }
+                };
+                state.history.add(&user_input);
+
+                return Ok(Some(user_input));
+            }
+        };
+
+        let mut user_input = String::new();
+        let bytes_read = reader
+            .read_line(&mut user_input)
+            .context("reading user input")?;
+
+        if bytes_read == 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(user_input.trim_end_matches(['\n', '\r']).to_owned()))
+    }
+
+    /// Reads and parses the next command, reading more lines (prompting with
+    /// `PS2` on a terminal) while it is incomplete. Aliases are replaced
+    /// before the command is parsed.
+    fn read_command_list(&mut self, state: &mut ShellState) -> Result<Parsed> {
+        let Some(mut user_input) = self.read_line(state, "PS1")? else {
+            return Ok(Parsed::EndOfInput);
+        };
+
+        loop {
+            let tokens = parse_input(user_input.clone())
+                .map(|tokens| expand_aliases(tokens, &state.aliases));
+
+            match tokens.and_then(parse) {
+                Ok(command_list) => return Ok(Parsed::Commands(command_list)),
+                Err(CustomError::UnexpectedEndOfInput) => {
+                    let Some(more_input) = self.read_line(state, "PS2")? else {
+                        return Ok(Parsed::SyntaxError(CustomError::UnexpectedEndOfInput));
+                    };
+
+                    user_input.push('\n');
+                    user_input.push_str(&more_input);
+                }
+                Err(error) => return Ok(Parsed::SyntaxError(error)),
+            }
+        }
+    }
+}
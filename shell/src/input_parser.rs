@@ -1,3 +1,253 @@
+use std::{fmt, iter::Peekable, os::fd::RawFd, str::Chars};
+
+use crate::errors::CustomError;
+
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Word(Word),
+    /// A redirection operator with the file descriptor written before it,
+    /// if any. The word after it is the target.
+    Redirection(Option<RawFd>, RedirectionOperator),
+    Pipe,
+    Background,
+    And,
+    Or,
+    Semicolon,
+    DoubleSemicolon,
+    Newline,
+    LeftParenthesis,
+    RightParenthesis,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedirectionOperator {
+    /// `<`
+    Input,
+    /// `>`
+    Output,
+    /// `>>`
+    Append,
+    /// `<&`
+    DuplicateInput,
+    /// `>&`
+    DuplicateOutput,
+    /// `&>`
+    OutputAndError,
+    /// `&>>`
+    AppendOutputAndError,
+    /// `<<` or `<<-`. Once the rest of the line has been read the word after
+    /// the operator is replaced by the body of the here-document.
+    HereDocument { strip_tabs: bool, delimiter: String },
+    /// `<<<`
+    HereString,
+}
+
+impl fmt::Display for RedirectionOperator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RedirectionOperator::Input => write!(f, "<"),
+            RedirectionOperator::Output => write!(f, ">"),
+            RedirectionOperator::Append => write!(f, ">>"),
+            RedirectionOperator::DuplicateInput => write!(f, "<&"),
+            RedirectionOperator::DuplicateOutput => write!(f, ">&"),
+            RedirectionOperator::OutputAndError => write!(f, "&>"),
+            RedirectionOperator::AppendOutputAndError => write!(f, "&>>"),
+            RedirectionOperator::HereDocument { strip_tabs, .. } => {
+                write!(f, "{}", if *strip_tabs { "<<-" } else { "<<" })
+            }
+            RedirectionOperator::HereString => write!(f, "<<<"),
+        }
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Word(word) => write!(f, "{word}"),
+            Token::Redirection(Some(fd), operator) => write!(f, "{fd}{operator}"),
+            Token::Redirection(None, operator) => write!(f, "{operator}"),
+            Token::Pipe => write!(f, "|"),
+            Token::Background => write!(f, "&"),
+            Token::And => write!(f, "&&"),
+            Token::Or => write!(f, "||"),
+            Token::Semicolon => write!(f, ";"),
+            Token::DoubleSemicolon => write!(f, ";;"),
+            Token::Newline => write!(f, "newline"),
+            Token::LeftParenthesis => write!(f, "("),
+            Token::RightParenthesis => write!(f, ")"),
+        }
+    }
+}
+
+/// A shell word as typed, with quoting preserved so expansions can be
+/// performed when the command runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Word {
+    pub parts: Vec<WordPart>,
+    /// Set once a quote has been seen, so `""` still produces an argument.
+    pub quoted: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordPart {
+    /// Unquoted text.
+    Literal(String),
+    /// Text that came from quotes or escapes and is never expanded further.
+    Quoted(String),
+    /// `$NAME`, `${NAME}` or `${NAME:-word}` and the other operators,
+    /// including special parameters like `$?` and positional parameters like
+    /// `$1`.
+    Parameter {
+        name: String,
+        operation: Option<(ParameterOperator, Word)>,
+        quoted: bool,
+    },
+    /// `$(command)` or `` `command` ``, replaced by the command's output.
+    CommandSubstitution { command: String, quoted: bool },
+    /// `$((expression))`, replaced by the value of the integer expression.
+    Arithmetic { expression: String, quoted: bool },
+}
+
+/// The operator of `${NAME:-word}`, `${NAME:=word}` or `${NAME:+word}`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterOperator {
+    /// `${NAME:-word}`: use `word` instead.
+    Default,
+    /// `${NAME:=word}`: assign `word` to `NAME` and use it.
+    Assign,
+    /// `${NAME:+word}`: use `word` only when `NAME` is set and non-empty.
+    Alternative,
+}
+
+impl ParameterOperator {
+    fn from_symbol(symbol: char) -> Option<Self> {
+        match symbol {
+            '-' => Some(Self::Default),
+            '=' => Some(Self::Assign),
+            '+' => Some(Self::Alternative),
+            _ => None,
+        }
+    }
+
+    fn symbol(self) -> char {
+        match self {
+            Self::Default => '-',
+            Self::Assign => '=',
+            Self::Alternative => '+',
+        }
+    }
+}
+
+impl Word {
+    /// The word's text when it is made up only of unquoted literal
+    /// characters, used to recognise operators and assignments.
+    pub fn as_literal(&self) -> Option<&str> {
+        match self.parts.as_slice() {
+            [WordPart::Literal(text)] if !self.quoted => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Splits a `NAME=value` word into the name and the value word.
+    pub fn as_assignment(&self) -> Option<(String, Word)> {
+        let Some(WordPart::Literal(first)) = self.parts.first() else {
+            return None;
+        };
+        let (name, value) = first.split_once('=')?;
+
+        if !is_valid_name(name) {
+            return None;
+        }
+
+        let mut parts = vec![];
+        if !value.is_empty() {
+            parts.push(WordPart::Literal(value.to_owned()));
+        }
+        parts.extend(self.parts[1..].iter().cloned());
+
+        Some((
+            name.to_owned(),
+            Word {
+                parts,
+                quoted: self.quoted,
+            },
+        ))
+    }
+
+    pub fn has_command_substitution(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, WordPart::CommandSubstitution { .. }))
+    }
+
+    fn is_empty(&self) -> bool {
+        self.parts.is_empty() && !self.quoted
+    }
+
+    fn push_char(&mut self, character: char, quoted: bool) {
+        match (self.parts.last_mut(), quoted) {
+            (Some(WordPart::Literal(text)), false) | (Some(WordPart::Quoted(text)), true) => {
+                text.push(character)
+            }
+            (_, false) => self.parts.push(WordPart::Literal(character.to_string())),
+            (_, true) => self.parts.push(WordPart::Quoted(character.to_string())),
+        }
+    }
+
+    fn push_str(&mut self, text: &str, quoted: bool) {
+        text.chars()
+            .for_each(|character| self.push_char(character, quoted));
+    }
+}
+
+/// Writes the word back out in a form that parses to the same word, used to
+/// show commands in `jobs`.
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.parts.is_empty() && self.quoted {
+            return write!(f, "''");
+        }
+
+        for part in &self.parts {
+            match part {
+                WordPart::Literal(text) => write!(f, "{text}")?,
+                WordPart::Quoted(text) => write!(f, "'{}'", text.replace('\'', r"'\''"))?,
+                WordPart::Parameter {
+                    name,
+                    operation,
+                    quoted,
+                } => {
+                    let quote = if *quoted { "\"" } else { "" };
+                    match operation {
+                        Some((operator, word)) => {
+                            let symbol = operator.symbol();
+                            write!(f, "{quote}${{{name}:{symbol}{word}}}{quote}")?
+                        }
+                        None => write!(f, "{quote}${{{name}}}{quote}")?,
+                    }
+                }
+                WordPart::CommandSubstitution { command, quoted } => {
+                    let quote = if *quoted { "\"" } else { "" };
+                    write!(f, "{quote}$({command}){quote}")?
+                }
+                WordPart::Arithmetic { expression, quoted } => {
+                    let quote = if *quoted { "\"" } else { "" };
+                    write!(f, "{quote}$(({expression})){quote}")?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut characters = name.chars();
+
+    matches!(characters.next(), Some(first) if first.is_ascii_alphabetic() || first == '_')
+        && characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+}
+
 enum ProcessInputState {
     Escaping,
     InsideSingleQuotes,
@@ -11,100 +261,972 @@ impl ProcessInputState {
         matches!(self, Self::InsideSingleQuotes) || matches!(self, Self::InsideDoubleQuotes)
     }
 
-    pub fn to_normal(&mut self) {
+    pub fn enter_normal(&mut self) {
         *self = Self::Normal;
     }
 
-    pub fn to_double_quoting(&mut self) {
+    pub fn enter_double_quotes(&mut self) {
         *self = Self::InsideDoubleQuotes;
     }
 
-    pub fn to_double_quote_escaping(&mut self) {
+    pub fn enter_double_quote_escaping(&mut self) {
         *self = Self::InsideDoubleQuotesEscaping;
     }
 
-    pub fn to_single_quoting(&mut self) {
+    pub fn enter_single_quotes(&mut self) {
         *self = Self::InsideSingleQuotes;
     }
 
-    pub fn to_escaping(&mut self) {
+    pub fn enter_escaping(&mut self) {
         *self = Self::Escaping;
     }
 }
 
-pub fn parse_input(input: String) -> Vec<String> {
+/// Splits input into words and operators. Fails with
+/// `CustomError::UnexpectedEndOfInput` when a quote is left open, the input
+/// ends in a backslash or a here-document hasn't reached its delimiter, so
+/// the caller can ask for another line.
+pub fn parse_input(input: String) -> Result<Vec<Token>, CustomError> {
+    let mut characters = input.trim().chars().peekable();
+
+    tokenize(&mut characters, ProcessInputState::Normal, true)
+}
+
+/// Runs the quoting state machine over `characters`. When `split_words` is
+/// false whitespace and operators are kept as part of a single word, which
+/// is how the word of `${NAME:-word}` is read.
+fn tokenize(
+    characters: &mut Peekable<Chars>,
+    mut state: ProcessInputState,
+    split_words: bool,
+) -> Result<Vec<Token>, CustomError> {
     let mut result = vec![];
-    let mut current_argument = String::new();
-    let mut state = ProcessInputState::Normal;
+    let mut current_word = Word::default();
+    // indexes of here-document operators whose bodies start on the next line
+    let mut pending_here_documents = vec![];
 
-    for argument_char in input.trim().chars() {
+    while let Some(argument_char) = characters.next() {
         if matches!(state, ProcessInputState::Escaping) {
-            current_argument.push(argument_char);
-            state.to_normal();
+            // a backslash before a newline continues the line
+            if argument_char != '\n' {
+                current_word.push_char(argument_char, true);
+            }
+            state.enter_normal();
             continue;
         }
 
         match argument_char {
             '\'' => match state {
-                ProcessInputState::InsideSingleQuotes => state.to_normal(),
-                ProcessInputState::InsideDoubleQuotes => current_argument.push(argument_char),
+                ProcessInputState::InsideSingleQuotes => state.enter_normal(),
+                ProcessInputState::InsideDoubleQuotes => {
+                    current_word.push_char(argument_char, true)
+                }
                 ProcessInputState::InsideDoubleQuotesEscaping => {
-                    current_argument.push('\\');
-                    current_argument.push(argument_char);
-                    state.to_double_quoting();
+                    current_word.push_str("\\'", true);
+                    state.enter_double_quotes();
+                }
+                ProcessInputState::Normal => {
+                    current_word.quoted = true;
+                    state.enter_single_quotes();
                 }
-                ProcessInputState::Normal => state.to_single_quoting(),
                 _ => (),
             },
             '"' => match state {
-                ProcessInputState::InsideSingleQuotes => current_argument.push(argument_char),
-                ProcessInputState::InsideDoubleQuotes => state.to_normal(),
+                ProcessInputState::InsideSingleQuotes => {
+                    current_word.push_char(argument_char, true)
+                }
+                ProcessInputState::InsideDoubleQuotes => state.enter_normal(),
                 ProcessInputState::InsideDoubleQuotesEscaping => {
-                    current_argument.push(argument_char);
-                    state.to_double_quoting();
+                    current_word.push_char(argument_char, true);
+                    state.enter_double_quotes();
+                }
+                ProcessInputState::Normal => {
+                    current_word.quoted = true;
+                    state.enter_double_quotes();
                 }
-                ProcessInputState::Normal => state.to_double_quoting(),
                 _ => (),
             },
             '~' => {
                 if matches!(state, ProcessInputState::InsideSingleQuotes) {
-                    current_argument.push(argument_char);
+                    current_word.push_char(argument_char, true);
                 } else {
                     let home_directory = std::env::home_dir().unwrap_or_default();
-                    current_argument.push_str(home_directory.to_str().unwrap_or_default());
+                    current_word.push_str(home_directory.to_str().unwrap_or_default(), true);
                 }
             }
-            ' ' => {
+            '$' if matches!(
+                state,
+                ProcessInputState::Normal | ProcessInputState::InsideDoubleQuotes
+            ) =>
+            {
+                let quoted = matches!(state, ProcessInputState::InsideDoubleQuotes);
+
+                match read_parameter(characters, quoted)? {
+                    Some(parameter) => current_word.parts.push(parameter),
+                    None => current_word.push_char(argument_char, quoted),
+                }
+            }
+            '`' if matches!(
+                state,
+                ProcessInputState::Normal | ProcessInputState::InsideDoubleQuotes
+            ) =>
+            {
+                let quoted = matches!(state, ProcessInputState::InsideDoubleQuotes);
+                let command = read_backquoted(characters, quoted)?;
+                current_word
+                    .parts
+                    .push(WordPart::CommandSubstitution { command, quoted });
+            }
+            '#' if split_words
+                && matches!(state, ProcessInputState::Normal)
+                && current_word.is_empty() =>
+            {
+                // comments run to the end of the line
+                while characters.next_if(|character| *character != '\n').is_some() {}
+            }
+            ' ' | '\t' if split_words => {
                 if state.inside_quotes() {
-                    current_argument.push(argument_char);
-                } else if !current_argument.is_empty() {
-                    result.push(current_argument.clone());
-                    current_argument.clear();
+                    current_word.push_char(argument_char, true);
+                } else if !current_word.is_empty() {
+                    result.push(Token::Word(std::mem::take(&mut current_word)));
+                }
+            }
+            '<' | '>' if split_words && matches!(state, ProcessInputState::Normal) => {
+                let fd = current_word
+                    .as_literal()
+                    .filter(|text| text.chars().all(|character| character.is_ascii_digit()))
+                    .and_then(|text| text.parse::<RawFd>().ok());
+
+                if fd.is_some() {
+                    current_word = Word::default();
+                } else if !current_word.is_empty() {
+                    result.push(Token::Word(std::mem::take(&mut current_word)));
+                }
+
+                let operator = read_redirection_operator(argument_char, characters);
+                if matches!(operator, RedirectionOperator::HereDocument { .. }) {
+                    pending_here_documents.push(result.len());
+                }
+                result.push(Token::Redirection(fd, operator));
+            }
+            '&' if split_words
+                && matches!(state, ProcessInputState::Normal)
+                && characters.next_if_eq(&'>').is_some() =>
+            {
+                if !current_word.is_empty() {
+                    result.push(Token::Word(std::mem::take(&mut current_word)));
+                }
+
+                let operator = if characters.next_if_eq(&'>').is_some() {
+                    RedirectionOperator::AppendOutputAndError
+                } else {
+                    RedirectionOperator::OutputAndError
+                };
+                result.push(Token::Redirection(None, operator));
+            }
+            '|' | '&' | ';' | '(' | ')' | '\n'
+                if split_words && matches!(state, ProcessInputState::Normal) =>
+            {
+                if !current_word.is_empty() {
+                    result.push(Token::Word(std::mem::take(&mut current_word)));
+                }
+
+                let token = match argument_char {
+                    '|' if characters.next_if_eq(&'|').is_some() => Token::Or,
+                    '|' => Token::Pipe,
+                    '&' if characters.next_if_eq(&'&').is_some() => Token::And,
+                    '&' => Token::Background,
+                    ';' if characters.next_if_eq(&';').is_some() => Token::DoubleSemicolon,
+                    ';' => Token::Semicolon,
+                    '(' => Token::LeftParenthesis,
+                    ')' => Token::RightParenthesis,
+                    _ => Token::Newline,
+                };
+                let is_newline = token == Token::Newline;
+                result.push(token);
+
+                if is_newline {
+                    for index in std::mem::take(&mut pending_here_documents) {
+                        read_here_document(&mut result, index, characters)?;
+                    }
                 }
             }
             '\\' => match state {
-                ProcessInputState::InsideSingleQuotes => current_argument.push(argument_char),
-                ProcessInputState::InsideDoubleQuotes => state.to_double_quote_escaping(),
+                ProcessInputState::InsideSingleQuotes => {
+                    current_word.push_char(argument_char, true)
+                }
+                ProcessInputState::InsideDoubleQuotes => state.enter_double_quote_escaping(),
                 ProcessInputState::InsideDoubleQuotesEscaping => {
-                    current_argument.push(argument_char);
-                    state.to_double_quoting();
+                    current_word.push_char(argument_char, true);
+                    state.enter_double_quotes();
                 }
-                ProcessInputState::Normal => state.to_escaping(),
+                ProcessInputState::Normal => state.enter_escaping(),
                 _ => (),
             },
             _ => {
                 if matches!(state, ProcessInputState::InsideDoubleQuotesEscaping) {
-                    state.to_double_quoting();
-                    current_argument.push('\\');
+                    state.enter_double_quotes();
+                    if argument_char != '$' && argument_char != '`' {
+                        current_word.push_char('\\', true);
+                    }
                 }
-                current_argument.push(argument_char);
+                current_word.push_char(argument_char, state.inside_quotes());
             }
         }
     }
 
-    if !current_argument.is_empty() {
-        result.push(current_argument);
+    if split_words
+        && (!matches!(state, ProcessInputState::Normal) || !pending_here_documents.is_empty())
+    {
+        return Err(CustomError::UnexpectedEndOfInput);
+    }
+
+    if !current_word.is_empty() {
+        result.push(Token::Word(current_word));
     }
 
-    result
+    Ok(result)
+}
+
+/// Reads the rest of a redirection operator starting with `<` or `>`.
+fn read_redirection_operator(first: char, characters: &mut Peekable<Chars>) -> RedirectionOperator {
+    if first == '>' {
+        return match characters.next_if(|character| ">&|".contains(*character)) {
+            Some('>') => RedirectionOperator::Append,
+            Some('&') => RedirectionOperator::DuplicateOutput,
+            _ => RedirectionOperator::Output,
+        };
+    }
+
+    match characters.next_if(|character| "<&".contains(*character)) {
+        Some('<') if characters.next_if_eq(&'<').is_some() => RedirectionOperator::HereString,
+        Some('<') => RedirectionOperator::HereDocument {
+            strip_tabs: characters.next_if_eq(&'-').is_some(),
+            delimiter: String::new(),
+        },
+        Some('&') => RedirectionOperator::DuplicateInput,
+        _ => RedirectionOperator::Input,
+    }
+}
+
+/// Reads the body of the here-document whose operator is at `index` from
+/// the lines following the command, replacing the delimiter word after the
+/// operator with the body. A quoted delimiter means the body is taken
+/// literally, otherwise parameters in it are expanded.
+fn read_here_document(
+    tokens: &mut [Token],
+    index: usize,
+    characters: &mut Peekable<Chars>,
+) -> Result<(), CustomError> {
+    let Some(Token::Word(delimiter_word)) = tokens.get(index + 1) else {
+        // the parser reports the missing delimiter
+        return Ok(());
+    };
+    // quoting any part of the delimiter, even with a backslash, keeps the
+    // body literal
+    let quoted = delimiter_word.quoted
+        || delimiter_word
+            .parts
+            .iter()
+            .any(|part| matches!(part, WordPart::Quoted(_)));
+    let delimiter: String = delimiter_word
+        .parts
+        .iter()
+        .map(|part| match part {
+            WordPart::Literal(text) | WordPart::Quoted(text) => text.clone(),
+            parameter => Word {
+                parts: vec![parameter.clone()],
+                quoted: false,
+            }
+            .to_string(),
+        })
+        .collect();
+
+    let Token::Redirection(
+        _,
+        RedirectionOperator::HereDocument {
+            strip_tabs,
+            delimiter: operator_delimiter,
+        },
+    ) = &mut tokens[index]
+    else {
+        return Ok(());
+    };
+    let strip_tabs = *strip_tabs;
+    operator_delimiter.clone_from(&delimiter);
+
+    let mut body = String::new();
+    loop {
+        if characters.peek().is_none() {
+            return Err(CustomError::UnexpectedEndOfInput);
+        }
+
+        let line: String =
+            std::iter::from_fn(|| characters.next_if(|character| *character != '\n')).collect();
+        characters.next();
+
+        let line = if strip_tabs {
+            line.trim_start_matches('\t')
+        } else {
+            &line
+        };
+        if line == delimiter {
+            break;
+        }
+
+        body.push_str(line);
+        body.push('\n');
+    }
+
+    tokens[index + 1] = Token::Word(if quoted {
+        Word {
+            parts: vec![WordPart::Quoted(body)],
+            quoted: true,
+        }
+    } else {
+        parse_here_document(&body)?
+    });
+
+    Ok(())
+}
+
+/// Reads an unquoted here-document body, where only `$`, backquotes and
+/// backslashes before `$`, `` ` ``, `\` or a newline are special. The text of
+/// an arithmetic expression is read the same way before it is evaluated.
+pub fn parse_here_document(text: &str) -> Result<Word, CustomError> {
+    let mut characters = text.chars().peekable();
+    let mut word = Word {
+        parts: vec![],
+        quoted: true,
+    };
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => match characters.next_if(|next| "$`\\\n".contains(*next)) {
+                Some('\n') => (),
+                Some(escaped) => word.push_char(escaped, true),
+                None => word.push_char(character, true),
+            },
+            '$' => match read_parameter(&mut characters, true)? {
+                Some(parameter) => word.parts.push(parameter),
+                None => word.push_char(character, true),
+            },
+            '`' => {
+                let command = read_backquoted(&mut characters, false)?;
+                word.parts.push(WordPart::CommandSubstitution {
+                    command,
+                    quoted: true,
+                });
+            }
+            character => word.push_char(character, true),
+        }
+    }
+
+    Ok(word)
+}
+
+/// Reads the parameter, command substitution or arithmetic expansion
+/// following a `$`, or returns `None` when the `$` should be taken literally.
+fn read_parameter(
+    characters: &mut Peekable<Chars>,
+    quoted: bool,
+) -> Result<Option<WordPart>, CustomError> {
+    let mut name = String::new();
+    let mut operation = None;
+
+    let Some(next) = characters.peek() else {
+        return Ok(None);
+    };
+
+    match next {
+        '(' => {
+            characters.next();
+
+            return Ok(Some(if characters.next_if_eq(&'(').is_some() {
+                WordPart::Arithmetic {
+                    expression: read_arithmetic(characters)?,
+                    quoted,
+                }
+            } else {
+                WordPart::CommandSubstitution {
+                    command: read_command_substitution(characters)?,
+                    quoted,
+                }
+            }));
+        }
+        '{' => {
+            characters.next();
+            let mut depth = 1;
+            let mut inner = String::new();
+
+            loop {
+                let character = characters.next().ok_or(CustomError::UnexpectedEndOfInput)?;
+                match character {
+                    '{' => depth += 1,
+                    '}' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            break;
+                        }
+                    }
+                    _ => (),
+                }
+                inner.push(character);
+            }
+
+            let bad_substitution = || CustomError::BadSubstitution(format!("${{{inner}}}"));
+            let (parameter_name, operation_text) = match inner.split_once(':') {
+                Some((parameter_name, rest)) => {
+                    let mut rest = rest.chars();
+                    let operator = rest
+                        .next()
+                        .and_then(ParameterOperator::from_symbol)
+                        .ok_or_else(bad_substitution)?;
+                    (parameter_name, Some((operator, rest.as_str())))
+                }
+                None => (inner.as_str(), None),
+            };
+            if !is_parameter_name(parameter_name) {
+                return Err(bad_substitution());
+            }
+
+            name.push_str(parameter_name);
+            operation = operation_text.map(|(operator, text)| (operator, parse_word(text, quoted)));
+        }
+        '?' | '$' | '!' | '#' | '@' | '*' | '0'..='9' => name.extend(characters.next()),
+        character if character.is_ascii_alphabetic() || *character == '_' => {
+            while let Some(character) = characters
+                .next_if(|character| character.is_ascii_alphanumeric() || *character == '_')
+            {
+                name.push(character);
+            }
+        }
+        _ => return Ok(None),
+    }
+
+    Ok(Some(WordPart::Parameter {
+        name,
+        operation,
+        quoted,
+    }))
+}
+
+/// Whether `${name}` names a parameter: a variable, a positional parameter
+/// or one of the special parameters.
+fn is_parameter_name(name: &str) -> bool {
+    let mut characters = name.chars();
+
+    match characters.next() {
+        Some('?' | '$' | '!' | '#' | '@' | '*') => characters.next().is_none(),
+        Some('0'..='9') => characters.all(|character| character.is_ascii_digit()),
+        Some(character) if character.is_ascii_alphabetic() || character == '_' => {
+            characters.all(|character| character.is_ascii_alphanumeric() || character == '_')
+        }
+        _ => false,
+    }
+}
+
+/// Reads the command of a `$(...)` up to its closing parenthesis, skipping
+/// over parentheses that are quoted or belong to nested commands.
+fn read_command_substitution(characters: &mut Peekable<Chars>) -> Result<String, CustomError> {
+    let mut command = String::new();
+    let mut depth = 1;
+
+    loop {
+        let character = characters.next().ok_or(CustomError::UnexpectedEndOfInput)?;
+
+        match character {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(command);
+                }
+            }
+            '\\' => {
+                command.push(character);
+                command.extend(characters.next());
+                continue;
+            }
+            '\'' | '"' => {
+                command.push(character);
+                let mut escaping = false;
+
+                loop {
+                    let quoted_character =
+                        characters.next().ok_or(CustomError::UnexpectedEndOfInput)?;
+                    command.push(quoted_character);
+
+                    if quoted_character == character && !escaping {
+                        break;
+                    }
+                    escaping = character == '"' && quoted_character == '\\' && !escaping;
+                }
+                continue;
+            }
+            _ => (),
+        }
+
+        command.push(character);
+    }
+}
+
+/// Reads the expression of a `$((...))` up to the closing `))`.
+fn read_arithmetic(characters: &mut Peekable<Chars>) -> Result<String, CustomError> {
+    let mut expression = String::new();
+    let mut depth = 0;
+
+    loop {
+        let character = characters.next().ok_or(CustomError::UnexpectedEndOfInput)?;
+
+        match character {
+            '(' => depth += 1,
+            ')' if depth == 0 && characters.next_if_eq(&')').is_some() => return Ok(expression),
+            ')' => depth -= 1,
+            _ => (),
+        }
+
+        expression.push(character);
+    }
+}
+
+/// Reads the command of a `` `...` `` up to the closing backquote. A
+/// backslash only escapes `$`, `` ` `` and `\`, and also `"` when the
+/// substitution is inside double quotes.
+fn read_backquoted(
+    characters: &mut Peekable<Chars>,
+    inside_double_quotes: bool,
+) -> Result<String, CustomError> {
+    let mut command = String::new();
+
+    loop {
+        match characters.next().ok_or(CustomError::UnexpectedEndOfInput)? {
+            '`' => return Ok(command),
+            '\\' => match characters
+                .next_if(|next| "$`\\".contains(*next) || (inside_double_quotes && *next == '"'))
+            {
+                Some(escaped) => command.push(escaped),
+                None => command.push('\\'),
+            },
+            character => command.push(character),
+        }
+    }
+}
+
+fn parse_word(text: &str, quoted: bool) -> Word {
+    let state = if quoted {
+        ProcessInputState::InsideDoubleQuotes
+    } else {
+        ProcessInputState::Normal
+    };
+
+    tokenize(&mut text.chars().peekable(), state, false)
+        .unwrap_or_default()
+        .into_iter()
+        .find_map(|token| match token {
+            Token::Word(word) => Some(word),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(input: &str) -> Vec<Token> {
+        parse_input(input.to_owned()).unwrap_or_else(|error| panic!("{input:?}: {error}"))
+    }
+
+    fn word(text: &str) -> Token {
+        Token::Word(Word {
+            parts: vec![WordPart::Literal(text.to_owned())],
+            quoted: false,
+        })
+    }
+
+    fn quoted_word(parts: Vec<WordPart>) -> Token {
+        Token::Word(Word {
+            parts,
+            quoted: true,
+        })
+    }
+
+    fn here_document(strip_tabs: bool, delimiter: &str) -> Token {
+        Token::Redirection(
+            None,
+            RedirectionOperator::HereDocument {
+                strip_tabs,
+                delimiter: delimiter.to_owned(),
+            },
+        )
+    }
+
+    fn parameter(
+        name: &str,
+        operation: Option<(ParameterOperator, Word)>,
+        quoted: bool,
+    ) -> WordPart {
+        WordPart::Parameter {
+            name: name.to_owned(),
+            operation,
+            quoted,
+        }
+    }
+
+    #[test]
+    fn pipes() {
+        assert_eq!(
+            tokens("ls -l|wc -l"),
+            [word("ls"), word("-l"), Token::Pipe, word("wc"), word("-l")]
+        );
+        assert_eq!(
+            tokens("echo a | tr a b | cat"),
+            [
+                word("echo"),
+                word("a"),
+                Token::Pipe,
+                word("tr"),
+                word("a"),
+                word("b"),
+                Token::Pipe,
+                word("cat"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_pipes_are_literal() {
+        let escaped = Word {
+            parts: vec![
+                WordPart::Literal("e".to_owned()),
+                WordPart::Quoted("|".to_owned()),
+                WordPart::Literal("f".to_owned()),
+            ],
+            quoted: false,
+        };
+
+        assert_eq!(
+            tokens(r#"echo 'a|b' "c|d" e\|f"#),
+            [
+                word("echo"),
+                quoted_word(vec![WordPart::Quoted("a|b".to_owned())]),
+                quoted_word(vec![WordPart::Quoted("c|d".to_owned())]),
+                Token::Word(escaped),
+            ]
+        );
+    }
+
+    #[test]
+    fn parameters() {
+        let unquoted = |parts| {
+            Token::Word(Word {
+                parts,
+                quoted: false,
+            })
+        };
+
+        assert_eq!(
+            tokens(r#"$? x${NAME}y "$HOME" '$HOME' \$NAME a$"#),
+            [
+                unquoted(vec![parameter("?", None, false)]),
+                unquoted(vec![
+                    WordPart::Literal("x".to_owned()),
+                    parameter("NAME", None, false),
+                    WordPart::Literal("y".to_owned()),
+                ]),
+                quoted_word(vec![parameter("HOME", None, true)]),
+                quoted_word(vec![WordPart::Quoted("$HOME".to_owned())]),
+                unquoted(vec![
+                    WordPart::Quoted("$".to_owned()),
+                    WordPart::Literal("NAME".to_owned()),
+                ]),
+                word("a$"),
+            ]
+        );
+    }
+
+    #[test]
+    fn parameter_operators() {
+        let literal = |text: &str| Word {
+            parts: vec![WordPart::Literal(text.to_owned())],
+            quoted: false,
+        };
+
+        for (input, operator) in [
+            ("${NAME:-a b}", ParameterOperator::Default),
+            ("${NAME:=a b}", ParameterOperator::Assign),
+            ("${NAME:+a b}", ParameterOperator::Alternative),
+        ] {
+            assert_eq!(
+                tokens(input),
+                [Token::Word(Word {
+                    parts: vec![parameter("NAME", Some((operator, literal("a b"))), false)],
+                    quoted: false,
+                })],
+                "{input}"
+            );
+        }
+    }
+
+    #[test]
+    fn control_operators() {
+        assert_eq!(
+            tokens("a&&b||c;d&\n(e)"),
+            [
+                word("a"),
+                Token::And,
+                word("b"),
+                Token::Or,
+                word("c"),
+                Token::Semicolon,
+                word("d"),
+                Token::Background,
+                Token::Newline,
+                Token::LeftParenthesis,
+                word("e"),
+                Token::RightParenthesis,
+            ]
+        );
+    }
+
+    #[test]
+    fn redirection_operators() {
+        let operators = [
+            ("<", RedirectionOperator::Input),
+            (">", RedirectionOperator::Output),
+            (">|", RedirectionOperator::Output),
+            (">>", RedirectionOperator::Append),
+            ("<&", RedirectionOperator::DuplicateInput),
+            (">&", RedirectionOperator::DuplicateOutput),
+            ("&>", RedirectionOperator::OutputAndError),
+            ("&>>", RedirectionOperator::AppendOutputAndError),
+            ("<<<", RedirectionOperator::HereString),
+        ];
+
+        for (text, operator) in operators {
+            assert_eq!(
+                tokens(&format!("a {text} b")),
+                [word("a"), Token::Redirection(None, operator), word("b")],
+                "{text}"
+            );
+        }
+    }
+
+    #[test]
+    fn redirections_without_spaces() {
+        assert_eq!(
+            tokens("echo hi>out"),
+            [
+                word("echo"),
+                word("hi"),
+                Token::Redirection(None, RedirectionOperator::Output),
+                word("out"),
+            ]
+        );
+        assert_eq!(
+            tokens("cat<in>>out"),
+            [
+                word("cat"),
+                Token::Redirection(None, RedirectionOperator::Input),
+                word("in"),
+                Token::Redirection(None, RedirectionOperator::Append),
+                word("out"),
+            ]
+        );
+    }
+
+    #[test]
+    fn redirection_file_descriptors() {
+        assert_eq!(
+            tokens("a 2>&1"),
+            [
+                word("a"),
+                Token::Redirection(Some(2), RedirectionOperator::DuplicateOutput),
+                word("1"),
+            ]
+        );
+        assert_eq!(
+            tokens("a 12<x 3>&-"),
+            [
+                word("a"),
+                Token::Redirection(Some(12), RedirectionOperator::Input),
+                word("x"),
+                Token::Redirection(Some(3), RedirectionOperator::DuplicateOutput),
+                word("-"),
+            ]
+        );
+
+        // only an unquoted number directly before the operator is a descriptor
+        assert_eq!(
+            tokens("a x2>y"),
+            [
+                word("a"),
+                word("x2"),
+                Token::Redirection(None, RedirectionOperator::Output),
+                word("y"),
+            ]
+        );
+        assert_eq!(
+            tokens("a '2'>y"),
+            [
+                word("a"),
+                quoted_word(vec![WordPart::Quoted("2".to_owned())]),
+                Token::Redirection(None, RedirectionOperator::Output),
+                word("y"),
+            ]
+        );
+        assert_eq!(
+            tokens("a 2 >y"),
+            [
+                word("a"),
+                word("2"),
+                Token::Redirection(None, RedirectionOperator::Output),
+                word("y"),
+            ]
+        );
+    }
+
+    #[test]
+    fn quoted_operators_are_words() {
+        assert_eq!(
+            tokens(r"echo '>' \< a\&b"),
+            [
+                word("echo"),
+                quoted_word(vec![WordPart::Quoted(">".to_owned())]),
+                Token::Word(Word {
+                    parts: vec![WordPart::Quoted("<".to_owned())],
+                    quoted: false,
+                }),
+                Token::Word(Word {
+                    parts: vec![
+                        WordPart::Literal("a".to_owned()),
+                        WordPart::Quoted("&".to_owned()),
+                        WordPart::Literal("b".to_owned()),
+                    ],
+                    quoted: false,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn here_documents() {
+        assert_eq!(
+            tokens("cat <<EOF\nhello\n  world\nEOF"),
+            [
+                word("cat"),
+                here_document(false, "EOF"),
+                quoted_word(vec![WordPart::Quoted("hello\n  world\n".to_owned())]),
+                Token::Newline,
+            ]
+        );
+        assert_eq!(
+            tokens("cat <<-END\n\tindented\n\tEND\necho after"),
+            [
+                word("cat"),
+                here_document(true, "END"),
+                quoted_word(vec![WordPart::Quoted("indented\n".to_owned())]),
+                Token::Newline,
+                word("echo"),
+                word("after"),
+            ]
+        );
+        assert_eq!(
+            tokens("cat <<EOF\nEOF"),
+            [
+                word("cat"),
+                here_document(false, "EOF"),
+                quoted_word(vec![]),
+                Token::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn here_document_expansion() {
+        assert_eq!(
+            tokens("cat <<EOF\n$x \\$y\nEOF")[2],
+            quoted_word(vec![
+                WordPart::Parameter {
+                    name: "x".to_owned(),
+                    operation: None,
+                    quoted: true,
+                },
+                WordPart::Quoted(" $y\n".to_owned()),
+            ])
+        );
+
+        // a quoted delimiter keeps the body literal
+        for input in [
+            "cat <<'EOF'\n$x\nEOF",
+            "cat <<\"EOF\"\n$x\nEOF",
+            "cat <<\\EOF\n$x\nEOF",
+        ] {
+            assert_eq!(
+                tokens(input),
+                [
+                    word("cat"),
+                    here_document(false, "EOF"),
+                    quoted_word(vec![WordPart::Quoted("$x\n".to_owned())]),
+                    Token::Newline,
+                ],
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn several_here_documents() {
+        assert_eq!(
+            tokens("a <<A; b <<B\none\nA\ntwo\nB"),
+            [
+                word("a"),
+                here_document(false, "A"),
+                quoted_word(vec![WordPart::Quoted("one\n".to_owned())]),
+                Token::Semicolon,
+                word("b"),
+                here_document(false, "B"),
+                quoted_word(vec![WordPart::Quoted("two\n".to_owned())]),
+                Token::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn unterminated_input() {
+        let inputs = [
+            "cat <<EOF",
+            "cat <<EOF\nno delimiter",
+            "cat <<EOF\n EOF",
+            "echo 'open",
+            "echo \"open",
+            "echo open\\",
+            "echo $(open",
+            "echo ${HOME",
+        ];
+
+        for input in inputs {
+            assert!(
+                matches!(
+                    parse_input(input.to_owned()),
+                    Err(CustomError::UnexpectedEndOfInput)
+                ),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn bad_substitutions() {
+        for input in ["echo ${}", "echo ${x%y}", "echo ${1a}", "echo \"${?x}\""] {
+            assert!(
+                matches!(
+                    parse_input(input.to_owned()),
+                    Err(CustomError::BadSubstitution(_))
+                ),
+                "{input}"
+            );
+        }
+    }
 }
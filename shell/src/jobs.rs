@@ -0,0 +1,433 @@
+use anyhow::Result;
+use std::{fmt, io::Write, sync::atomic::Ordering};
+
+use crate::process::{
+    CHILD_STATUS_CHANGED, ProcessStatus, catch_signal, give_terminal_to, ignore_signal,
+    install_child_handler, mark_signal_pending, reset_signal_dispositions, set_process_group,
+    signal_name, take_control_of_terminal, try_wait_any, wait_for,
+};
+
+#[derive(Debug)]
+pub struct Process {
+    pub pid: libc::pid_t,
+    pub status: ProcessStatus,
+}
+
+/// A pipeline started by the shell, tracked until all of its processes
+/// have exited.
+#[derive(Debug)]
+pub struct Job {
+    pub id: usize,
+    pub pgid: libc::pid_t,
+    pub processes: Vec<Process>,
+    pub text: String,
+}
+
+impl Job {
+    pub fn new(pgid: libc::pid_t, pids: Vec<libc::pid_t>, text: String) -> Self {
+        let processes = pids
+            .into_iter()
+            .map(|pid| Process {
+                pid,
+                status: ProcessStatus::Running,
+            })
+            .collect();
+
+        Self {
+            id: 0,
+            pgid,
+            processes,
+            text,
+        }
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.processes
+            .iter()
+            .any(|process| matches!(process.status, ProcessStatus::Stopped(_)))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.processes
+            .iter()
+            .all(|process| process.status.is_finished())
+    }
+
+    /// The status of the last process, which is the status of the job.
+    pub fn exit_status(&self) -> i32 {
+        self.processes
+            .last()
+            .map_or(0, |process| process.status.exit_status())
+    }
+
+    pub fn mark_running(&mut self) {
+        self.processes
+            .iter_mut()
+            .filter(|process| matches!(process.status, ProcessStatus::Stopped(_)))
+            .for_each(|process| process.status = ProcessStatus::Running);
+    }
+
+    pub fn state_name(&self) -> String {
+        if self.is_done() {
+            match self.processes.last().map(|process| process.status) {
+                Some(ProcessStatus::Signaled(libc::SIGTERM)) => "Terminated".to_owned(),
+                Some(ProcessStatus::Signaled(libc::SIGKILL)) => "Killed".to_owned(),
+                Some(ProcessStatus::Signaled(signal)) => {
+                    format!("SIG{}", signal_name(signal).unwrap_or("?"))
+                }
+                _ => match self.exit_status() {
+                    0 => "Done".to_owned(),
+                    code => format!("Exit {code}"),
+                },
+            }
+        } else if self.is_stopped() {
+            "Stopped".to_owned()
+        } else {
+            "Running".to_owned()
+        }
+    }
+
+    pub fn contains(&self, pid: libc::pid_t) -> bool {
+        self.processes.iter().any(|process| process.pid == pid)
+    }
+
+    fn update(&mut self, pid: libc::pid_t, status: ProcessStatus) {
+        self.processes
+            .iter_mut()
+            .filter(|process| process.pid == pid)
+            .for_each(|process| process.status = status);
+    }
+}
+
+/// The shell's jobs, most recently used last. When `job_control` is off
+/// (non-interactive shells and forked children) processes are still
+/// tracked, but never moved between process groups or given the terminal.
+#[derive(Debug, Default)]
+pub struct JobTable {
+    pub jobs: Vec<Job>,
+    pub job_control: bool,
+    shell_pgid: libc::pid_t,
+    stopped_notices: Vec<usize>,
+}
+
+impl JobTable {
+    pub fn new(interactive: bool) -> Self {
+        install_child_handler();
+
+        if !interactive {
+            return Self::default();
+        }
+
+        // Ctrl-C and Ctrl-\ are meant for the foreground job, not the shell
+        catch_signal(libc::SIGINT);
+        catch_signal(libc::SIGQUIT);
+
+        Self {
+            job_control: true,
+            shell_pgid: take_control_of_terminal(),
+            ..Self::default()
+        }
+    }
+
+    /// Called in a freshly forked child to join the job's process group.
+    /// The child gets an empty table so that anything it runs itself doesn't
+    /// try to manage the terminal. Without job control a background job
+    /// shares the terminal's process group, so it ignores Ctrl-C and Ctrl-\.
+    pub fn enter_job(&mut self, pgid: libc::pid_t, foreground: bool) {
+        if self.job_control {
+            set_process_group(0, pgid);
+            if foreground {
+                give_terminal_to(if pgid == 0 {
+                    unsafe { libc::getpid() }
+                } else {
+                    pgid
+                });
+            }
+        }
+
+        if !self.job_control && !foreground {
+            ignore_signal(libc::SIGINT);
+            ignore_signal(libc::SIGQUIT);
+        }
+        reset_signal_dispositions();
+        *self = Self::default();
+    }
+
+    /// Called in the shell after forking a job's process.
+    pub fn adopt(&self, pid: libc::pid_t, pgid: libc::pid_t) {
+        if self.job_control {
+            set_process_group(pid, pgid);
+        }
+    }
+
+    /// Adds a job, keeping its id if it already had one.
+    pub fn add(&mut self, mut job: Job) -> usize {
+        if job.id == 0 {
+            job.id = self.jobs.iter().map(|job| job.id).max().unwrap_or(0) + 1;
+        }
+
+        let id = job.id;
+        self.jobs.push(job);
+        id
+    }
+
+    /// Gives the job the terminal and waits until it exits or is stopped,
+    /// returning its exit status. Stopped jobs are added to the table.
+    pub fn wait_foreground(&mut self, mut job: Job) -> Result<i32> {
+        if self.job_control {
+            give_terminal_to(job.pgid);
+        }
+
+        let mut result = Ok(());
+        let unfinished = job
+            .processes
+            .iter_mut()
+            .filter(|process| !process.status.is_finished());
+
+        for process in unfinished {
+            match wait_for(process.pid, true) {
+                Ok(status) => {
+                    process.status = status;
+                    if matches!(status, ProcessStatus::Stopped(_)) {
+                        break;
+                    }
+                }
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+        }
+
+        if self.job_control {
+            give_terminal_to(self.shell_pgid);
+        }
+
+        result?;
+
+        let interrupted = job
+            .processes
+            .iter()
+            .any(|process| process.status == ProcessStatus::Signaled(libc::SIGINT));
+        if self.job_control && interrupted {
+            // the job took the Ctrl-C, but it is meant to interrupt whatever
+            // the shell is running as well
+            eprintln!();
+            mark_signal_pending(libc::SIGINT);
+        }
+
+        let status = job.exit_status();
+        if job.is_stopped() {
+            let id = self.add(job);
+            self.stopped_notices.push(id);
+        }
+
+        Ok(status)
+    }
+
+    /// Blocks until the job at `index` exits, removing it from the table.
+    pub fn wait_job(&mut self, index: usize) -> Result<i32> {
+        let mut job = self.jobs.remove(index);
+
+        for process in job.processes.iter_mut() {
+            if !process.status.is_finished() {
+                process.status = wait_for(process.pid, false)?;
+            }
+        }
+
+        Ok(job.exit_status())
+    }
+
+    /// Collects the status of any children that changed state since the
+    /// last call, without blocking.
+    pub fn reap(&mut self) {
+        if !CHILD_STATUS_CHANGED.swap(false, Ordering::SeqCst) {
+            return;
+        }
+
+        while let Some((pid, status)) = try_wait_any() {
+            let Some(job) = self.jobs.iter_mut().find(|job| job.contains(pid)) else {
+                continue;
+            };
+            let was_stopped = job.is_stopped();
+
+            job.update(pid, status);
+
+            if !was_stopped && job.is_stopped() {
+                self.stopped_notices.push(job.id);
+            }
+        }
+    }
+
+    /// Prints a line for every job that finished or stopped since the last
+    /// prompt, and forgets the finished ones.
+    pub fn notify(&mut self, stderr: &mut impl Write) -> Result<()> {
+        for id in std::mem::take(&mut self.stopped_notices) {
+            if let Some(job) = self.jobs.iter().find(|job| job.id == id && job.is_stopped()) {
+                writeln!(stderr, "\n{}", self.describe(job, false))?;
+            }
+        }
+
+        if self.job_control {
+            for job in self.jobs.iter().filter(|job| job.is_done()) {
+                writeln!(stderr, "{}", self.describe(job, false))?;
+            }
+        }
+
+        self.jobs.retain(|job| !job.is_done());
+
+        Ok(())
+    }
+
+    /// Formats a job the way `jobs` lists it.
+    pub fn describe(&self, job: &Job, with_pid: bool) -> String {
+        let marker = if self.jobs.last().is_some_and(|last| last.id == job.id) {
+            '+'
+        } else if self.jobs.iter().rev().nth(1).is_some_and(|previous| previous.id == job.id) {
+            '-'
+        } else {
+            ' '
+        };
+        let pid = if with_pid {
+            format!("{} ", job.pgid)
+        } else {
+            String::new()
+        };
+        let background = if job.is_stopped() || job.is_done() {
+            ""
+        } else {
+            " &"
+        };
+
+        format!(
+            "[{}]{marker}  {pid}{:<24}{}{background}",
+            job.id,
+            job.state_name(),
+            job.text
+        )
+    }
+
+    /// Finds a job from a job spec like `%2`, `%+`, `%-`, `%vim` (the job
+    /// whose command starts with `vim`) or `%?main` (the job whose command
+    /// contains `main`), or the current job when no spec is given.
+    pub fn find(&self, spec: Option<&str>) -> Result<usize, JobSpecError> {
+        let spec = spec.map(|spec| spec.strip_prefix('%').unwrap_or(spec));
+
+        let index = match spec {
+            None | Some("") | Some("+") | Some("%") => self.jobs.len().checked_sub(1),
+            Some("-") => self.jobs.len().checked_sub(2),
+            Some(spec) => match spec.parse::<usize>() {
+                Ok(id) => self.jobs.iter().position(|job| job.id == id),
+                Err(_) => {
+                    let mut matching = self.jobs.iter().enumerate().filter(|(_, job)| {
+                        match spec.strip_prefix('?') {
+                            Some(text) => job.text.contains(text),
+                            None => job.text.starts_with(spec),
+                        }
+                    });
+                    let first = matching.next().map(|(index, _)| index);
+                    if matching.next().is_some() {
+                        return Err(JobSpecError::Ambiguous);
+                    }
+                    first
+                }
+            },
+        };
+
+        index.ok_or(JobSpecError::NoSuchJob)
+    }
+}
+
+/// Why a job spec didn't pick out a single job.
+#[derive(Debug, PartialEq)]
+pub enum JobSpecError {
+    NoSuchJob,
+    Ambiguous,
+}
+
+impl fmt::Display for JobSpecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoSuchJob => write!(f, "no such job"),
+            Self::Ambiguous => write!(f, "ambiguous job spec"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A table of jobs running `texts`, with ids from 1 and the last one
+    /// current.
+    fn running(texts: &[&str]) -> JobTable {
+        let mut table = JobTable::default();
+        for (pid, text) in (100..).zip(texts) {
+            table.add(Job::new(pid, vec![pid], text.to_string()));
+        }
+        table
+    }
+
+    #[test]
+    fn job_specs() {
+        let table = running(&["vim notes", "make all", "vim main.rs"]);
+
+        assert_eq!(table.find(None), Ok(2));
+        assert_eq!(table.find(Some("%+")), Ok(2));
+        assert_eq!(table.find(Some("%%")), Ok(2));
+        assert_eq!(table.find(Some("%-")), Ok(1));
+        assert_eq!(table.find(Some("%1")), Ok(0));
+        assert_eq!(table.find(Some("2")), Ok(1));
+        assert_eq!(table.find(Some("%4")), Err(JobSpecError::NoSuchJob));
+        assert_eq!(table.find(Some("%make")), Ok(1));
+        assert_eq!(table.find(Some("%?all")), Ok(1));
+        assert_eq!(table.find(Some("%?main")), Ok(2));
+        assert_eq!(table.find(Some("%emacs")), Err(JobSpecError::NoSuchJob));
+        assert_eq!(table.find(Some("%?emacs")), Err(JobSpecError::NoSuchJob));
+    }
+
+    #[test]
+    fn ambiguous_job_specs() {
+        let table = running(&["vim notes", "make all", "vim main.rs"]);
+
+        assert_eq!(table.find(Some("%vim")), Err(JobSpecError::Ambiguous));
+        assert_eq!(table.find(Some("%?m")), Err(JobSpecError::Ambiguous));
+        assert_eq!(table.find(Some("%vim m")), Ok(2));
+        assert_eq!(JobSpecError::Ambiguous.to_string(), "ambiguous job spec");
+
+        let table = running(&["sleep 1"]);
+        assert_eq!(table.find(Some("%-")), Err(JobSpecError::NoSuchJob));
+        assert_eq!(JobTable::default().find(None), Err(JobSpecError::NoSuchJob));
+    }
+
+    #[test]
+    fn job_listing() {
+        let mut table = running(&["make all", "sleep 10", "vim notes", "false", "yes"]);
+        table.jobs[0].processes[0].status = ProcessStatus::Exited(0);
+        table.jobs[2].processes[0].status = ProcessStatus::Stopped(libc::SIGTSTP);
+        table.jobs[3].processes[0].status = ProcessStatus::Exited(1);
+        table.jobs[4].processes[0].status = ProcessStatus::Signaled(libc::SIGTERM);
+
+        let listing: Vec<String> = table
+            .jobs
+            .iter()
+            .map(|job| table.describe(job, false))
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                "[1]   Done                    make all",
+                "[2]   Running                 sleep 10 &",
+                "[3]   Stopped                 vim notes",
+                "[4]-  Exit 1                  false",
+                "[5]+  Terminated              yes",
+            ]
+        );
+
+        table.jobs[4].processes[0].status = ProcessStatus::Signaled(libc::SIGINT);
+        assert_eq!(
+            table.describe(&table.jobs[4], true),
+            "[5]+  104 SIGINT                  yes"
+        );
+    }
+}
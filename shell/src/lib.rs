@@ -1,84 +1,107 @@
+mod aliases;
+mod arithmetic;
+mod brace_expansion;
 mod builtin_commands;
 mod command;
+mod command_hash;
+mod command_list;
+mod command_node;
+mod command_substitution;
+mod completion;
+mod compound_command;
 mod errors;
+mod expansion;
+mod history;
+mod input;
 pub mod input_parser;
+mod jobs;
+mod line_editor;
+mod parser;
+mod pattern;
+mod pipeline;
+mod process;
+mod prompt;
+mod redirect;
+mod shell_state;
+mod traps;
 pub mod utils;
 
 use crate::{
-    builtin_commands::{
-        BuiltinCommand, builtin_type::builtin_type, change_directory::change_directory, echo::echo,
-        pwd::pwd, run_external_executable::run_external_executable,
-    },
-    errors::CustomError,
-    utils::{
-        append_all_to_file, find_executable_file, get_command, get_path, print_prompt,
-        write_all_to_file,
-    },
+    errors::io_error_message, input::Input, process::is_interactive, shell_state::ShellState,
+    traps::run_exit_trap,
 };
-use anyhow::{Context, Result};
+use anyhow::Result;
 
-pub fn run() -> Result<()> {
-    let path = get_path().context("Getting path")?;
-    let mut stdout: Vec<String> = vec![];
-    let mut stderr: Vec<String> = vec![];
+/// Runs the shell and returns its exit status. It is started as one of
+///
+/// - `shell` to read commands from standard input, interactively on a terminal
+/// - `shell -c command [name [argument...]]` to run a string of commands
+/// - `shell script [argument...]` to run a script file
+pub fn run() -> Result<i32> {
+    let arguments: Vec<String> = std::env::args().collect();
+    let shell_name = arguments.first().map_or("shell", String::as_str);
 
-    loop {
-        print_prompt();
+    let (mut state, mut input) = match arguments.get(1).map(String::as_str) {
+        Some("-c") => {
+            let Some(command) = arguments.get(2) else {
+                eprintln!("{shell_name}: -c: option requires an argument");
+                return Ok(2);
+            };
 
-        let command = get_command(&mut stderr).context("getting command")?;
-
-        match command.builtin_command {
-            BuiltinCommand::ChangeDirectory(arguments) => {
-                change_directory(&arguments, &mut stderr)?
-            }
-            BuiltinCommand::Echo(command_string) => {
-                echo(command_string.as_slice(), &mut stdout, &mut stderr)?;
-            }
-            BuiltinCommand::Exit => break,
-            BuiltinCommand::PWD => pwd(&mut stdout, &mut stderr)?,
-            BuiltinCommand::Type(arguments) => {
-                builtin_type(arguments, &path, &mut stdout, &mut stderr)?;
-            }
-            BuiltinCommand::NotFound(command_string, arguments) => {
-                if let Some(executable) = find_executable_file(&command_string, &path) {
-                    run_external_executable(executable, &arguments, &mut stdout, &mut stderr)?;
-                } else {
-                    let error = CustomError::CommandNotFound(command_string);
-                    stderr.push(format!("{error}"));
-                }
+            let mut state = ShellState::new(false)?;
+            if let Some(name) = arguments.get(3) {
+                state.shell_name.clone_from(name);
             }
-        }
+            state.positional_parameters = arguments.iter().skip(4).cloned().collect();
 
-        match command.standard_out {
-            command::Output::Standard => {
-                stdout
-                    .iter()
-                    .map(|message| message.trim())
-                    .for_each(|message| println!("{message}"));
-            }
-            command::Output::CreateFile(input) => {
-                write_all_to_file(&stdout, &input).context("redirecting standard out to a file")?
-            }
-            command::Output::AppendFile(input) => append_all_to_file(&stdout, &input)
-                .context("Error appending standard out to a file.")?,
+            (state, Input::from_text(command.clone()))
         }
+        Some(option) if option.starts_with('-') => {
+            eprintln!("{shell_name}: {option}: invalid option");
+            eprintln!("Usage: {shell_name} [-c command [argument...]] [script [argument...]]");
+            return Ok(2);
+        }
+        Some(script) => {
+            let input = match Input::from_file(script.as_ref()) {
+                Ok(input) => input,
+                Err(error) => {
+                    eprintln!("{shell_name}: {script}: {}", io_error_message(&error));
+                    return Ok(127);
+                }
+            };
 
-        match command.standard_error {
-            command::Output::Standard => {
-                stderr
-                    .iter()
-                    .map(|message| message.trim())
-                    .for_each(|message| eprintln!("{message}"));
+            let mut state = ShellState::new(false)?;
+            state.shell_name = script.to_owned();
+            state.positional_parameters = arguments[2..].to_vec();
+
+            (state, input)
+        }
+        None => {
+            let interactive = is_interactive();
+            let mut state = ShellState::new(interactive)?;
+            if interactive {
+                load_startup_file(&mut state)?;
             }
-            command::Output::CreateFile(input) => write_all_to_file(&stderr, &input)
-                .context("redirecting standard error to a file")?,
-            command::Output::AppendFile(input) => append_all_to_file(&stderr, &input)
-                .context("Error appending standard error to a file.")?,
+
+            (state, Input::standard_input(interactive))
         }
+    };
+
+    input.run(&mut state)?;
+    run_exit_trap(&mut state)?;
+
+    Ok(state.last_exit_status)
+}
 
-        stderr.clear();
-        stdout.clear();
-    }
+/// Runs `~/.shellrc`, if there is one, before an interactive shell shows its
+/// first prompt.
+fn load_startup_file(state: &mut ShellState) -> Result<()> {
+    let Some(path) = std::env::home_dir().map(|home| home.join(".shellrc")) else {
+        return Ok(());
+    };
+    let Ok(mut input) = Input::from_file(&path) else {
+        return Ok(());
+    };
 
-    Ok(())
+    input.run(state)
 }
@@ -0,0 +1,428 @@
+use anyhow::{Context, Result};
+use std::io::{self, Write};
+
+/// Returns completions for the word being typed, given whether it is in
+/// command position.
+pub type Completer<'a> = &'a dyn Fn(&str, bool) -> Vec<String>;
+
+/// Reads a line from the terminal with cursor movement, history navigation,
+/// reverse search and tab completion. Returns `None` on end of input.
+pub fn read_line(prompt: &str, history: &[String], complete: Completer) -> Result<Option<String>> {
+    // only the last line of the prompt is redrawn as the line is edited
+    let prompt = match prompt.rsplit_once('\n') {
+        Some((first_lines, last_line)) => {
+            write_terminal(&format!("{first_lines}\n"))?;
+            last_line
+        }
+        None => prompt,
+    };
+
+    let _raw_mode = RawMode::enable()?;
+    let mut editor = Editor::new(prompt, history);
+
+    editor.run(complete)
+}
+
+/// Puts the terminal in a mode where keys arrive one at a time and aren't
+/// echoed, restoring the previous settings on drop.
+struct RawMode {
+    original: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> Result<Self> {
+        let mut original: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut original) } == -1 {
+            return Err(io::Error::last_os_error()).context("reading terminal settings");
+        }
+
+        let mut raw = original;
+        raw.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG | libc::IEXTEN);
+        raw.c_iflag &= !(libc::IXON | libc::ICRNL);
+        raw.c_cc[libc::VMIN] = 1;
+        raw.c_cc[libc::VTIME] = 0;
+
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &raw) } == -1 {
+            return Err(io::Error::last_os_error()).context("enabling raw mode");
+        }
+
+        Ok(Self { original })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSADRAIN, &self.original) };
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Key {
+    Character(char),
+    Control(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+    Escape,
+}
+
+fn read_byte() -> Result<Option<u8>> {
+    let mut byte = 0u8;
+
+    loop {
+        match unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) } {
+            1 => return Ok(Some(byte)),
+            0 => return Ok(None),
+            _ => {
+                let error = io::Error::last_os_error();
+                if error.kind() != io::ErrorKind::Interrupted {
+                    return Err(error).context("reading from terminal");
+                }
+            }
+        }
+    }
+}
+
+fn read_key() -> Result<Option<Key>> {
+    let Some(byte) = read_byte()? else {
+        return Ok(None);
+    };
+
+    let key = match byte {
+        b'\r' | b'\n' => Key::Enter,
+        b'\t' => Key::Tab,
+        0x7f | 0x08 => Key::Backspace,
+        0x1b => read_escape_sequence()?,
+        0x01..=0x1a => Key::Control((b'a' + byte - 1) as char),
+        byte if byte < 0x80 => Key::Character(byte as char),
+        lead => {
+            let length = match lead {
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            let mut bytes = vec![lead];
+            for _ in 1..length {
+                bytes.extend(read_byte()?);
+            }
+            let text = String::from_utf8_lossy(&bytes);
+            Key::Character(text.chars().next().unwrap_or(char::REPLACEMENT_CHARACTER))
+        }
+    };
+
+    Ok(Some(key))
+}
+
+fn read_escape_sequence() -> Result<Key> {
+    let Some(introducer) = read_byte()? else {
+        return Ok(Key::Escape);
+    };
+    if introducer != b'[' && introducer != b'O' {
+        return Ok(Key::Escape);
+    }
+
+    let mut parameter = String::new();
+    loop {
+        let Some(byte) = read_byte()? else {
+            return Ok(Key::Escape);
+        };
+
+        let key = match byte {
+            b'0'..=b'9' | b';' => {
+                parameter.push(byte as char);
+                continue;
+            }
+            b'A' => Key::Up,
+            b'B' => Key::Down,
+            b'C' => Key::Right,
+            b'D' => Key::Left,
+            b'H' => Key::Home,
+            b'F' => Key::End,
+            b'~' => match parameter.as_str() {
+                "1" | "7" => Key::Home,
+                "4" | "8" => Key::End,
+                "3" => Key::Delete,
+                _ => Key::Escape,
+            },
+            _ => Key::Escape,
+        };
+
+        return Ok(key);
+    }
+}
+
+struct Editor<'a> {
+    prompt: &'a str,
+    buffer: Vec<char>,
+    cursor: usize,
+    history: &'a [String],
+    history_index: usize,
+    /// The line being typed before moving into history.
+    saved_line: Vec<char>,
+    last_key_was_tab: bool,
+}
+
+impl<'a> Editor<'a> {
+    fn new(prompt: &'a str, history: &'a [String]) -> Self {
+        Self {
+            prompt,
+            buffer: vec![],
+            cursor: 0,
+            history,
+            history_index: history.len(),
+            saved_line: vec![],
+            last_key_was_tab: false,
+        }
+    }
+
+    fn run(&mut self, complete: Completer) -> Result<Option<String>> {
+        self.render()?;
+
+        while let Some(key) = read_key()? {
+            let is_tab = key == Key::Tab;
+
+            match self.handle_key(key, complete)? {
+                Some(Outcome::Line) => {
+                    write_terminal("\r\n")?;
+                    return Ok(Some(self.buffer.iter().collect()));
+                }
+                Some(Outcome::EndOfInput) => {
+                    write_terminal("\r\n")?;
+                    return Ok(None);
+                }
+                None => (),
+            }
+
+            self.last_key_was_tab = is_tab;
+        }
+
+        Ok(None)
+    }
+
+    fn handle_key(&mut self, key: Key, complete: Completer) -> Result<Option<Outcome>> {
+        match key {
+            Key::Enter => return Ok(Some(Outcome::Line)),
+            Key::Character(character) => {
+                self.buffer.insert(self.cursor, character);
+                self.cursor += 1;
+            }
+            Key::Tab => self.complete(complete)?,
+            Key::Backspace | Key::Control('h') if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.buffer.remove(self.cursor);
+            }
+            Key::Delete if self.cursor < self.buffer.len() => {
+                self.buffer.remove(self.cursor);
+            }
+            Key::Control('d') => {
+                if self.buffer.is_empty() {
+                    return Ok(Some(Outcome::EndOfInput));
+                }
+                if self.cursor < self.buffer.len() {
+                    self.buffer.remove(self.cursor);
+                }
+            }
+            Key::Left | Key::Control('b') => self.cursor = self.cursor.saturating_sub(1),
+            Key::Right | Key::Control('f') => self.cursor = (self.cursor + 1).min(self.buffer.len()),
+            Key::Home | Key::Control('a') => self.cursor = 0,
+            Key::End | Key::Control('e') => self.cursor = self.buffer.len(),
+            Key::Up | Key::Control('p') => self.move_in_history(-1),
+            Key::Down | Key::Control('n') => self.move_in_history(1),
+            Key::Control('k') => self.buffer.truncate(self.cursor),
+            Key::Control('u') => {
+                self.buffer.drain(..self.cursor);
+                self.cursor = 0;
+            }
+            Key::Control('w') => {
+                let mut start = self.cursor;
+                while start > 0 && self.buffer[start - 1] == ' ' {
+                    start -= 1;
+                }
+                while start > 0 && self.buffer[start - 1] != ' ' {
+                    start -= 1;
+                }
+                self.buffer.drain(start..self.cursor);
+                self.cursor = start;
+            }
+            Key::Control('l') => write_terminal("\x1b[H\x1b[2J")?,
+            Key::Control('c') => {
+                write_terminal("^C\r\n")?;
+                self.buffer.clear();
+                self.cursor = 0;
+                self.history_index = self.history.len();
+            }
+            Key::Control('r') => {
+                if let Some(outcome) = self.reverse_search(complete)? {
+                    return Ok(Some(outcome));
+                }
+            }
+            _ => (),
+        }
+
+        self.render()?;
+        Ok(None)
+    }
+
+    fn render(&self) -> Result<()> {
+        let line: String = self.buffer.iter().collect();
+        let after_cursor = self.buffer.len() - self.cursor;
+        let mut output = format!("\r{}{line}\x1b[K", self.prompt);
+
+        if after_cursor > 0 {
+            output.push_str(&format!("\x1b[{after_cursor}D"));
+        }
+
+        write_terminal(&output)
+    }
+
+    fn set_buffer(&mut self, line: &str) {
+        self.buffer = line.chars().collect();
+        self.cursor = self.buffer.len();
+    }
+
+    fn move_in_history(&mut self, direction: isize) {
+        let Some(index) = self.history_index.checked_add_signed(direction) else {
+            return;
+        };
+        if index > self.history.len() {
+            return;
+        }
+
+        if self.history_index == self.history.len() {
+            self.saved_line = self.buffer.clone();
+        }
+        self.history_index = index;
+
+        match self.history.get(index) {
+            Some(entry) => self.set_buffer(entry),
+            None => {
+                self.buffer = self.saved_line.clone();
+                self.cursor = self.buffer.len();
+            }
+        }
+    }
+
+    /// Ctrl-R: incrementally searches backwards through history for lines
+    /// containing the typed text.
+    fn reverse_search(&mut self, complete: Completer) -> Result<Option<Outcome>> {
+        let original = self.buffer.clone();
+        let mut query = String::new();
+        let mut found: Option<usize> = None;
+
+        loop {
+            let matched = found.map(|index| self.history[index].as_str()).unwrap_or("");
+            write_terminal(&format!("\r(reverse-i-search)`{query}': {matched}\x1b[K"))?;
+
+            let Some(key) = read_key()? else {
+                return Ok(Some(Outcome::EndOfInput));
+            };
+
+            let search_from = match key {
+                Key::Character(character) => {
+                    query.push(character);
+                    found.map_or(self.history.len(), |index| index + 1)
+                }
+                Key::Backspace => {
+                    query.pop();
+                    self.history.len()
+                }
+                Key::Control('r') => found.unwrap_or(self.history.len()),
+                Key::Control('g') | Key::Control('c') => {
+                    self.buffer = original;
+                    self.cursor = self.buffer.len();
+                    return Ok(None);
+                }
+                key => {
+                    if let Some(index) = found {
+                        self.history_index = index;
+                        let entry = self.history[index].clone();
+                        self.set_buffer(&entry);
+                    }
+
+                    return match key {
+                        Key::Escape => Ok(None),
+                        key => self.handle_key(key, complete),
+                    };
+                }
+            };
+
+            found = self.history[..search_from.min(self.history.len())]
+                .iter()
+                .rposition(|entry| entry.contains(&query))
+                .or(found.filter(|index| self.history[*index].contains(&query)));
+        }
+    }
+
+    fn complete(&mut self, complete: Completer) -> Result<()> {
+        let before_cursor: String = self.buffer[..self.cursor].iter().collect();
+        let word_start = before_cursor
+            .rfind(|character: char| character.is_whitespace() || "|&;(".contains(character))
+            .map_or(0, |index| index + 1);
+        let word = &before_cursor[word_start..];
+        let command_position = before_cursor[..word_start]
+            .trim_end()
+            .chars()
+            .last()
+            .is_none_or(|character| "|&;(".contains(character));
+
+        let candidates = complete(word, command_position);
+
+        let replacement = match candidates.as_slice() {
+            [] => return Ok(()),
+            [candidate] if candidate.ends_with('/') => candidate.clone(),
+            [candidate] => format!("{candidate} "),
+            candidates => {
+                let prefix = common_prefix(candidates);
+                if prefix.chars().count() > word.chars().count() {
+                    prefix
+                } else {
+                    if self.last_key_was_tab {
+                        write_terminal(&format!("\r\n{}\r\n", candidates.join("  ")))?;
+                    }
+                    return Ok(());
+                }
+            }
+        };
+
+        let start = before_cursor[..word_start].chars().count();
+        self.buffer.splice(start..self.cursor, replacement.chars());
+        self.cursor = start + replacement.chars().count();
+
+        Ok(())
+    }
+}
+
+enum Outcome {
+    Line,
+    EndOfInput,
+}
+
+fn common_prefix(candidates: &[String]) -> String {
+    let mut prefix: Vec<char> = candidates[0].chars().collect();
+
+    for candidate in &candidates[1..] {
+        let matching = prefix
+            .iter()
+            .zip(candidate.chars())
+            .take_while(|(left, right)| **left == *right)
+            .count();
+        prefix.truncate(matching);
+    }
+
+    prefix.into_iter().collect()
+}
+
+fn write_terminal(text: &str) -> Result<()> {
+    let mut stdout = io::stdout();
+    stdout.write_all(text.as_bytes())?;
+    stdout.flush()?;
+
+    Ok(())
+}
@@ -2,9 +2,9 @@ use shell::{run, utils::exit};
 
 fn main() {
     match run() {
-        Ok(()) => exit(0),
+        Ok(status) => exit(status),
         Err(error) => {
-            eprintln!("Error: ${error:?}");
+            eprintln!("shell: {error:#}");
             exit(1);
         }
     }
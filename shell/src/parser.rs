@@ -0,0 +1,846 @@
+use std::{iter::Peekable, rc::Rc, vec};
+
+use crate::{
+    command::Command,
+    command_list::{AndOr, CommandList, Connector, ListItem},
+    command_node::CommandNode,
+    compound_command::{CaseItem, CompoundCommand},
+    errors::CustomError,
+    input_parser::{Token, Word, is_valid_name},
+    pipeline::Pipeline,
+    redirect::{Redirection, Redirects},
+};
+
+/// Reserved words that end the list before them when they appear in command
+/// position.
+const LIST_TERMINATORS: &[&str] = &["}", "then", "elif", "else", "fi", "do", "done", "esac"];
+
+/// Builds the command tree from the tokens of a complete input. Fails with
+/// `CustomError::UnexpectedEndOfInput` when the input stops part way through
+/// a command, such as after `&&` or inside an unclosed group, so the caller
+/// can ask for another line.
+pub fn parse(tokens: Vec<Token>) -> Result<CommandList, CustomError> {
+    let mut parser = Parser {
+        tokens: tokens.into_iter().peekable(),
+        last_keyword: None,
+    };
+    let list = parser.parse_list()?;
+
+    match parser.tokens.next() {
+        Some(token) => Err(CustomError::UnexpectedToken(token.to_string())),
+        None => Ok(list),
+    }
+}
+
+struct Parser {
+    tokens: Peekable<vec::IntoIter<Token>>,
+    /// The keyword most recently consumed by `expect`, used to tell which
+    /// one closed an `if` branch.
+    last_keyword: Option<String>,
+}
+
+impl Parser {
+    /// list := and_or ((`;` | `&` | newline) and_or?)*
+    fn parse_list(&mut self) -> Result<CommandList, CustomError> {
+        let mut list = CommandList::default();
+
+        loop {
+            self.skip_newlines();
+
+            if self.at_list_end() {
+                break;
+            }
+
+            let and_or = self.parse_and_or()?;
+            let background = match self.tokens.peek() {
+                Some(Token::Background) => true,
+                Some(Token::Semicolon) | Some(Token::Newline) => false,
+                _ => {
+                    list.items.push(ListItem {
+                        and_or,
+                        background: false,
+                    });
+                    break;
+                }
+            };
+            self.tokens.next();
+
+            list.items.push(ListItem { and_or, background });
+        }
+
+        Ok(list)
+    }
+
+    /// and_or := pipeline ((`&&` | `||`) newline* pipeline)*
+    fn parse_and_or(&mut self) -> Result<AndOr, CustomError> {
+        let first = self.parse_pipeline()?;
+        let mut rest = vec![];
+
+        loop {
+            let connector = match self.tokens.peek() {
+                Some(Token::And) => Connector::And,
+                Some(Token::Or) => Connector::Or,
+                _ => break,
+            };
+            self.tokens.next();
+            self.skip_newlines();
+
+            rest.push((connector, self.parse_pipeline()?));
+        }
+
+        Ok(AndOr { first, rest })
+    }
+
+    /// pipeline := `!`? command (`|` newline* command)*
+    fn parse_pipeline(&mut self) -> Result<Pipeline, CustomError> {
+        let negated = self.next_if_literal("!");
+        let mut stages = vec![self.parse_command()?];
+
+        while self.tokens.next_if_eq(&Token::Pipe).is_some() {
+            self.skip_newlines();
+            stages.push(self.parse_command()?);
+        }
+
+        Ok(Pipeline { stages, negated })
+    }
+
+    /// command := compound redirects | name `(` `)` command | simple
+    fn parse_command(&mut self) -> Result<CommandNode, CustomError> {
+        let compound = match self.tokens.peek() {
+            None => return Err(CustomError::UnexpectedEndOfInput),
+            Some(Token::LeftParenthesis) => {
+                self.tokens.next();
+                let body = self.parse_list()?;
+                self.expect_close(&body, |token| *token == Token::RightParenthesis)?;
+
+                CompoundCommand::Subshell(body)
+            }
+            Some(Token::Word(word)) => match word.as_literal() {
+                Some("{") => {
+                    self.tokens.next();
+                    let body = self.parse_list()?;
+                    self.expect_close(&body, |token| is_literal(token, "}"))?;
+
+                    CompoundCommand::Group(body)
+                }
+                Some("if") => self.parse_if()?,
+                Some(keyword @ ("while" | "until")) => {
+                    let until = keyword == "until";
+                    self.tokens.next();
+                    let condition = self.parse_list()?;
+                    if condition.is_empty() {
+                        return Err(self.unexpected_token());
+                    }
+                    let body = self.parse_do_group()?;
+
+                    CompoundCommand::Loop {
+                        until,
+                        condition,
+                        body,
+                    }
+                }
+                Some("for") => self.parse_for()?,
+                Some("case") => self.parse_case()?,
+                _ => return self.parse_simple_command(),
+            },
+            Some(Token::Redirection(..)) => return self.parse_simple_command(),
+            Some(_) => return Err(self.unexpected_token()),
+        };
+        let redirects = self.parse_redirects()?;
+
+        Ok(CommandNode::Compound(compound, redirects))
+    }
+
+    /// Reads a simple command, or a function definition when a lone name is
+    /// followed by `()`.
+    fn parse_simple_command(&mut self) -> Result<CommandNode, CustomError> {
+        let (words, redirects) = self.take_words_and_redirects()?;
+
+        if let [word] = words.as_slice()
+            && redirects.is_empty()
+            && let Some(name) = word.as_literal().filter(|name| is_valid_name(name))
+            && self.tokens.next_if_eq(&Token::LeftParenthesis).is_some()
+        {
+            let name = name.to_owned();
+            self.expect(|token| *token == Token::RightParenthesis)?;
+            self.skip_newlines();
+            let body = self.parse_command()?;
+
+            return Ok(CommandNode::FunctionDefinition(name, Rc::new(body)));
+        }
+
+        Ok(CommandNode::Simple(Command::new(words, redirects)))
+    }
+
+    /// if := `if` list `then` list (`elif` list `then` list)* (`else` list)? `fi`
+    fn parse_if(&mut self) -> Result<CompoundCommand, CustomError> {
+        let mut branches = vec![];
+        let mut otherwise = None;
+        self.tokens.next();
+
+        loop {
+            let condition = self.parse_list()?;
+            self.expect_close(&condition, |token| is_literal(token, "then"))?;
+            let body = self.parse_list()?;
+            self.expect_close(&body, |token| {
+                ["elif", "else", "fi"]
+                    .iter()
+                    .any(|keyword| is_literal(token, keyword))
+            })?;
+            branches.push((condition, body));
+
+            match self.last_keyword.as_deref() {
+                Some("elif") => continue,
+                Some("else") => {
+                    let body = self.parse_list()?;
+                    self.expect_close(&body, |token| is_literal(token, "fi"))?;
+                    otherwise = Some(body);
+                }
+                _ => (),
+            }
+            break;
+        }
+
+        Ok(CompoundCommand::If {
+            branches,
+            otherwise,
+        })
+    }
+
+    /// for := `for` name ((newline* `in` word* (`;` | newline)) | `;`)? newline* do_group
+    fn parse_for(&mut self) -> Result<CompoundCommand, CustomError> {
+        self.tokens.next();
+
+        let variable = match self.tokens.next() {
+            None => return Err(CustomError::UnexpectedEndOfInput),
+            Some(Token::Word(word)) if word.as_literal().is_some_and(is_valid_name) => {
+                word.as_literal().unwrap_or_default().to_owned()
+            }
+            Some(token) => return Err(CustomError::UnexpectedToken(token.to_string())),
+        };
+
+        self.skip_newlines();
+        let words = if self.next_if_literal("in") {
+            let words = self.take_words();
+            self.expect(|token| matches!(token, Token::Semicolon | Token::Newline))?;
+            Some(words)
+        } else {
+            self.tokens.next_if_eq(&Token::Semicolon);
+            None
+        };
+
+        let body = self.parse_do_group()?;
+
+        Ok(CompoundCommand::For {
+            variable,
+            words,
+            body,
+        })
+    }
+
+    /// do_group := newline* `do` list `done`
+    fn parse_do_group(&mut self) -> Result<CommandList, CustomError> {
+        self.skip_newlines();
+        self.expect(|token| is_literal(token, "do"))?;
+
+        let body = self.parse_list()?;
+        self.expect_close(&body, |token| is_literal(token, "done"))?;
+
+        Ok(body)
+    }
+
+    /// case := `case` word newline* `in` newline* case_item* `esac`
+    /// case_item := `(`? word (`|` word)* `)` list (`;;` | before `esac`)
+    fn parse_case(&mut self) -> Result<CompoundCommand, CustomError> {
+        self.tokens.next();
+
+        let word = match self.tokens.next() {
+            None => return Err(CustomError::UnexpectedEndOfInput),
+            Some(Token::Word(word)) => word,
+            Some(token) => return Err(CustomError::UnexpectedToken(token.to_string())),
+        };
+        self.skip_newlines();
+        self.expect(|token| is_literal(token, "in"))?;
+
+        let mut items = vec![];
+        loop {
+            self.skip_newlines();
+            if self.next_if_literal("esac") {
+                break;
+            }
+
+            self.tokens.next_if_eq(&Token::LeftParenthesis);
+            let mut patterns = vec![self.expect_word()?];
+            while self.tokens.next_if_eq(&Token::Pipe).is_some() {
+                patterns.push(self.expect_word()?);
+            }
+            self.expect(|token| *token == Token::RightParenthesis)?;
+
+            let body = self.parse_list()?;
+            items.push(CaseItem { patterns, body });
+
+            match self.tokens.peek() {
+                Some(Token::DoubleSemicolon) => {
+                    self.tokens.next();
+                }
+                Some(token) if is_literal(token, "esac") => (),
+                None => return Err(CustomError::UnexpectedEndOfInput),
+                Some(_) => return Err(self.unexpected_token()),
+            }
+        }
+
+        Ok(CompoundCommand::Case { word, items })
+    }
+
+    /// Consumes the token that closes `body`, which must not be empty.
+    fn expect_close(
+        &mut self,
+        body: &CommandList,
+        is_close: impl Fn(&Token) -> bool,
+    ) -> Result<(), CustomError> {
+        match self.tokens.peek() {
+            None => Err(CustomError::UnexpectedEndOfInput),
+            Some(token) if is_close(token) && !body.is_empty() => self.expect(is_close),
+            Some(_) => Err(self.unexpected_token()),
+        }
+    }
+
+    /// Consumes the next token if it is the one expected, remembering it if
+    /// it was a keyword.
+    fn expect(&mut self, is_expected: impl Fn(&Token) -> bool) -> Result<(), CustomError> {
+        match self.tokens.next() {
+            None => Err(CustomError::UnexpectedEndOfInput),
+            Some(token) if is_expected(&token) => {
+                self.last_keyword = match token {
+                    Token::Word(word) => word.as_literal().map(str::to_owned),
+                    _ => None,
+                };
+                Ok(())
+            }
+            Some(token) => Err(CustomError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    fn expect_word(&mut self) -> Result<Word, CustomError> {
+        match self.tokens.next() {
+            None => Err(CustomError::UnexpectedEndOfInput),
+            Some(Token::Word(word)) => Ok(word),
+            Some(token) => Err(CustomError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Reads the redirections that may follow a group or subshell.
+    fn parse_redirects(&mut self) -> Result<Redirects, CustomError> {
+        let (words, redirects) = self.take_words_and_redirects()?;
+
+        match words.first() {
+            Some(word) => Err(CustomError::UnexpectedToken(word.to_string())),
+            None => Ok(redirects),
+        }
+    }
+
+    /// Reads the words and redirections of a simple command, which can be
+    /// mixed in any order.
+    fn take_words_and_redirects(&mut self) -> Result<(Vec<Word>, Redirects), CustomError> {
+        let mut words = vec![];
+        let mut redirects = Redirects::default();
+
+        loop {
+            match self
+                .tokens
+                .next_if(|token| matches!(token, Token::Word(_) | Token::Redirection(..)))
+            {
+                Some(Token::Word(word)) => words.push(word),
+                Some(Token::Redirection(fd, operator)) => {
+                    let target = match self.tokens.next() {
+                        Some(Token::Word(word)) => word,
+                        Some(token) => return Err(CustomError::UnexpectedToken(token.to_string())),
+                        None => {
+                            return Err(CustomError::UnexpectedToken(Token::Newline.to_string()));
+                        }
+                    };
+                    redirects.0.push(Redirection::new(fd, operator, target));
+                }
+                _ => break,
+            }
+        }
+
+        Ok((words, redirects))
+    }
+
+    fn take_words(&mut self) -> Vec<Word> {
+        let mut words = vec![];
+
+        while let Some(Token::Word(word)) =
+            self.tokens.next_if(|token| matches!(token, Token::Word(_)))
+        {
+            words.push(word);
+        }
+
+        words
+    }
+
+    /// Whether the next token closes the list being parsed.
+    fn at_list_end(&mut self) -> bool {
+        match self.tokens.peek() {
+            None | Some(Token::RightParenthesis) | Some(Token::DoubleSemicolon) => true,
+            Some(token) => LIST_TERMINATORS
+                .iter()
+                .any(|terminator| is_literal(token, terminator)),
+        }
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.tokens.next_if_eq(&Token::Newline).is_some() {}
+    }
+
+    fn next_if_literal(&mut self, literal: &str) -> bool {
+        self.tokens
+            .next_if(|token| is_literal(token, literal))
+            .is_some()
+    }
+
+    fn unexpected_token(&mut self) -> CustomError {
+        match self.tokens.next() {
+            Some(token) => CustomError::UnexpectedToken(token.to_string()),
+            None => CustomError::UnexpectedEndOfInput,
+        }
+    }
+}
+
+fn is_literal(token: &Token, literal: &str) -> bool {
+    matches!(token, Token::Word(word) if word.as_literal() == Some(literal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input_parser::{RedirectionOperator, parse_input};
+    use std::os::fd::RawFd;
+
+    fn try_parse(input: &str) -> Result<CommandList, CustomError> {
+        parse(parse_input(input.to_owned())?)
+    }
+
+    fn parsed(input: &str) -> CommandList {
+        try_parse(input).unwrap_or_else(|error| panic!("{input:?}: {error}"))
+    }
+
+    /// The error message for input that doesn't parse.
+    fn syntax_error(input: &str) -> String {
+        match try_parse(input) {
+            Ok(list) => panic!("{input:?} parsed as {list}"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    fn only_command(input: &str) -> CommandNode {
+        let mut list = parsed(input);
+        assert_eq!(list.items.len(), 1, "{input:?}");
+        let item = list.items.remove(0);
+        assert!(item.and_or.rest.is_empty(), "{input:?}");
+
+        let mut stages = item.and_or.first.stages;
+        assert_eq!(stages.len(), 1, "{input:?}");
+        stages.remove(0)
+    }
+
+    #[test]
+    fn lists() {
+        let list = parsed("a; b & c\nd");
+
+        let background: Vec<bool> = list.items.iter().map(|item| item.background).collect();
+        assert_eq!(background, [false, true, false, false]);
+        assert_eq!(list.to_string(), "a; b & c; d");
+
+        assert!(parsed("").is_empty());
+        assert!(parsed("\n\n").is_empty());
+        assert_eq!(parsed("a;").items.len(), 1);
+        assert_eq!(parsed("a &").to_string(), "a &");
+    }
+
+    #[test]
+    fn and_or_lists() {
+        let list = parsed("a && b || c");
+        let and_or = &list.items[0].and_or;
+
+        let connectors: Vec<Connector> = and_or
+            .rest
+            .iter()
+            .map(|(connector, _)| *connector)
+            .collect();
+        assert_eq!(connectors, [Connector::And, Connector::Or]);
+        assert_eq!(and_or.to_string(), "a && b || c");
+
+        assert_eq!(parsed("a &&\n\nb").to_string(), "a && b");
+        assert_eq!(parsed("a && b & c").to_string(), "a && b & c");
+    }
+
+    #[test]
+    fn pipelines() {
+        let list = parsed("! a | b |\n c");
+        let pipeline = &list.items[0].and_or.first;
+
+        assert!(pipeline.negated);
+        assert_eq!(pipeline.stages.len(), 3);
+        assert_eq!(pipeline.to_string(), "! a | b | c");
+    }
+
+    #[test]
+    fn groups_and_subshells() {
+        let CommandNode::Compound(CompoundCommand::Group(body), _) = only_command("{ a; b; }")
+        else {
+            panic!("not a group");
+        };
+        assert_eq!(body.items.len(), 2);
+
+        let CommandNode::Compound(CompoundCommand::Subshell(body), _) = only_command("(a && b)")
+        else {
+            panic!("not a subshell");
+        };
+        assert_eq!(body.to_string(), "a && b");
+
+        assert_eq!(parsed("{ (a) | b\n}").to_string(), "{ (a) | b; }");
+        assert_eq!(parsed("(a &)").to_string(), "(a &)");
+        assert_eq!(
+            parsed("a | (b; c) && { d; }").to_string(),
+            "a | (b; c) && { d; }"
+        );
+    }
+
+    #[test]
+    fn braces_are_only_reserved_as_whole_words() {
+        assert_eq!(only_command("echo { }").to_string(), "echo { }");
+        assert!(matches!(only_command("{a}"), CommandNode::Simple(_)));
+    }
+
+    #[test]
+    fn incomplete_lists() {
+        for input in ["a &&", "a ||", "a |", "{ a;", "(a", "{ a; } &&"] {
+            assert!(
+                matches!(try_parse(input), Err(CustomError::UnexpectedEndOfInput)),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn list_syntax_errors() {
+        assert_eq!(
+            syntax_error("&& a"),
+            "syntax error near unexpected token `&&'"
+        );
+        assert_eq!(
+            syntax_error("a; ; b"),
+            "syntax error near unexpected token `;'"
+        );
+        assert_eq!(
+            syntax_error("a | | b"),
+            "syntax error near unexpected token `|'"
+        );
+        assert_eq!(
+            syntax_error("a )"),
+            "syntax error near unexpected token `)'"
+        );
+        assert_eq!(
+            syntax_error("{ }"),
+            "syntax error near unexpected token `}'"
+        );
+        assert_eq!(syntax_error("()"), "syntax error near unexpected token `)'");
+        assert_eq!(
+            syntax_error("a ;; b"),
+            "syntax error near unexpected token `;;'"
+        );
+        assert_eq!(
+            syntax_error("(a) b"),
+            "syntax error near unexpected token `b'"
+        );
+    }
+
+    #[test]
+    fn if_commands() {
+        let CommandNode::Compound(
+            CompoundCommand::If {
+                branches,
+                otherwise,
+            },
+            _,
+        ) = only_command("if a; then b; elif c\nthen d; e; else f; fi")
+        else {
+            panic!("not an if");
+        };
+
+        let branches: Vec<(String, String)> = branches
+            .iter()
+            .map(|(condition, body)| (condition.to_string(), body.to_string()))
+            .collect();
+        assert_eq!(
+            branches,
+            [
+                ("a".to_owned(), "b".to_owned()),
+                ("c".to_owned(), "d; e".to_owned())
+            ]
+        );
+        assert_eq!(otherwise.map(|body| body.to_string()).as_deref(), Some("f"));
+
+        assert_eq!(parsed("if a\nthen\n b\nfi").to_string(), "if a; then b; fi");
+        assert_eq!(
+            parsed("if ! a && b; then c & fi").to_string(),
+            "if ! a && b; then c & fi"
+        );
+    }
+
+    #[test]
+    fn loops() {
+        let CommandNode::Compound(
+            CompoundCommand::Loop {
+                until,
+                condition,
+                body,
+            },
+            _,
+        ) = only_command("until a; do b; c; done")
+        else {
+            panic!("not a loop");
+        };
+        assert!(until);
+        assert_eq!(condition.to_string(), "a");
+        assert_eq!(body.items.len(), 2);
+
+        assert_eq!(
+            parsed("while a\ndo\n  b\ndone").to_string(),
+            "while a; do b; done"
+        );
+    }
+
+    #[test]
+    fn for_loops() {
+        let CommandNode::Compound(
+            CompoundCommand::For {
+                variable,
+                words,
+                body,
+            },
+            _,
+        ) = only_command("for x in a 'b c'; do echo $x; done")
+        else {
+            panic!("not a for loop");
+        };
+        assert_eq!(variable, "x");
+        assert_eq!(words.map(|words| words.len()), Some(2));
+        assert_eq!(body.to_string(), "echo ${x}");
+
+        for input in ["for x; do a; done", "for x\ndo a; done", "for x do a; done"] {
+            let CommandNode::Compound(CompoundCommand::For { words, .. }, _) = only_command(input)
+            else {
+                panic!("{input:?} is not a for loop");
+            };
+            assert!(words.is_none(), "{input:?}");
+        }
+
+        assert_eq!(
+            parsed("for x in\ndo a; done").to_string(),
+            "for x in; do a; done"
+        );
+    }
+
+    #[test]
+    fn case_commands() {
+        let CommandNode::Compound(CompoundCommand::Case { word, items }, _) =
+            only_command("case $x in\n a | b) c;;\n (*) d; e\nesac")
+        else {
+            panic!("not a case");
+        };
+        assert_eq!(word.to_string(), "${x}");
+
+        let items: Vec<(usize, String)> = items
+            .iter()
+            .map(|item| (item.patterns.len(), item.body.to_string()))
+            .collect();
+        assert_eq!(items, [(2, "c".to_owned()), (1, "d; e".to_owned())]);
+
+        assert_eq!(parsed("case x in esac").to_string(), "case x in esac");
+        assert_eq!(
+            parsed("case x in a) ;; esac").to_string(),
+            "case x in a) ;; esac"
+        );
+    }
+
+    #[test]
+    fn function_definitions() {
+        let CommandNode::FunctionDefinition(name, body) = only_command("f() { echo $1; }") else {
+            panic!("not a function definition");
+        };
+        assert_eq!(name, "f");
+        assert!(matches!(
+            *body,
+            CommandNode::Compound(CompoundCommand::Group(_), _)
+        ));
+
+        assert_eq!(parsed("f ( )\n(a) > out").to_string(), "f() (a) > out");
+        assert_eq!(
+            parsed("f() if a; then b; fi").to_string(),
+            "f() if a; then b; fi"
+        );
+    }
+
+    #[test]
+    fn reserved_words_are_only_special_in_command_position() {
+        assert_eq!(
+            only_command("echo if then fi done").to_string(),
+            "echo if then fi done"
+        );
+        assert_eq!(
+            parsed("if echo fi; then echo then; fi").to_string(),
+            "if echo fi; then echo then; fi"
+        );
+    }
+
+    #[test]
+    fn incomplete_compound_commands() {
+        let inputs = [
+            "if a; then b;",
+            "if a; then b; else",
+            "while a; do b;",
+            "for x in a",
+            "for x in a; do",
+            "case x in a) b;;",
+            "f()",
+        ];
+
+        for input in inputs {
+            assert!(
+                matches!(try_parse(input), Err(CustomError::UnexpectedEndOfInput)),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn compound_syntax_errors() {
+        let errors = [
+            ("if then", "then"),
+            ("if a; then fi", "fi"),
+            ("if a; fi", "fi"),
+            ("if a; then b; else fi", "fi"),
+            ("if a; then b; done", "done"),
+            ("while do b; done", "do"),
+            ("while a; do done", "done"),
+            ("until a; b; done", "done"),
+            ("for 1 in a; do b; done", "1"),
+            ("for x in a do b; done", "done"),
+            ("case x in a) b; c) d;; esac", ")"),
+            ("case x a) b;; esac", "a"),
+            ("f() )", ")"),
+            ("fi", "fi"),
+            ("done", "done"),
+            ("{ a; } b", "b"),
+        ];
+
+        for (input, token) in errors {
+            assert_eq!(
+                syntax_error(input),
+                format!("syntax error near unexpected token `{token}'"),
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn simple_command_redirections() {
+        let CommandNode::Simple(command) = only_command("> out echo 2>&1 hi <in") else {
+            panic!("not a simple command");
+        };
+        assert_eq!(command.words.len(), 2);
+
+        let redirections: Vec<(RawFd, RedirectionOperator, String)> = command
+            .redirects
+            .0
+            .iter()
+            .map(|redirection| {
+                let target = redirection.target.to_string();
+                (redirection.fd, redirection.operator.clone(), target)
+            })
+            .collect();
+        assert_eq!(
+            redirections,
+            [
+                (1, RedirectionOperator::Output, "out".to_owned()),
+                (2, RedirectionOperator::DuplicateOutput, "1".to_owned()),
+                (0, RedirectionOperator::Input, "in".to_owned()),
+            ]
+        );
+
+        let CommandNode::Simple(command) = only_command("> out") else {
+            panic!("not a simple command");
+        };
+        assert!(command.words.is_empty());
+    }
+
+    #[test]
+    fn compound_command_redirections() {
+        let CommandNode::Compound(CompoundCommand::Group(_), redirects) =
+            only_command("{ a; } > out 2>&1")
+        else {
+            panic!("not a group");
+        };
+        assert_eq!(redirects.0.len(), 2);
+
+        assert_eq!(
+            parsed("while read x; do a; done < in | b").to_string(),
+            "while read x; do a; done < in | b"
+        );
+        assert_eq!(parsed("(a) 3>> log").to_string(), "(a) 3>> log");
+    }
+
+    #[test]
+    fn here_document_redirections() {
+        let CommandNode::Simple(command) = only_command("cat <<EOF > out\nbody\nEOF") else {
+            panic!("not a simple command");
+        };
+        let [here_document, output] = command.redirects.0.as_slice() else {
+            panic!("wrong number of redirections");
+        };
+
+        assert_eq!(here_document.fd, 0);
+        assert!(matches!(
+            &here_document.operator,
+            RedirectionOperator::HereDocument { delimiter, .. } if delimiter == "EOF"
+        ));
+        assert_eq!(here_document.target.to_string(), "'body\n'");
+        assert_eq!(output.operator, RedirectionOperator::Output);
+
+        let CommandNode::Compound(_, redirects) =
+            only_command("if a; then cat; fi <<EOF\nbody\nEOF")
+        else {
+            panic!("not a compound command");
+        };
+        assert_eq!(redirects.0.len(), 1);
+
+        assert!(matches!(
+            try_parse("cat <<EOF\nbody"),
+            Err(CustomError::UnexpectedEndOfInput)
+        ));
+    }
+
+    #[test]
+    fn redirection_syntax_errors() {
+        let errors = [
+            ("echo >", "newline"),
+            ("echo > ;", ";"),
+            ("echo < | a", "|"),
+            ("cat <<\necho", "newline"),
+            ("cat << &&\necho", "&&"),
+            ("> out (a)", "("),
+            ("{ a; } > out b", "b"),
+        ];
+
+        for (input, token) in errors {
+            assert_eq!(
+                syntax_error(input),
+                format!("syntax error near unexpected token `{token}'"),
+                "{input:?}"
+            );
+        }
+    }
+}
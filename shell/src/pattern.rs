@@ -0,0 +1,364 @@
+use std::path::Path;
+
+/// Matches `text` against a shell pattern, where `*` matches any run of
+/// characters, `?` any single character, `[...]` any character in the set
+/// (`[!...]` or `[^...]` any character not in it) and a backslash makes the
+/// next character literal.
+pub fn pattern_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    matches_from(&pattern, &text)
+}
+
+/// Backtracks to the last `*` only: when a later character fails to match,
+/// that `*` takes one more character and matching resumes after it.
+fn matches_from(pattern: &[char], text: &[char]) -> bool {
+    let (mut pattern_index, mut text_index) = (0, 0);
+    // the pattern index just after the last `*` and the text it resumed at
+    let mut last_star = None;
+
+    loop {
+        if pattern.get(pattern_index) == Some(&'*') {
+            pattern_index += 1;
+            last_star = Some((pattern_index, text_index));
+            continue;
+        }
+
+        if pattern_index == pattern.len() && text_index == text.len() {
+            return true;
+        }
+
+        let matched = text
+            .get(text_index)
+            .and_then(|&character| match_one(&pattern[pattern_index..], character));
+        if let Some(length) = matched {
+            pattern_index += length;
+            text_index += 1;
+            continue;
+        }
+
+        match last_star {
+            Some((star_end, resumed)) if resumed < text.len() => {
+                last_star = Some((star_end, resumed + 1));
+                pattern_index = star_end;
+                text_index = resumed + 1;
+            }
+            _ => return false,
+        }
+    }
+}
+
+/// Matches one character against the pattern element at the start of
+/// `pattern`, returning how much of the pattern the element used.
+fn match_one(pattern: &[char], character: char) -> Option<usize> {
+    let (&first, rest) = pattern.split_first()?;
+
+    match first {
+        '?' => Some(1),
+        '[' => match match_bracket(rest, Some(character)) {
+            Some((true, length)) => Some(1 + length),
+            Some((false, _)) => None,
+            // an unclosed `[` is just a character
+            None => (character == '[').then_some(1),
+        },
+        '\\' if !rest.is_empty() => (rest[0] == character).then_some(2),
+        literal => (literal == character).then_some(1),
+    }
+}
+
+/// Matches a character against the bracket expression at the start of
+/// `pattern`, just after the `[`. Returns whether it matched and how much of
+/// the pattern the expression used, or `None` if there is no closing `]`.
+fn match_bracket(pattern: &[char], character: Option<char>) -> Option<(bool, usize)> {
+    let mut index = 0;
+    let negated = matches!(pattern.first(), Some('!') | Some('^'));
+    if negated {
+        index += 1;
+    }
+
+    let mut matched = false;
+    let mut first = true;
+
+    loop {
+        let current = *pattern.get(index)?;
+        if current == ']' && !first {
+            break;
+        }
+        first = false;
+
+        let (low, next) = match current {
+            '\\' => (*pattern.get(index + 1)?, index + 2),
+            current => (current, index + 1),
+        };
+
+        if pattern.get(next) == Some(&'-') && pattern.get(next + 1).is_some_and(|high| *high != ']')
+        {
+            let high = pattern[next + 1];
+            matched |= character.is_some_and(|character| (low..=high).contains(&character));
+            index = next + 2;
+        } else {
+            matched |= character == Some(low);
+            index = next;
+        }
+    }
+
+    Some((matched != negated, index + 1))
+}
+
+/// Expands a pattern into the sorted paths it matches. Each `/`-separated
+/// component is matched against directory entries, `**` matches any number of
+/// directories, and names starting with `.` are only matched by a pattern
+/// that starts with a literal `.`.
+pub fn glob(pattern: &str) -> Vec<String> {
+    let (mut candidates, components) = match pattern.strip_prefix('/') {
+        Some(rest) => (vec!["/".to_owned()], rest),
+        None => (vec![String::new()], pattern),
+    };
+
+    for component in components
+        .split('/')
+        .filter(|component| !component.is_empty())
+    {
+        candidates = if component == "**" {
+            candidates
+                .into_iter()
+                .flat_map(|candidate| {
+                    let mut directories = vec![candidate.clone()];
+                    directories.extend(subdirectories(&candidate));
+                    directories
+                })
+                .collect()
+        } else if has_wildcards(component) {
+            candidates
+                .into_iter()
+                .flat_map(|candidate| {
+                    matching_entries(&candidate, component)
+                        .into_iter()
+                        .map(move |name| join(&candidate, &name))
+                })
+                .collect()
+        } else {
+            let name = unescape(component);
+            candidates
+                .into_iter()
+                .map(|candidate| join(&candidate, &name))
+                .filter(|path| std::fs::symlink_metadata(path).is_ok())
+                .collect()
+        };
+    }
+
+    if pattern.ends_with('/') {
+        candidates = candidates
+            .into_iter()
+            .filter(|path| Path::new(path).is_dir())
+            .map(|path| path + "/")
+            .collect();
+    }
+
+    candidates.retain(|path| !path.is_empty());
+    candidates.sort();
+    candidates
+}
+
+/// Whether the pattern has an unescaped `*`, `?` or `[`.
+fn has_wildcards(pattern: &str) -> bool {
+    let mut characters = pattern.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => {
+                characters.next();
+            }
+            '*' | '?' | '[' => return true,
+            _ => (),
+        }
+    }
+
+    false
+}
+
+fn unescape(pattern: &str) -> String {
+    let mut text = String::new();
+    let mut characters = pattern.chars();
+
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => text.extend(characters.next()),
+            character => text.push(character),
+        }
+    }
+
+    text
+}
+
+fn join(directory: &str, name: &str) -> String {
+    if directory.is_empty() {
+        name.to_owned()
+    } else if directory.ends_with('/') {
+        format!("{directory}{name}")
+    } else {
+        format!("{directory}/{name}")
+    }
+}
+
+fn read_directory(directory: &str) -> Vec<(String, bool)> {
+    let path = if directory.is_empty() { "." } else { directory };
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return vec![];
+    };
+
+    entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let name = entry.file_name().into_string().ok()?;
+            // the entry's own type, so `**` never follows a symlink into a cycle
+            let is_directory = entry.file_type().ok()?.is_dir();
+            Some((name, is_directory))
+        })
+        .collect()
+}
+
+fn matching_entries(directory: &str, pattern: &str) -> Vec<String> {
+    let match_hidden = pattern.starts_with('.');
+
+    read_directory(directory)
+        .into_iter()
+        .map(|(name, _)| name)
+        .filter(|name| (match_hidden || !name.starts_with('.')) && pattern_matches(pattern, name))
+        .collect()
+}
+
+/// Every directory below `directory`, not descending into hidden ones.
+fn subdirectories(directory: &str) -> Vec<String> {
+    read_directory(directory)
+        .into_iter()
+        .filter(|(name, is_directory)| *is_directory && !name.starts_with('.'))
+        .flat_map(|(name, _)| {
+            let path = join(directory, &name);
+            let mut directories = subdirectories(&path);
+            directories.insert(0, path);
+            directories
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(pattern_matches("*", ""));
+        assert!(pattern_matches("*.rs", "main.rs"));
+        assert!(!pattern_matches("*.rs", "main.rs.bak"));
+        assert!(pattern_matches("a*b*c", "aXXbYYc"));
+        assert!(pattern_matches("?", "x"));
+        assert!(!pattern_matches("?", ""));
+        assert!(!pattern_matches("??", "x"));
+        assert!(pattern_matches("", ""));
+        assert!(!pattern_matches("", "x"));
+        assert!(pattern_matches("*a*", "bab"));
+        assert!(!pattern_matches("a*", "ba"));
+    }
+
+    #[test]
+    fn many_stars_do_not_backtrack_exponentially() {
+        let text = "a".repeat(100);
+        assert!(!pattern_matches("*a*a*a*a*a*a*a*a*a*a*b", &text));
+        assert!(pattern_matches("*a*a*a*a*a*a*a*a*a*a", &text));
+    }
+
+    #[test]
+    fn bracket_expressions() {
+        assert!(pattern_matches("[abc]", "b"));
+        assert!(!pattern_matches("[abc]", "d"));
+        assert!(pattern_matches("[a-c]x", "cx"));
+        assert!(!pattern_matches("[a-c]", "-"));
+        assert!(pattern_matches("[a-]", "-"));
+        assert!(pattern_matches("[]]", "]"));
+        assert!(pattern_matches("[0-9][0-9]", "42"));
+        assert!(!pattern_matches("[abc]", ""));
+    }
+
+    #[test]
+    fn negated_bracket_expressions() {
+        assert!(pattern_matches("[!abc]", "d"));
+        assert!(!pattern_matches("[!abc]", "a"));
+        assert!(pattern_matches("[^a-c]", "z"));
+        assert!(!pattern_matches("[^a-c]", "b"));
+        assert!(pattern_matches("[!]]", "a"));
+        assert!(!pattern_matches("[!]]", "]"));
+    }
+
+    #[test]
+    fn unclosed_bracket_is_literal() {
+        assert!(pattern_matches("[abc", "[abc"));
+        assert!(!pattern_matches("[abc", "a"));
+    }
+
+    #[test]
+    fn escapes() {
+        assert!(pattern_matches(r"\*", "*"));
+        assert!(!pattern_matches(r"\*", "x"));
+        assert!(pattern_matches(r"a\?", "a?"));
+        assert!(!pattern_matches(r"a\?", "ab"));
+        assert!(pattern_matches(r"[\]]", "]"));
+        assert!(pattern_matches(r"[a\-z]", "-"));
+        assert!(!pattern_matches(r"[a\-z]", "m"));
+        assert!(pattern_matches("\\", "\\"));
+    }
+
+    #[test]
+    fn wildcard_detection() {
+        assert!(has_wildcards("*.rs"));
+        assert!(has_wildcards("a[bc]"));
+        assert!(!has_wildcards(r"\*.rs"));
+        assert!(!has_wildcards("plain"));
+        assert_eq!(unescape(r"\*a\\b"), r"*a\b");
+    }
+
+    #[test]
+    fn glob_matches_directory_entries() {
+        let directory = std::env::temp_dir().join(format!("shell-glob-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("sub")).unwrap();
+        for name in ["a.txt", "b.txt", "c.rs", ".hidden.txt", "sub/d.txt"] {
+            std::fs::write(directory.join(name), "").unwrap();
+        }
+        let root = directory.to_string_lossy();
+        let names = |pattern: &str| -> Vec<String> {
+            glob(&format!("{root}/{pattern}"))
+                .into_iter()
+                .map(|path| path[root.len() + 1..].to_owned())
+                .collect()
+        };
+
+        assert_eq!(names("*.txt"), ["a.txt", "b.txt"]);
+        assert_eq!(names(".*.txt"), [".hidden.txt"]);
+        assert_eq!(names("[!a]*"), ["b.txt", "c.rs", "sub"]);
+        assert_eq!(names("*/"), ["sub/"]);
+        assert_eq!(names("**/*.txt"), ["a.txt", "b.txt", "sub/d.txt"]);
+        assert_eq!(names("a.txt"), ["a.txt"]);
+        assert!(names("*.md").is_empty());
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+
+    #[test]
+    fn double_star_does_not_follow_symlinks() {
+        let directory =
+            std::env::temp_dir().join(format!("shell-glob-links-{}", std::process::id()));
+        std::fs::create_dir_all(directory.join("d")).unwrap();
+        std::fs::write(directory.join("d/f.txt"), "").unwrap();
+        std::os::unix::fs::symlink("..", directory.join("d/up")).unwrap();
+        let root = directory.to_string_lossy();
+
+        let paths: Vec<String> = glob(&format!("{root}/**/*.txt"))
+            .into_iter()
+            .map(|path| path[root.len() + 1..].to_owned())
+            .collect();
+        assert_eq!(paths, ["d/f.txt"]);
+
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{Context, Result};
+use std::{
+    fmt,
+    io::{self, PipeReader, PipeWriter},
+};
+
+use crate::{
+    command_node::CommandNode,
+    jobs::Job,
+    process::{Fork, duplicate_onto, exit_child, fork},
+    shell_state::ShellState,
+    traps::run_subshell,
+};
+
+/// Commands connected by `|`, optionally preceded by `!` to invert the exit
+/// status.
+#[derive(Debug)]
+pub struct Pipeline {
+    pub stages: Vec<CommandNode>,
+    pub negated: bool,
+}
+
+impl Pipeline {
+    /// Runs the pipeline and returns the exit status of its last stage. A
+    /// single foreground command runs in the shell process so builtins like
+    /// `cd` can change the shell's state, otherwise every stage gets its own
+    /// process, connected to its neighbours by pipes and sharing a process
+    /// group so the whole job can be stopped, resumed or signalled together.
+    pub fn run(&self, state: &mut ShellState, background: bool) -> Result<i32> {
+        if !background && let [stage] = self.stages.as_slice() {
+            let status = stage.run(state, false)?;
+            return Ok(self.apply_negation(status));
+        }
+
+        let foreground = !background;
+        let stage_count = self.stages.len();
+        let mut child_ids = vec![];
+        let mut pgid = 0;
+        let mut previous_reader: Option<PipeReader> = None;
+
+        for (index, stage) in self.stages.iter().enumerate() {
+            let (reader, writer) = if index + 1 < stage_count {
+                let (reader, writer) = io::pipe().context("creating pipe between commands")?;
+                (Some(reader), Some(writer))
+            } else {
+                (None, None)
+            };
+
+            match fork()? {
+                Fork::Child => {
+                    drop(reader);
+                    state.jobs.enter_job(pgid, foreground);
+                    let status = run_subshell(state, |state| {
+                        run_stage(stage, previous_reader, writer, state)
+                    })
+                    .unwrap_or_else(|error| {
+                        eprintln!("shell: {error:#}");
+                        1
+                    });
+                    exit_child(status);
+                }
+                Fork::Parent(pid) => {
+                    if pgid == 0 {
+                        pgid = pid;
+                    }
+                    state.jobs.adopt(pid, pgid);
+                    child_ids.push(pid);
+                }
+            }
+
+            previous_reader = reader;
+        }
+
+        let job = Job::new(pgid, child_ids, self.to_string());
+        let status = launch(state, job, foreground)?;
+
+        Ok(if foreground {
+            self.apply_negation(status)
+        } else {
+            status
+        })
+    }
+
+    fn apply_negation(&self, status: i32) -> i32 {
+        match (self.negated, status) {
+            (false, status) => status,
+            (true, 0) => 1,
+            (true, _) => 0,
+        }
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.negated {
+            write!(f, "! ")?;
+        }
+
+        let stages: Vec<String> = self.stages.iter().map(CommandNode::to_string).collect();
+        write!(f, "{}", stages.join(" | "))
+    }
+}
+
+/// Forks a process running `body` as a job of its own, such as a subshell or
+/// a backgrounded `&&` list, and either waits for it or leaves it running in
+/// the background.
+pub fn run_in_child(
+    state: &mut ShellState,
+    foreground: bool,
+    text: String,
+    body: impl FnOnce(&mut ShellState) -> Result<i32>,
+) -> Result<i32> {
+    match fork()? {
+        Fork::Child => {
+            state.jobs.enter_job(0, foreground);
+            let status = run_subshell(state, body).unwrap_or_else(|error| {
+                eprintln!("shell: {error:#}");
+                1
+            });
+            exit_child(status);
+        }
+        Fork::Parent(pid) => {
+            state.jobs.adopt(pid, pid);
+            launch(state, Job::new(pid, vec![pid], text), foreground)
+        }
+    }
+}
+
+/// Waits for a freshly started job, or records it as a background job and
+/// reports its id.
+fn launch(state: &mut ShellState, job: Job, foreground: bool) -> Result<i32> {
+    if foreground {
+        return state.jobs.wait_foreground(job);
+    }
+
+    let last_pid = job
+        .processes
+        .last()
+        .map(|process| process.pid)
+        .unwrap_or_default();
+    state.last_background_pid = Some(last_pid);
+    let id = state.jobs.add(job);
+    if state.jobs.job_control {
+        eprintln!("[{id}] {last_pid}");
+    }
+
+    Ok(0)
+}
+
+fn run_stage(
+    stage: &CommandNode,
+    stdin: Option<PipeReader>,
+    stdout: Option<PipeWriter>,
+    state: &mut ShellState,
+) -> Result<i32> {
+    if let Some(reader) = stdin {
+        duplicate_onto(&reader, libc::STDIN_FILENO)?;
+    }
+    if let Some(writer) = stdout {
+        duplicate_onto(&writer, libc::STDOUT_FILENO)?;
+    }
+
+    stage.run(state, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{input_parser::parse_input, parser::parse};
+
+    fn run(input: &str) -> i32 {
+        let list = parse(parse_input(input.to_owned()).unwrap()).unwrap();
+        list.run(&mut ShellState::new(false).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn stages_are_connected_by_pipes() {
+        let file = std::env::temp_dir().join(format!("shell-pipeline-{}", std::process::id()));
+        let output = |input: &str| {
+            assert_eq!(run(&format!("{input} > {}", file.display())), 0);
+            std::fs::read_to_string(&file).unwrap()
+        };
+
+        assert_eq!(output("printf 'hello\\n' | tr a-z A-Z"), "HELLO\n");
+        assert_eq!(output("printf 'b\\na\\n' | sort | head -n 1"), "a\n");
+
+        std::fs::remove_file(&file).unwrap();
+    }
+
+    #[test]
+    fn status_is_that_of_the_last_stage() {
+        assert_eq!(run("true | false"), 1);
+        assert_eq!(run("false | true"), 0);
+        assert_eq!(run("echo hi | sh -c 'exit 3'"), 3);
+        assert_eq!(run("echo hi | no-such-command-here"), 127);
+    }
+
+    #[test]
+    fn negation() {
+        assert_eq!(run("! true"), 1);
+        assert_eq!(run("! false | sh -c 'exit 3'"), 0);
+        assert_eq!(run("! false"), 0);
+    }
+
+    #[test]
+    fn single_commands_report_their_status() {
+        assert_eq!(run("true"), 0);
+        assert_eq!(run("sh -c 'exit 5'"), 5);
+        assert_eq!(run("ls /no/such/directory 2> /dev/null"), 2);
+        assert_eq!(run("no-such-command-here"), 127);
+    }
+}
@@ -0,0 +1,388 @@
+use anyhow::{Context, Result};
+use std::{
+    io::{self, Write},
+    os::fd::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+};
+
+/// Set from the SIGCHLD handler so the prompt loop knows to reap children.
+pub static CHILD_STATUS_CHANGED: AtomicBool = AtomicBool::new(false);
+
+/// One bit per signal caught by the shell that hasn't been dealt with yet.
+static PENDING_SIGNALS: AtomicU64 = AtomicU64::new(0);
+
+/// Signals the shell ignores while it has job control, and which children
+/// must get back before they run.
+const JOB_CONTROL_SIGNALS: [libc::c_int; 3] = [libc::SIGTSTP, libc::SIGTTIN, libc::SIGTTOU];
+
+pub const SIGNALS: [(&str, libc::c_int); 18] = [
+    ("HUP", libc::SIGHUP),
+    ("INT", libc::SIGINT),
+    ("QUIT", libc::SIGQUIT),
+    ("ABRT", libc::SIGABRT),
+    ("KILL", libc::SIGKILL),
+    ("USR1", libc::SIGUSR1),
+    ("SEGV", libc::SIGSEGV),
+    ("USR2", libc::SIGUSR2),
+    ("PIPE", libc::SIGPIPE),
+    ("ALRM", libc::SIGALRM),
+    ("TERM", libc::SIGTERM),
+    ("CHLD", libc::SIGCHLD),
+    ("CONT", libc::SIGCONT),
+    ("STOP", libc::SIGSTOP),
+    ("TSTP", libc::SIGTSTP),
+    ("TTIN", libc::SIGTTIN),
+    ("TTOU", libc::SIGTTOU),
+    ("WINCH", libc::SIGWINCH),
+];
+
+/// Accepts `TERM`, `SIGTERM` or `15`.
+pub fn signal_from_name(name: &str) -> Option<libc::c_int> {
+    if let Ok(number) = name.parse() {
+        return Some(number);
+    }
+
+    let name = name.to_ascii_uppercase();
+    let name = name.strip_prefix("SIG").unwrap_or(&name);
+
+    SIGNALS
+        .iter()
+        .find(|(signal_name, _)| *signal_name == name)
+        .map(|(_, signal)| *signal)
+}
+
+pub fn signal_name(signal: libc::c_int) -> Option<&'static str> {
+    SIGNALS
+        .iter()
+        .find(|(_, number)| *number == signal)
+        .map(|(name, _)| *name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProcessStatus {
+    Running,
+    Stopped(libc::c_int),
+    Exited(i32),
+    Signaled(libc::c_int),
+}
+
+impl ProcessStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Exited(_) | Self::Signaled(_))
+    }
+
+    /// The status `$?` reports: the exit code, or 128 plus the signal that
+    /// killed or stopped the process.
+    pub fn exit_status(&self) -> i32 {
+        match self {
+            Self::Exited(code) => *code,
+            Self::Stopped(signal) | Self::Signaled(signal) => 128 + signal,
+            Self::Running => 0,
+        }
+    }
+}
+
+pub enum Fork {
+    Parent(libc::pid_t),
+    Child,
+}
+
+/// Forks the shell. Signals stay blocked in the child until
+/// `reset_signal_dispositions`, so one arriving before the child has put
+/// back the default handlers isn't swallowed by the shell's.
+pub fn fork() -> Result<Fork> {
+    // anything still buffered would otherwise be written by both processes
+    io::stdout().flush().context("flushing standard out before fork")?;
+    io::stderr().flush().context("flushing standard error before fork")?;
+
+    let mut all_signals: libc::sigset_t = unsafe { std::mem::zeroed() };
+    let mut previous_mask: libc::sigset_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::sigfillset(&mut all_signals);
+        libc::sigprocmask(libc::SIG_BLOCK, &all_signals, &mut previous_mask);
+    }
+
+    let pid = unsafe { libc::fork() };
+    if pid != 0 {
+        unsafe { libc::sigprocmask(libc::SIG_SETMASK, &previous_mask, std::ptr::null_mut()) };
+    }
+
+    match pid {
+        -1 => Err(io::Error::last_os_error()).context("forking the shell"),
+        0 => Ok(Fork::Child),
+        pid => Ok(Fork::Parent(pid)),
+    }
+}
+
+pub fn duplicate_onto(source: &impl AsRawFd, target: RawFd) -> Result<()> {
+    // dup2 does nothing when the descriptors are the same, not even clearing
+    // close-on-exec, so do that here so that commands still inherit it
+    if source.as_raw_fd() == target {
+        if unsafe { libc::fcntl(target, libc::F_SETFD, 0) } == -1 {
+            return Err(io::Error::last_os_error())
+                .with_context(|| format!("duplicating file descriptor onto {target}"));
+        }
+        return Ok(());
+    }
+
+    if unsafe { libc::dup2(source.as_raw_fd(), target) } == -1 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("duplicating file descriptor onto {target}"));
+    }
+
+    Ok(())
+}
+
+/// Blocks until `pid` exits, or also until it stops when `until_stopped` is
+/// set.
+pub fn wait_for(pid: libc::pid_t, until_stopped: bool) -> Result<ProcessStatus> {
+    let options = if until_stopped { libc::WUNTRACED } else { 0 };
+    let mut status = 0;
+
+    loop {
+        if unsafe { libc::waitpid(pid, &mut status, options) } != -1 {
+            break;
+        }
+
+        let error = io::Error::last_os_error();
+        if error.kind() != io::ErrorKind::Interrupted {
+            return Err(error).context("waiting for child process");
+        }
+    }
+
+    Ok(process_status(status))
+}
+
+/// Reaps one child whose state changed without blocking, if there is one.
+pub fn try_wait_any() -> Option<(libc::pid_t, ProcessStatus)> {
+    let mut status = 0;
+    let options = libc::WNOHANG | libc::WUNTRACED | libc::WCONTINUED;
+    let pid = unsafe { libc::waitpid(-1, &mut status, options) };
+
+    (pid > 0).then(|| (pid, process_status(status)))
+}
+
+fn process_status(status: libc::c_int) -> ProcessStatus {
+    if libc::WIFSTOPPED(status) {
+        ProcessStatus::Stopped(libc::WSTOPSIG(status))
+    } else if libc::WIFCONTINUED(status) {
+        ProcessStatus::Running
+    } else if libc::WIFSIGNALED(status) {
+        ProcessStatus::Signaled(libc::WTERMSIG(status))
+    } else {
+        ProcessStatus::Exited(libc::WEXITSTATUS(status))
+    }
+}
+
+pub fn send_signal(pid: libc::pid_t, signal: libc::c_int) -> Result<()> {
+    if unsafe { libc::kill(pid, signal) } == -1 {
+        return Err(io::Error::last_os_error()).with_context(|| format!("({pid})"));
+    }
+
+    Ok(())
+}
+
+pub fn set_process_group(pid: libc::pid_t, pgid: libc::pid_t) {
+    // fails harmlessly when the child has already exec'd, since the child
+    // makes the same call itself
+    unsafe { libc::setpgid(pid, pgid) };
+}
+
+pub fn give_terminal_to(pgid: libc::pid_t) {
+    unsafe { libc::tcsetpgrp(libc::STDIN_FILENO, pgid) };
+}
+
+pub fn is_interactive() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+/// Puts the shell in its own process group in the foreground of the
+/// terminal and ignores the signals used to stop it, so that only the jobs
+/// it runs can be suspended.
+pub fn take_control_of_terminal() -> libc::pid_t {
+    unsafe {
+        // wait until we are started in the foreground
+        loop {
+            let pgid = libc::getpgrp();
+            if libc::tcgetpgrp(libc::STDIN_FILENO) == pgid {
+                break;
+            }
+            libc::kill(-pgid, libc::SIGTTIN);
+        }
+
+        for signal in JOB_CONTROL_SIGNALS {
+            libc::signal(signal, libc::SIG_IGN);
+        }
+
+        let pid = libc::getpid();
+        libc::setpgid(pid, pid);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pid);
+
+        pid
+    }
+}
+
+extern "C" fn handle_child_signal(_signal: libc::c_int) {
+    CHILD_STATUS_CHANGED.store(true, Ordering::SeqCst);
+}
+
+pub fn install_child_handler() {
+    set_handler(
+        libc::SIGCHLD,
+        handle_child_signal as *const () as libc::sighandler_t,
+    );
+}
+
+extern "C" fn handle_signal(signal: libc::c_int) {
+    if signal == libc::SIGCHLD {
+        CHILD_STATUS_CHANGED.store(true, Ordering::SeqCst);
+    }
+    mark_signal_pending(signal);
+}
+
+/// Catches `signal` so that it is reported by `take_pending_signals` rather
+/// than acted on straight away.
+pub fn catch_signal(signal: libc::c_int) {
+    set_handler(signal, handle_signal as *const () as libc::sighandler_t);
+}
+
+pub fn ignore_signal(signal: libc::c_int) {
+    set_handler(signal, libc::SIG_IGN);
+}
+
+pub fn default_signal(signal: libc::c_int) {
+    set_handler(signal, libc::SIG_DFL);
+}
+
+fn set_handler(signal: libc::c_int, handler: libc::sighandler_t) {
+    unsafe {
+        let mut action: libc::sigaction = std::mem::zeroed();
+        action.sa_sigaction = handler;
+        action.sa_flags = libc::SA_RESTART;
+        libc::sigemptyset(&mut action.sa_mask);
+        libc::sigaction(signal, &action, std::ptr::null_mut());
+    }
+}
+
+fn is_caught(signal: libc::c_int) -> bool {
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    let queried = unsafe { libc::sigaction(signal, std::ptr::null(), &mut action) } == 0;
+
+    queried && action.sa_sigaction == handle_signal as *const () as libc::sighandler_t
+}
+
+/// Records `signal` as if it had just been caught, used when a foreground
+/// job in its own process group was interrupted instead of the shell.
+pub fn mark_signal_pending(signal: libc::c_int) {
+    PENDING_SIGNALS.fetch_or(1 << signal, Ordering::SeqCst);
+}
+
+/// The signals caught since the last call, lowest numbered first.
+pub fn take_pending_signals() -> Vec<libc::c_int> {
+    let pending = PENDING_SIGNALS.swap(0, Ordering::SeqCst);
+
+    (1..64)
+        .filter(|signal| pending & (1 << signal) != 0)
+        .collect()
+}
+
+/// Undoes the shell's own signal setup in a freshly forked child. Caught
+/// signals go back to their default action, while ignored ones stay
+/// ignored.
+pub fn reset_signal_dispositions() {
+    for signal in JOB_CONTROL_SIGNALS.into_iter().chain([libc::SIGCHLD]) {
+        default_signal(signal);
+    }
+    for (_, signal) in SIGNALS {
+        if is_caught(signal) {
+            default_signal(signal);
+        }
+    }
+
+    PENDING_SIGNALS.store(0, Ordering::SeqCst);
+    unblock_signals();
+}
+
+fn unblock_signals() {
+    let mut all_signals: libc::sigset_t = unsafe { std::mem::zeroed() };
+    unsafe {
+        libc::sigfillset(&mut all_signals);
+        libc::sigprocmask(libc::SIG_UNBLOCK, &all_signals, std::ptr::null_mut());
+    }
+}
+
+pub fn exit_child(code: i32) -> ! {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+
+    unsafe { libc::_exit(code) }
+}
+
+/// Copies of the descriptors replaced by redirections, restored on drop so
+/// builtins can be redirected without affecting the shell itself.
+#[derive(Default)]
+pub struct SavedDescriptors {
+    saved: Vec<(RawFd, Option<OwnedFd>)>,
+}
+
+impl SavedDescriptors {
+    pub fn redirect(&mut self, source: &impl AsRawFd, target: RawFd) -> Result<()> {
+        self.save(target);
+        duplicate_onto(source, target)
+    }
+
+    /// Closes `target` until the descriptors are restored, as `>&-` does.
+    pub fn close(&mut self, target: RawFd) -> Result<()> {
+        self.save(target);
+        unsafe { libc::close(target) };
+
+        Ok(())
+    }
+
+    fn save(&mut self, target: RawFd) {
+        let copy = unsafe { libc::fcntl(target, libc::F_DUPFD_CLOEXEC, 10) };
+        let copy = if copy == -1 {
+            None
+        } else {
+            Some(unsafe { OwnedFd::from_raw_fd(copy) })
+        };
+
+        self.saved.push((target, copy));
+    }
+}
+
+impl Drop for SavedDescriptors {
+    fn drop(&mut self) {
+        let _ = io::stdout().flush();
+        let _ = io::stderr().flush();
+
+        for (target, copy) in self.saved.drain(..).rev() {
+            match copy {
+                Some(copy) => unsafe {
+                    libc::dup2(copy.as_raw_fd(), target);
+                },
+                None => unsafe {
+                    libc::close(target);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_statuses() {
+        assert_eq!(ProcessStatus::Exited(0).exit_status(), 0);
+        assert_eq!(ProcessStatus::Exited(42).exit_status(), 42);
+        assert_eq!(ProcessStatus::Signaled(libc::SIGINT).exit_status(), 130);
+        assert_eq!(ProcessStatus::Signaled(libc::SIGKILL).exit_status(), 137);
+        assert_eq!(
+            ProcessStatus::Stopped(libc::SIGTSTP).exit_status(),
+            128 + libc::SIGTSTP
+        );
+        assert_eq!(ProcessStatus::Running.exit_status(), 0);
+    }
+}
@@ -0,0 +1,203 @@
+use std::{
+    ffi::CStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{builtin_commands::change_directory::current_directory, shell_state::ShellState};
+
+/// The prompt from the variable `name`, `PS1` or `PS2`, with its escapes
+/// replaced. Unset variables give `$ ` and `> `.
+pub fn prompt(name: &str, state: &ShellState) -> String {
+    let default = if name == "PS2" { "> " } else { "$ " };
+    let template = state
+        .get_variable(name)
+        .unwrap_or_else(|| default.to_owned());
+
+    expand_prompt(&template, state)
+}
+
+/// Replaces the backslash escapes in a prompt:
+///
+/// - `\u` the user name, `\h` the host name up to the first `.`, `\H` all of it
+/// - `\w` the working directory with the home directory as `~`, `\W` its last
+///   component
+/// - `\?` the exit status of the last command
+/// - `\g` the current git branch, or nothing outside a repository
+/// - `\$` `#` for root and `$` for everyone else
+/// - `\n` a newline, `\e` an escape character and `\\` a backslash
+/// - `\[` and `\]`, which only mark where non-printing sequences start and
+///   end, are dropped
+fn expand_prompt(template: &str, state: &ShellState) -> String {
+    let mut prompt = String::new();
+    let mut characters = template.chars();
+
+    while let Some(character) = characters.next() {
+        if character != '\\' {
+            prompt.push(character);
+            continue;
+        }
+
+        match characters.next() {
+            Some('u') => prompt.push_str(&user_name(state)),
+            Some('h') => prompt.push_str(host_name().split('.').next().unwrap_or_default()),
+            Some('H') => prompt.push_str(&host_name()),
+            Some('w') => prompt.push_str(&working_directory(state, false)),
+            Some('W') => prompt.push_str(&working_directory(state, true)),
+            Some('?') => prompt.push_str(&state.last_exit_status.to_string()),
+            Some('g') => prompt.push_str(&git_branch().unwrap_or_default()),
+            Some('$') => prompt.push(if is_root() { '#' } else { '$' }),
+            Some('n') => prompt.push('\n'),
+            Some('e') => prompt.push('\x1b'),
+            Some('\\') => prompt.push('\\'),
+            Some('[' | ']') => (),
+            Some(other) => {
+                prompt.push('\\');
+                prompt.push(other);
+            }
+            None => prompt.push('\\'),
+        }
+    }
+
+    prompt
+}
+
+fn user_name(state: &ShellState) -> String {
+    if let Some(user) = state.get_variable("USER") {
+        return user;
+    }
+
+    let entry = unsafe { libc::getpwuid(libc::geteuid()) };
+    if entry.is_null() {
+        return String::new();
+    }
+
+    unsafe { CStr::from_ptr((*entry).pw_name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+fn host_name() -> String {
+    let mut buffer = [0 as libc::c_char; 256];
+    if unsafe { libc::gethostname(buffer.as_mut_ptr(), buffer.len()) } == -1 {
+        return String::new();
+    }
+
+    unsafe { CStr::from_ptr(buffer.as_ptr()) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+fn working_directory(state: &ShellState, last_component: bool) -> String {
+    let directory = current_directory(state);
+    let home = std::env::home_dir().filter(|home| home != Path::new("/"));
+
+    abbreviate_directory(&directory, home.as_deref(), last_component)
+}
+
+/// `directory` as `\w` shows it, or as `\W` with `last_component`, where
+/// the home directory and anything below it start with `~`.
+fn abbreviate_directory(directory: &Path, home: Option<&Path>, last_component: bool) -> String {
+    if home == Some(directory) {
+        return "~".to_owned();
+    }
+    if last_component {
+        return directory.file_name().map_or_else(
+            || "/".to_owned(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+    }
+
+    match home.and_then(|home| directory.strip_prefix(home).ok()) {
+        Some(relative) => format!("~/{}", relative.display()),
+        None => directory.display().to_string(),
+    }
+}
+
+/// The branch checked out in the repository containing the working
+/// directory, or the abbreviated commit when the head is detached.
+fn git_branch() -> Option<String> {
+    let head = fs::read_to_string(git_directory()?.join("HEAD")).ok()?;
+    let head = head.trim();
+
+    match head.strip_prefix("ref: ") {
+        Some(reference) => Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_owned(),
+        ),
+        None => Some(head.chars().take(7).collect()),
+    }
+}
+
+/// Finds the `.git` directory of the working directory's repository,
+/// following the `gitdir:` file used by worktrees and submodules.
+fn git_directory() -> Option<PathBuf> {
+    let directory = std::env::current_dir().ok()?;
+
+    for ancestor in directory.ancestors() {
+        let git = ancestor.join(".git");
+
+        if git.is_dir() {
+            return Some(git);
+        }
+        if let Ok(contents) = fs::read_to_string(&git) {
+            let path = contents.trim().strip_prefix("gitdir: ")?;
+            return Some(ancestor.join(path));
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes() {
+        let mut state = ShellState::new(false).unwrap();
+        state.set_variable("USER", "tester".to_owned());
+        state.last_exit_status = 3;
+
+        let host = host_name();
+        let short_host = host.split('.').next().unwrap_or_default();
+        let dollar = if is_root() { '#' } else { '$' };
+
+        assert_eq!(
+            expand_prompt(r"\u@\h \H \? \$ \\ \[\e[1m\]x\n> ", &state),
+            format!("tester@{short_host} {host} 3 {dollar} \\ \x1b[1mx\n> ")
+        );
+        assert_eq!(expand_prompt(r"\q trailing \", &state), r"\q trailing \");
+
+        state.set_variable("PS2", "more: ".to_owned());
+        assert_eq!(prompt("PS2", &state), "more: ");
+    }
+
+    #[test]
+    fn working_directory_abbreviates_home() {
+        let home = Some(Path::new("/home/me"));
+        let cases = [
+            ("/home/me", "~", "~"),
+            ("/home/me/src/shell", "~/src/shell", "shell"),
+            ("/home/meat", "/home/meat", "meat"),
+            ("/usr/bin", "/usr/bin", "bin"),
+            ("/", "/", "/"),
+        ];
+
+        for (directory, full, last) in cases {
+            let directory = Path::new(directory);
+            assert_eq!(abbreviate_directory(directory, home, false), full);
+            assert_eq!(abbreviate_directory(directory, home, true), last);
+        }
+        assert_eq!(
+            abbreviate_directory(Path::new("/home/me"), None, false),
+            "/home/me"
+        );
+    }
+}
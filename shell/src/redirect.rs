@@ -0,0 +1,181 @@
+use anyhow::{Context, Result, bail};
+use std::{
+    fmt,
+    fs::File,
+    io::{self, Seek, Write},
+    os::fd::{AsRawFd, FromRawFd, RawFd},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use crate::{
+    errors::{CustomError, io_error_message},
+    expansion::expand_word_to_string,
+    input_parser::{RedirectionOperator, Word},
+    process::SavedDescriptors,
+    shell_state::ShellState,
+};
+
+/// The redirections of a command, applied in the order they were written so
+/// that `> file 2>&1` and `2>&1 > file` behave differently.
+#[derive(Debug, Default)]
+pub struct Redirects(pub Vec<Redirection>);
+
+#[derive(Debug)]
+pub struct Redirection {
+    pub fd: RawFd,
+    pub operator: RedirectionOperator,
+    pub target: Word,
+}
+
+impl Redirection {
+    /// Builds a redirection from an operator, the file descriptor written
+    /// before it and the word after it.
+    pub fn new(fd: Option<RawFd>, operator: RedirectionOperator, target: Word) -> Self {
+        let default_fd = match operator {
+            RedirectionOperator::Input
+            | RedirectionOperator::DuplicateInput
+            | RedirectionOperator::HereDocument { .. }
+            | RedirectionOperator::HereString => libc::STDIN_FILENO,
+            _ => libc::STDOUT_FILENO,
+        };
+
+        Self {
+            fd: fd.unwrap_or(default_fd),
+            operator,
+            target,
+        }
+    }
+
+    fn apply(&self, state: &mut ShellState, saved: &mut SavedDescriptors) -> Result<()> {
+        let target = expand_word_to_string(&self.target, state)?;
+
+        let open_error =
+            |error: io::Error| CustomError::Redirection(target.clone(), io_error_message(&error));
+
+        let file = match &self.operator {
+            RedirectionOperator::Input => File::open(&target).map_err(open_error)?,
+            RedirectionOperator::Output => File::create(&target).map_err(open_error)?,
+            RedirectionOperator::Append => File::options()
+                .create(true)
+                .append(true)
+                .open(&target)
+                .map_err(open_error)?,
+            RedirectionOperator::OutputAndError => {
+                let file = move_clear_of(File::create(&target).map_err(open_error)?, self.fd)?;
+                saved.redirect(&file, libc::STDOUT_FILENO)?;
+                return saved.redirect(&file, libc::STDERR_FILENO);
+            }
+            RedirectionOperator::AppendOutputAndError => {
+                let file = File::options()
+                    .create(true)
+                    .append(true)
+                    .open(&target)
+                    .map_err(open_error)?;
+                let file = move_clear_of(file, self.fd)?;
+                saved.redirect(&file, libc::STDOUT_FILENO)?;
+                return saved.redirect(&file, libc::STDERR_FILENO);
+            }
+            RedirectionOperator::DuplicateInput | RedirectionOperator::DuplicateOutput => {
+                if target == "-" {
+                    return saved.close(self.fd);
+                }
+
+                let Ok(source) = target.parse::<RawFd>() else {
+                    bail!(CustomError::AmbiguousRedirect(target));
+                };
+                return saved
+                    .redirect(&source, self.fd)
+                    .map_err(|_| CustomError::BadFileDescriptor(target).into());
+            }
+            RedirectionOperator::HereDocument { .. } => {
+                here_document_file(&target).context("writing here-document")?
+            }
+            RedirectionOperator::HereString => {
+                here_document_file(&(target + "\n")).context("writing here-string")?
+            }
+        };
+
+        saved.redirect(&move_clear_of(file, self.fd)?, self.fd)
+    }
+}
+
+/// Moves a newly opened file to a descriptor of 10 or above other than
+/// `target`. Opening takes the lowest free descriptor, which is `target`
+/// itself when it was closed, and saving `target` would then keep the file
+/// open after the command instead of restoring the closed descriptor.
+fn move_clear_of(file: File, target: RawFd) -> Result<File> {
+    let minimum = if target >= 10 { target + 1 } else { 10 };
+    let fd = unsafe { libc::fcntl(file.as_raw_fd(), libc::F_DUPFD_CLOEXEC, minimum) };
+    if fd == -1 {
+        return Err(io::Error::last_os_error()).context("moving file descriptor");
+    }
+
+    Ok(unsafe { File::from_raw_fd(fd) })
+}
+
+impl Redirects {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Applies the redirections until the returned descriptors are dropped.
+    pub fn apply(&self, state: &mut ShellState) -> Result<SavedDescriptors> {
+        let mut saved = SavedDescriptors::default();
+
+        for redirection in &self.0 {
+            redirection.apply(state, &mut saved)?;
+        }
+
+        Ok(saved)
+    }
+}
+
+impl fmt::Display for Redirects {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for redirection in &self.0 {
+            write!(f, " {redirection}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Redirection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let default_fd = Redirection::new(None, self.operator.clone(), Word::default()).fd;
+        if self.fd != default_fd {
+            write!(f, "{}", self.fd)?;
+        }
+
+        match &self.operator {
+            RedirectionOperator::HereDocument {
+                strip_tabs,
+                delimiter,
+            } => write!(f, "{}{delimiter}", if *strip_tabs { "<<-" } else { "<<" }),
+            operator => write!(f, "{operator} {}", self.target),
+        }
+    }
+}
+
+/// Writes here-document text to an unlinked temporary file and returns it
+/// positioned at the start, ready to be read as standard input.
+fn here_document_file(text: &str) -> std::io::Result<File> {
+    static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "shell-here-document-{}-{}",
+        std::process::id(),
+        COUNT.fetch_add(1, Ordering::Relaxed)
+    ));
+    let mut file = File::options()
+        .read(true)
+        .write(true)
+        .create_new(true)
+        .open(&path)?;
+    std::fs::remove_file(&path)?;
+
+    file.write_all(text.as_bytes())?;
+    file.rewind()?;
+
+    Ok(file)
+}
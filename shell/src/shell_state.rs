@@ -0,0 +1,259 @@
+use anyhow::Result;
+use std::{collections::HashMap, path::PathBuf, rc::Rc};
+
+use crate::{
+    builtin_commands::change_directory::current_directory,
+    command_hash::CommandHash,
+    command_node::CommandNode,
+    errors::CustomError,
+    history::{DEFAULT_SIZE, History},
+    jobs::JobTable,
+    utils::split_path,
+};
+
+#[derive(Debug, Clone)]
+pub struct Variable {
+    value: String,
+    exported: bool,
+}
+
+/// Everything the shell remembers between commands.
+#[derive(Debug)]
+pub struct ShellState {
+    pub last_exit_status: i32,
+    pub should_exit: bool,
+    pub history: History,
+    pub jobs: JobTable,
+    pub last_background_pid: Option<libc::pid_t>,
+    /// `$0`.
+    pub shell_name: String,
+    /// `$1`, `$2` and so on.
+    pub positional_parameters: Vec<String>,
+    pub functions: HashMap<String, Rc<CommandNode>>,
+    /// Defined with `alias`, and replaced when a command is read.
+    pub aliases: HashMap<String, String>,
+    /// Set by `break`, `continue` and `return` to unwind to the enclosing
+    /// loop or function.
+    pub control_flow: Option<ControlFlow>,
+    /// How many loops are running, which limits `break` and `continue`.
+    pub loop_depth: usize,
+    /// How many function calls are running, which `return` can leave.
+    pub call_depth: usize,
+    /// Commands set with `trap`, by signal number with 0 for `EXIT`. An
+    /// empty command means the signal is ignored.
+    pub traps: HashMap<libc::c_int, String>,
+    /// Set when Ctrl-C interrupts commands the shell is running itself, so
+    /// that they are abandoned and the prompt comes back.
+    pub interrupted: bool,
+    /// The directories saved by `pushd`, most recent first, not including
+    /// the working directory.
+    pub directory_stack: Vec<String>,
+    /// Where external commands were found, emptied when `PATH` changes.
+    pub command_hash: CommandHash,
+    variables: HashMap<String, Variable>,
+    /// The directories in `PATH`, split again whenever it changes.
+    path: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ControlFlow {
+    Break(usize),
+    Continue(usize),
+    Return,
+}
+
+impl ShellState {
+    pub fn new(interactive: bool) -> Result<Self> {
+        let variables: HashMap<_, _> = std::env::vars()
+            .map(|(name, value)| {
+                let exported = true;
+                (name, Variable { value, exported })
+            })
+            .collect();
+
+        let history = if interactive {
+            let file = history_file(&variables);
+            History::load(file, history_size(&variables))
+        } else {
+            History::default()
+        };
+
+        let path = variables
+            .get("PATH")
+            .map(|variable| split_path(&variable.value))
+            .unwrap_or_default();
+
+        let mut state = Self {
+            last_exit_status: 0,
+            should_exit: false,
+            history,
+            jobs: JobTable::new(interactive),
+            last_background_pid: None,
+            shell_name: std::env::args()
+                .next()
+                .unwrap_or_else(|| "shell".to_owned()),
+            positional_parameters: vec![],
+            functions: HashMap::new(),
+            aliases: HashMap::new(),
+            control_flow: None,
+            loop_depth: 0,
+            call_depth: 0,
+            traps: HashMap::new(),
+            interrupted: false,
+            directory_stack: vec![],
+            command_hash: CommandHash::default(),
+            variables,
+            path,
+        };
+
+        // an inherited `PWD` is only kept if it leads to where the shell is
+        let pwd = current_directory(&state).to_string_lossy().into_owned();
+        state.export_variable("PWD", Some(pwd));
+
+        Ok(state)
+    }
+
+    pub fn get_variable(&self, name: &str) -> Option<String> {
+        match name {
+            "?" => Some(self.last_exit_status.to_string()),
+            "$" => Some(std::process::id().to_string()),
+            "!" => self.last_background_pid.map(|pid| pid.to_string()),
+            "#" => Some(self.positional_parameters.len().to_string()),
+            "@" | "*" => Some(self.positional_parameters.join(" ")),
+            _ if name.chars().all(|character| character.is_ascii_digit()) => {
+                match name.parse::<usize>().ok()? {
+                    // `${00}` is `$0` too
+                    0 => Some(self.shell_name.clone()),
+                    index => self.positional_parameters.get(index - 1).cloned(),
+                }
+            }
+            _ => self
+                .variables
+                .get(name)
+                .map(|variable| variable.value.clone()),
+        }
+    }
+
+    /// The directories searched for external commands.
+    pub fn path(&self) -> &[PathBuf] {
+        &self.path
+    }
+
+    /// Finds the executable to run for the command `name`, using and filling
+    /// in the command hash.
+    pub fn find_command(&mut self, name: &str) -> Result<PathBuf, CustomError> {
+        self.command_hash.find(name, &self.path)
+    }
+
+    /// Whether the commands being run should stop early, because of `exit`,
+    /// `break`, `continue`, `return` or Ctrl-C.
+    pub fn should_stop(&self) -> bool {
+        self.should_exit || self.control_flow.is_some() || self.interrupted
+    }
+
+    /// Sets a variable, keeping it exported if it already was.
+    pub fn set_variable(&mut self, name: &str, value: String) {
+        self.variables
+            .entry(name.to_owned())
+            .and_modify(|variable| variable.value.clone_from(&value))
+            .or_insert(Variable {
+                value,
+                exported: false,
+            });
+        self.variable_changed(name);
+    }
+
+    /// Marks a variable as exported, creating it with an empty value if
+    /// `value` is `None` and it doesn't exist yet.
+    pub fn export_variable(&mut self, name: &str, value: Option<String>) {
+        let variable = self
+            .variables
+            .entry(name.to_owned())
+            .or_insert(Variable {
+                value: String::new(),
+                exported: true,
+            });
+
+        variable.exported = true;
+        if let Some(value) = value {
+            variable.value = value;
+        }
+        self.variable_changed(name);
+    }
+
+    pub fn unset_variable(&mut self, name: &str) {
+        self.variables.remove(name);
+        self.variable_changed(name);
+    }
+
+    /// Applies `NAME=value` prefixes for the duration of a single command,
+    /// returning what is needed to put the previous values back.
+    pub fn push_temporary_variables(
+        &mut self,
+        assignments: Vec<(String, String)>,
+    ) -> Vec<(String, Option<Variable>)> {
+        assignments
+            .into_iter()
+            .map(|(name, value)| {
+                let previous = self.variables.get(&name).cloned();
+                self.export_variable(&name, Some(value));
+                (name, previous)
+            })
+            .collect()
+    }
+
+    pub fn pop_temporary_variables(&mut self, saved: Vec<(String, Option<Variable>)>) {
+        for (name, previous) in saved.into_iter().rev() {
+            match previous {
+                Some(variable) => self.variables.insert(name.clone(), variable),
+                None => self.variables.remove(&name),
+            };
+            self.variable_changed(&name);
+        }
+    }
+
+    /// Keeps the path and the command hash in step with `PATH`, and the
+    /// history's size with `HISTSIZE`.
+    fn variable_changed(&mut self, name: &str) {
+        if name == "HISTSIZE" {
+            self.history.set_size(history_size(&self.variables));
+        }
+        if name == "PATH" {
+            self.path = self
+                .variables
+                .get("PATH")
+                .map(|variable| split_path(&variable.value))
+                .unwrap_or_default();
+            self.command_hash.clear();
+        }
+    }
+
+    /// The exported variables, sorted by name, as passed to external commands.
+    pub fn environment(&self) -> Vec<(String, String)> {
+        let mut environment: Vec<_> = self
+            .variables
+            .iter()
+            .filter(|(_, variable)| variable.exported)
+            .map(|(name, variable)| (name.clone(), variable.value.clone()))
+            .collect();
+
+        environment.sort();
+        environment
+    }
+}
+
+/// `$HISTSIZE` if it is a number, otherwise `DEFAULT_SIZE`.
+fn history_size(variables: &HashMap<String, Variable>) -> usize {
+    variables
+        .get("HISTSIZE")
+        .and_then(|variable| variable.value.parse().ok())
+        .unwrap_or(DEFAULT_SIZE)
+}
+
+/// `$HISTFILE` if set, otherwise `~/.shell_history`.
+fn history_file(variables: &HashMap<String, Variable>) -> Option<PathBuf> {
+    match variables.get("HISTFILE") {
+        Some(variable) if !variable.value.is_empty() => Some(PathBuf::from(&variable.value)),
+        _ => std::env::home_dir().map(|home| home.join(".shell_history")),
+    }
+}
@@ -0,0 +1,59 @@
+use anyhow::Result;
+
+use crate::{input::Input, process::take_pending_signals, shell_state::ShellState};
+
+/// The condition number `trap` uses for the shell exiting.
+pub const EXIT: libc::c_int = 0;
+
+/// Runs the traps for signals caught since the last call. An untrapped
+/// SIGINT can only have been caught by an interactive shell, and interrupts
+/// whatever it is running.
+pub fn run_pending_traps(state: &mut ShellState) -> Result<()> {
+    for signal in take_pending_signals() {
+        match state.traps.get(&signal).cloned() {
+            Some(command) => run_trap(&command, state)?,
+            None if signal == libc::SIGINT => state.interrupted = true,
+            None => (),
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the `EXIT` trap, if there is one, as the shell finishes.
+pub fn run_exit_trap(state: &mut ShellState) -> Result<()> {
+    match state.traps.remove(&EXIT) {
+        Some(command) => run_trap(&command, state),
+        None => Ok(()),
+    }
+}
+
+/// Runs a trap's command without disturbing `$?`, unless it calls `exit`.
+fn run_trap(command: &str, state: &mut ShellState) -> Result<()> {
+    let status = state.last_exit_status;
+    let should_exit = std::mem::take(&mut state.should_exit);
+
+    Input::from_text(command.to_owned()).run(state)?;
+
+    if !state.should_exit {
+        state.last_exit_status = status;
+    }
+    state.should_exit |= should_exit;
+
+    Ok(())
+}
+
+/// Runs `body` in a freshly forked child, which doesn't keep the traps it
+/// inherited apart from ignored signals, and then the child's own `EXIT`
+/// trap. Returns the status to exit with.
+pub fn run_subshell(
+    state: &mut ShellState,
+    body: impl FnOnce(&mut ShellState) -> Result<i32>,
+) -> Result<i32> {
+    state.traps.retain(|_, command| command.is_empty());
+
+    state.last_exit_status = body(state)?;
+    run_exit_trap(state)?;
+
+    Ok(state.last_exit_status)
+}
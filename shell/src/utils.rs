@@ -1,126 +1,60 @@
-use crate::command::Command;
-use anyhow::{Context, Result, bail};
 pub use std::process::exit;
 use std::{
-    env::{self, split_paths},
-    fs::DirEntry,
-    io::{self, Write, stdin},
+    env::split_paths,
     os::unix::fs::MetadataExt,
     path::{Path, PathBuf},
 };
 
-pub fn get_user_input() -> Result<String> {
-    let mut user_input = String::new();
-    stdin()
-        .read_line(&mut user_input)
-        .context("reading user input")?;
-    Ok(user_input.trim().to_owned())
-}
-
-pub fn print_prompt() {
-    print!("$ ");
-    io::stdout().flush().unwrap();
-}
-
-pub fn get_command(standard_out: &mut Vec<String>) -> Result<Command> {
-    let user_input = get_user_input()?;
-    let command = Command::new(user_input, standard_out)?;
-
-    Ok(command)
-}
-
-pub fn get_path() -> Result<Vec<PathBuf>> {
-    let path = env::var("PATH").context("Getting PATH environment variable")?;
-    let split_paths = split_paths(&path).map(|path| {
-        if path.is_file() {
-            bail!("PATH from environment variable is an file")
-        } else {
-            Ok(path)
-        }
-    });
-
-    split_paths.collect()
-}
-
-pub fn find_files(name: &str, paths: &[PathBuf]) -> Vec<DirEntry> {
-    paths
-        .iter()
-        .filter_map(|path| {
-            let Ok(directory) = std::fs::read_dir(path) else {
-                return None;
-            };
-
-            for dir_entry in directory {
-                let Ok(dir_entry) = dir_entry else {
-                    continue;
-                };
-                let file_name = dir_entry.file_name();
-
-                if name == file_name {
-                    return Some(dir_entry);
-                } else {
-                    continue;
-                }
+/// The directories in a `PATH` value, with empty entries meaning the current
+/// directory.
+pub fn split_path(path: &str) -> Vec<PathBuf> {
+    split_paths(path)
+        .map(|directory| {
+            if directory.as_os_str().is_empty() {
+                PathBuf::from(".")
+            } else {
+                directory
             }
-
-            None
         })
         .collect()
 }
 
-pub fn find_executable_file(name: &str, paths: &[PathBuf]) -> Option<DirEntry> {
-    let dir_entries = find_files(name, paths);
-
-    for dir_entry in dir_entries {
-        let metadata = dir_entry.metadata().ok()?;
-        let mode = metadata.mode();
-        let user_exec = mode & 0o100 != 0;
-        let group_exec = mode & 0o010 != 0;
-        let other_exec = mode & 0o001 != 0;
-
-        if user_exec || group_exec || other_exec {
-            return Some(dir_entry);
-        }
-    }
-
-    None
+/// The first file called `name` in the directories of `paths`.
+pub fn find_file(name: &str, paths: &[PathBuf]) -> Option<PathBuf> {
+    paths
+        .iter()
+        .map(|directory| directory.join(name))
+        .find(|path| path.is_file())
 }
 
-pub fn write_all_to_file(messages: &[String], filename: &str) -> Result<()> {
-    let file_path = Path::new(filename);
-    let mut file = std::fs::File::options()
-        .write(true)
-        .truncate(true)
-        .create(true)
-        .open(file_path)?;
+/// Every executable called `name` in the directories of `paths`, in order.
+/// A name with a slash is a path to the file rather than a name to look up.
+pub fn find_executable_files(name: &str, paths: &[PathBuf]) -> Vec<PathBuf> {
+    if name.contains('/') {
+        return find_executable_file(name, paths).into_iter().collect();
+    }
 
-    messages
+    paths
         .iter()
-        .try_for_each(|message| file.write_all(message.as_bytes()))?;
-
-    Ok(())
+        .map(|directory| directory.join(name))
+        .filter(|path| is_executable(path))
+        .collect()
 }
 
-pub fn append_all_to_file(messages: &[String], filename: &str) -> Result<()> {
-    let file_path = Path::new(filename);
-    let mut file = std::fs::File::options()
-        .create(true)
-        .append(true)
-        .open(file_path)?;
-
-    if let Ok(metadata) = file.metadata() {
-        if metadata.len() > 0 {
-            file.write(b"\n")
-                .context("writing new line to appended file")?;
-        }
-    } else {
-        bail!("Cannot read open file for appending");
+pub fn find_executable_file(name: &str, paths: &[PathBuf]) -> Option<PathBuf> {
+    if name.contains('/') {
+        let path = PathBuf::from(name);
+        return is_executable(&path).then_some(path);
     }
 
-    messages
+    paths
         .iter()
-        .map(|message| message.trim())
-        .try_for_each(|message| file.write_all(message.as_bytes()))?;
+        .map(|directory| directory.join(name))
+        .find(|path| is_executable(path))
+}
 
-    Ok(())
+/// Whether `path` is a file with any execute permission bit set.
+pub fn is_executable(path: &Path) -> bool {
+    path.metadata()
+        .is_ok_and(|metadata| metadata.is_file() && metadata.mode() & 0o111 != 0)
 }
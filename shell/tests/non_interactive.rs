@@ -0,0 +1,635 @@
+//! Runs the shell binary on `-c` strings, script files and piped input and
+//! checks what it writes and the status it exits with.
+
+use std::{
+    fs,
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+const SHELL: &str = env!("CARGO_BIN_EXE_shell");
+
+struct Output {
+    status: i32,
+    stdout: String,
+    stderr: String,
+}
+
+/// Runs the shell with `arguments` in `directory`, feeding it `stdin`.
+fn run_in(directory: &Path, arguments: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(SHELL)
+        .args(arguments)
+        .current_dir(directory)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    Output {
+        status: output.status.code().unwrap_or(-1),
+        stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+        stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+    }
+}
+
+fn run_command(command: &str) -> Output {
+    let directory = TemporaryDirectory::new();
+    run_in(&directory.0, &["-c", command], "")
+}
+
+/// The standard output of a `-c` command that should succeed.
+fn stdout_of(command: &str) -> String {
+    let output = run_command(command);
+    assert_eq!(output.status, 0, "{command:?}: {}", output.stderr);
+    output.stdout
+}
+
+/// A directory for a test's files, removed once the test is done.
+struct TemporaryDirectory(PathBuf);
+
+impl TemporaryDirectory {
+    fn new() -> Self {
+        static COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        let path = std::env::temp_dir().join(format!(
+            "shell-test-{}-{}",
+            std::process::id(),
+            COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path).unwrap();
+
+        Self(path)
+    }
+
+    fn write(&self, name: &str, contents: &str, executable: bool) -> PathBuf {
+        let path = self.0.join(name);
+        fs::write(&path, contents).unwrap();
+
+        let mode = if executable { 0o755 } else { 0o644 };
+        fs::set_permissions(&path, fs::Permissions::from_mode(mode)).unwrap();
+
+        path
+    }
+
+    fn read(&self, name: &str) -> String {
+        fs::read_to_string(self.0.join(name)).unwrap()
+    }
+}
+
+impl Drop for TemporaryDirectory {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn command_option() {
+    assert_eq!(stdout_of("echo hello; echo world"), "hello\nworld\n");
+
+    let directory = TemporaryDirectory::new();
+    let output = run_in(&directory.0, &["-c", "echo $0 $1 $#", "name", "a", "b"], "");
+    assert_eq!(output.stdout, "name a 2\n");
+
+    let output = run_in(&directory.0, &["-c"], "");
+    assert_eq!(output.status, 2);
+    assert!(output.stderr.contains("option requires an argument"));
+}
+
+#[test]
+fn exit_statuses() {
+    assert_eq!(run_command("exit 3").status, 3);
+    assert_eq!(run_command("false").status, 1);
+    assert_eq!(run_command("false; true").status, 0);
+    assert_eq!(stdout_of("false; echo $?; true; echo $?"), "1\n0\n");
+    assert_eq!(stdout_of("sh -c 'exit 42'; echo $?"), "42\n");
+}
+
+#[test]
+fn command_not_found() {
+    let output = run_command("no-such-command-for-the-test; echo $?");
+    assert_eq!(output.stdout, "127\n");
+    assert!(
+        output
+            .stderr
+            .contains("no-such-command-for-the-test: command not found"),
+        "{}",
+        output.stderr
+    );
+
+    assert_eq!(run_command("./missing").status, 127);
+    assert_eq!(run_command("no-such-command-for-the-test").status, 127);
+}
+
+#[test]
+fn command_not_executable() {
+    let directory = TemporaryDirectory::new();
+    directory.write("plain", "echo never\n", false);
+    fs::create_dir(directory.0.join("subdirectory")).unwrap();
+
+    let output = run_in(&directory.0, &["-c", "./plain"], "");
+    assert_eq!(output.status, 126);
+    assert!(
+        output.stderr.contains("Permission denied"),
+        "{}",
+        output.stderr
+    );
+    assert_eq!(output.stdout, "");
+
+    let output = run_in(&directory.0, &["-c", "./subdirectory"], "");
+    assert_eq!(output.status, 126);
+    assert!(
+        output.stderr.contains("Is a directory"),
+        "{}",
+        output.stderr
+    );
+}
+
+#[test]
+fn pipelines() {
+    assert_eq!(stdout_of("printf 'c\\nb\\na\\n' | sort | head -n 1"), "a\n");
+    assert_eq!(stdout_of("echo hello | tr a-z A-Z"), "HELLO\n");
+    assert_eq!(stdout_of("type echo | cat"), "echo is a shell builtin\n");
+
+    assert_eq!(run_command("true | false").status, 1);
+    assert_eq!(run_command("false | true").status, 0);
+    assert_eq!(stdout_of("! true; echo $?; ! false; echo $?"), "1\n0\n");
+}
+
+#[test]
+fn and_or_lists() {
+    assert_eq!(stdout_of("true && echo a || echo b"), "a\n");
+    assert_eq!(stdout_of("false && echo a || echo b"), "b\n");
+    assert_eq!(stdout_of("false || false || echo c"), "c\n");
+    assert_eq!(run_command("true && false").status, 1);
+}
+
+#[test]
+fn groups_and_subshells() {
+    assert_eq!(stdout_of("x=1; { x=2; }; echo $x"), "2\n");
+    assert_eq!(stdout_of("x=1; (x=2; cd /); echo $x"), "1\n");
+    assert_eq!(stdout_of("(exit 4); echo $?"), "4\n");
+}
+
+#[test]
+fn redirections() {
+    let directory = TemporaryDirectory::new();
+    let run = |command: &str| run_in(&directory.0, &["-c", command], "");
+
+    let output = run("echo a > file; echo b >> file; cat < file");
+    assert_eq!(output.stdout, "a\nb\n");
+
+    run("echo hi>file");
+    assert_eq!(directory.read("file"), "hi\n");
+
+    let output = run("{ echo out; echo err >&2; } > both 2>&1");
+    assert_eq!(output.stdout, "");
+    assert_eq!(directory.read("both"), "out\nerr\n");
+
+    let output = run("echo err 2>&1 >&2 >/dev/null");
+    assert_eq!(output.stdout, "");
+
+    run("ls no-such-file 2> errors");
+    assert!(!directory.read("errors").is_empty());
+
+    run("echo all &> all; echo more &>> all");
+    assert_eq!(directory.read("all"), "all\nmore\n");
+
+    let output = run("cat < missing; echo $?");
+    assert_eq!(output.stdout, "1\n");
+    assert!(output.stderr.contains("missing: No such file or directory"));
+}
+
+#[test]
+fn redirections_restore_descriptors() {
+    let directory = TemporaryDirectory::new();
+
+    let output = run_in(
+        &directory.0,
+        &["-c", "true 3>x; echo b >&3; echo $?; echo after"],
+        "",
+    );
+    assert_eq!(output.stdout, "1\nafter\n");
+    assert!(output.stderr.contains("3: Bad file descriptor"));
+    assert_eq!(directory.read("x"), "");
+
+    let output = run_in(&directory.0, &["-c", "echo a > out; echo b"], "");
+    assert_eq!(output.stdout, "b\n");
+}
+
+#[test]
+fn builtins_report_write_errors() {
+    for command in ["echo a >&-", "printf x 1>&-", "pwd >&-"] {
+        let output = run_command(command);
+        assert_eq!(output.status, 1, "{command}");
+        assert!(
+            output.stderr.contains("write error: Bad file descriptor"),
+            "{command}: {}",
+            output.stderr
+        );
+    }
+}
+
+#[test]
+fn here_documents() {
+    assert_eq!(
+        stdout_of("x=value\ncat <<EOF\n$x\nEOF\ncat <<'EOF'\n$x\nEOF"),
+        "value\n$x\n"
+    );
+    assert_eq!(stdout_of("cat <<-EOF\n\tindented\n\tEOF"), "indented\n");
+    assert_eq!(stdout_of("tr a-z A-Z <<< word"), "WORD\n");
+}
+
+#[test]
+fn filename_and_brace_expansion() {
+    let directory = TemporaryDirectory::new();
+    directory.write("a.txt", "", false);
+    directory.write("b.txt", "", false);
+
+    let output = run_in(
+        &directory.0,
+        &["-c", "echo *.txt '*.txt' \\*.txt *.none x{1,2}y {a..c}"],
+        "",
+    );
+    assert_eq!(
+        output.stdout,
+        "a.txt b.txt *.txt *.txt *.none x1y x2y a b c\n"
+    );
+}
+
+#[test]
+fn control_flow() {
+    let script = "
+        for x in 1 2 3 4 5; do
+            if [ $x = 2 ]; then
+                continue
+            elif [ $x = 4 ]; then
+                break
+            else
+                echo $x
+            fi
+        done
+        i=0
+        while [ $i -lt 3 ]; do
+            i=$((i + 1))
+        done
+        until true; do echo never; done
+        echo i=$i
+        case $i in
+            1 | 2) echo small ;;
+            3) echo three ;;
+            *) echo other ;;
+        esac
+    ";
+
+    assert_eq!(stdout_of(script), "1\n3\ni=3\nthree\n");
+}
+
+#[test]
+fn functions() {
+    let script = "
+        greet() {
+            echo \"hello $1 ($#)\"
+            return 5
+        }
+        greet world again
+        echo $?
+        echo $1
+    ";
+    let directory = TemporaryDirectory::new();
+    let output = run_in(&directory.0, &["-c", script, "shell", "outer"], "");
+
+    assert_eq!(output.stdout, "hello world (2)\n5\nouter\n");
+}
+
+#[test]
+fn positional_parameters() {
+    let directory = TemporaryDirectory::new();
+    let command = "echo ${0} ${00} $1 ${2} ${10} ${01}";
+    let arguments = [
+        "-c", command, "name", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j",
+    ];
+    let output = run_in(&directory.0, &arguments, "");
+
+    assert_eq!(output.status, 0, "{}", output.stderr);
+    assert_eq!(output.stdout, "name name a b j a\n");
+}
+
+#[test]
+fn script_files() {
+    let directory = TemporaryDirectory::new();
+    directory.write(
+        "script.sh",
+        "# a comment\necho $0 $# \"$@\"\nexit 7\necho never\n",
+        false,
+    );
+
+    let output = run_in(&directory.0, &["script.sh", "a", "b c"], "");
+    assert_eq!(output.status, 7);
+    assert_eq!(output.stdout, "script.sh 2 a b c\n");
+
+    let output = run_in(&directory.0, &["missing.sh"], "");
+    assert_eq!(output.status, 127);
+    assert!(
+        output
+            .stderr
+            .contains("missing.sh: No such file or directory")
+    );
+}
+
+#[test]
+fn scripts_stop_at_syntax_errors() {
+    let directory = TemporaryDirectory::new();
+    directory.write("script.sh", "echo before\nif then\necho after\n", false);
+
+    let output = run_in(&directory.0, &["script.sh"], "");
+    assert_eq!(output.status, 2);
+    assert_eq!(output.stdout, "before\n");
+    assert!(
+        output
+            .stderr
+            .contains("syntax error near unexpected token `then'")
+    );
+
+    assert_eq!(run_command("echo 'unterminated").status, 2);
+}
+
+#[test]
+fn shebang_scripts() {
+    let directory = TemporaryDirectory::new();
+    let script = directory.write(
+        "script",
+        &format!("#!{SHELL}\necho from shebang $1\n"),
+        true,
+    );
+
+    let output = Command::new(&script).arg("argument").output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "from shebang argument\n"
+    );
+}
+
+#[test]
+fn piped_standard_input() {
+    let directory = TemporaryDirectory::new();
+    let output = run_in(&directory.0, &[], "echo one\nfalse\necho $?\n");
+
+    assert_eq!(output.status, 0);
+    assert_eq!(output.stdout, "one\n1\n");
+    assert_eq!(output.stderr, "");
+}
+
+#[test]
+fn source() {
+    let directory = TemporaryDirectory::new();
+    directory.write(
+        "library.sh",
+        "helper() { echo helped $1; }\nvalue=set\n",
+        false,
+    );
+
+    let output = run_in(
+        &directory.0,
+        &[
+            "-c",
+            ". ./library.sh; helper me; source ./library.sh; echo $value",
+        ],
+        "",
+    );
+    assert_eq!(output.stdout, "helped me\nset\n");
+}
+
+#[test]
+fn traps() {
+    assert_eq!(stdout_of("trap \"echo bye\" EXIT; true"), "bye\n");
+
+    let output = run_command("trap 'echo bye $?' EXIT; echo before; exit 3; echo never");
+    assert_eq!(output.status, 3);
+    assert_eq!(output.stdout, "before\nbye 3\n");
+
+    assert_eq!(
+        stdout_of("trap 'echo caught' USR1; kill -USR1 $$; echo after"),
+        "caught\nafter\n"
+    );
+    assert_eq!(
+        stdout_of("trap 'echo never' EXIT; trap - EXIT; trap -p"),
+        ""
+    );
+    assert_eq!(
+        stdout_of("trap 'echo x' INT TERM; trap -p"),
+        "trap -- 'echo x' SIGINT\ntrap -- 'echo x' SIGTERM\n"
+    );
+}
+
+#[test]
+fn changing_path_forgets_hashed_commands() {
+    let directory = TemporaryDirectory::new();
+    for name in ["first", "second"] {
+        fs::create_dir(directory.0.join(name)).unwrap();
+        directory.write(
+            &format!("{name}/tool"),
+            &format!("#!/bin/sh\necho {name}\n"),
+            true,
+        );
+    }
+
+    let root = directory.0.display();
+    let command = format!("PATH={root}/first; tool; hash; PATH={root}/second; tool; hash -r; hash");
+    let output = run_in(&directory.0, &["-c", &command], "");
+    assert_eq!(output.status, 0, "{}", output.stderr);
+    assert_eq!(
+        output.stdout,
+        format!("first\nhits\tcommand\n   1\t{root}/first/tool\nsecond\n")
+    );
+    assert_eq!(output.stderr, "hash: hash table empty\n");
+}
+
+#[test]
+fn error_statuses_and_messages() {
+    let directory = TemporaryDirectory::new();
+    directory.write("plain", "echo never\n", false);
+    let run = |command: &str| run_in(&directory.0, &["-c", command], "");
+
+    let output = run("./plain; echo $?; no-such-command-for-the-test; echo $?");
+    assert_eq!(output.stdout, "126\n127\n");
+    assert_eq!(
+        output.stderr,
+        "shell: ./plain: Permission denied\n\
+         shell: no-such-command-for-the-test: command not found\n"
+    );
+
+    let output = run("sh -c 'kill -TERM $$'; echo $?; x=$(sh -c 'kill -KILL $$'); echo $?");
+    assert_eq!(output.stdout, "143\n137\n");
+
+    let output = run("cat < missing; echo $?");
+    assert_eq!(output.stdout, "1\n");
+    assert_eq!(output.stderr, "shell: missing: No such file or directory\n");
+
+    let output = run("echo before; fi");
+    assert_eq!(output.status, 2);
+    assert_eq!(output.stdout, "");
+    assert_eq!(
+        output.stderr,
+        "shell: syntax error near unexpected token `fi'\n"
+    );
+}
+
+#[test]
+fn cd_updates_pwd_and_oldpwd() {
+    let directory = TemporaryDirectory::new();
+    let root = fs::canonicalize(&directory.0).unwrap();
+    let root = root.display();
+    fs::create_dir(directory.0.join("a")).unwrap();
+    fs::create_dir(directory.0.join("b")).unwrap();
+
+    let command = "cd a; echo $PWD $OLDPWD; cd ../b; cd -; echo $PWD $OLDPWD; pwd";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(
+        output.stdout,
+        format!("{root}/a {root}\n{root}/a\n{root}/a {root}/b\n{root}/a\n")
+    );
+
+    let output = run_in(&directory.0, &["-c", "unset OLDPWD; cd -"], "");
+    assert_eq!(output.status, 1);
+    assert!(output.stderr.contains("cd: OLDPWD not set"));
+
+    let output = run_in(&directory.0, &["-c", "cd missing; echo $?; pwd"], "");
+    assert_eq!(output.stdout, format!("1\n{root}\n"));
+    assert!(
+        output
+            .stderr
+            .contains("cd: missing: No such file or directory")
+    );
+}
+
+#[test]
+fn cd_searches_cdpath() {
+    let directory = TemporaryDirectory::new();
+    let root = fs::canonicalize(&directory.0).unwrap();
+    let root = root.display();
+    fs::create_dir_all(directory.0.join("projects/app")).unwrap();
+    fs::create_dir(directory.0.join("local")).unwrap();
+
+    // a directory found through a CDPATH entry is printed
+    let output = run_in(
+        &directory.0,
+        &["-c", "CDPATH=$PWD/projects; cd app; pwd"],
+        "",
+    );
+    assert_eq!(
+        output.stdout,
+        format!("{root}/projects/app\n{root}/projects/app\n")
+    );
+
+    // but not one found through an empty entry, meaning the working directory
+    let command = "CDPATH=:$PWD/projects; cd local; pwd";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(output.stdout, format!("{root}/local\n"));
+
+    // and paths starting with `.` don't use CDPATH at all
+    let command = "CDPATH=$PWD/projects; cd ./app";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(output.status, 1);
+    assert!(
+        output
+            .stderr
+            .contains("cd: ./app: No such file or directory")
+    );
+}
+
+#[test]
+fn cd_logical_and_physical() {
+    let directory = TemporaryDirectory::new();
+    let root = fs::canonicalize(&directory.0).unwrap();
+    let root = root.display();
+    fs::create_dir_all(directory.0.join("real/inner")).unwrap();
+    std::os::unix::fs::symlink("real", directory.0.join("link")).unwrap();
+
+    let command = "cd link/inner; echo $PWD; cd ..; echo $PWD; pwd -P; cd -P ../link; echo $PWD";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(
+        output.stdout,
+        format!("{root}/link/inner\n{root}/link\n{root}/real\n{root}/real\n")
+    );
+
+    let command = "cd -P link/inner; cd -L ..; echo $PWD";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(output.stdout, format!("{root}/real\n"));
+}
+
+#[test]
+fn directory_stack() {
+    let directory = TemporaryDirectory::new();
+    let root = fs::canonicalize(&directory.0).unwrap();
+    let root = root.display();
+    for name in ["a", "b", "c"] {
+        fs::create_dir(directory.0.join(name)).unwrap();
+    }
+
+    let command = "
+        pushd a; pushd ../b; pushd ../c
+        pushd +1; pushd -0
+        popd +1; popd -0
+        dirs -v; pwd
+        popd; pwd
+    ";
+    let output = run_in(&directory.0, &["-c", command], "");
+    assert_eq!(output.status, 0, "{}", output.stderr);
+    let expected = [
+        format!("{root}/a {root}"),
+        format!("{root}/b {root}/a {root}"),
+        format!("{root}/c {root}/b {root}/a {root}"),
+        format!("{root}/b {root}/a {root} {root}/c"),
+        format!("{root}/c {root}/b {root}/a {root}"),
+        format!("{root}/c {root}/a {root}"),
+        format!("{root}/c {root}/a"),
+        format!(" 0  {root}/c"),
+        format!(" 1  {root}/a"),
+        format!("{root}/c"),
+        format!("{root}/a"),
+        format!("{root}/a"),
+    ];
+    assert_eq!(output.stdout, expected.join("\n") + "\n");
+}
+
+#[test]
+fn directory_stack_errors() {
+    for (command, message) in [
+        ("popd", "popd: directory stack empty"),
+        ("pushd", "pushd: no other directory"),
+        (
+            "pushd /; pushd +2",
+            "pushd: +2: directory stack index out of range",
+        ),
+        (
+            "pushd /; popd -2",
+            "popd: -2: directory stack index out of range",
+        ),
+        ("dirs +1", "dirs: +1: directory stack index out of range"),
+        (
+            "pushd /no-such-directory",
+            "pushd: /no-such-directory: No such file",
+        ),
+    ] {
+        let output = run_command(command);
+        assert_eq!(output.status, 1, "{command}");
+        assert!(
+            output.stderr.contains(message),
+            "{command}: {}",
+            output.stderr
+        );
+    }
+}